@@ -0,0 +1,12 @@
+-- Content fingerprints for deduplicating documents
+ALTER TABLE documents
+    ADD COLUMN IF NOT EXISTS content_hash TEXT,
+    ADD COLUMN IF NOT EXISTS simhash BIGINT;
+
+CREATE INDEX IF NOT EXISTS idx_documents_content_hash ON documents(content_hash);
+
+-- SimHash bands; two fingerprints within 3 bits share at least one band
+CREATE INDEX IF NOT EXISTS idx_documents_simhash_b0 ON documents ((simhash & 65535));
+CREATE INDEX IF NOT EXISTS idx_documents_simhash_b1 ON documents (((simhash >> 16) & 65535));
+CREATE INDEX IF NOT EXISTS idx_documents_simhash_b2 ON documents (((simhash >> 32) & 65535));
+CREATE INDEX IF NOT EXISTS idx_documents_simhash_b3 ON documents (((simhash >> 48) & 65535));
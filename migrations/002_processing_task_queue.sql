@@ -0,0 +1,14 @@
+-- Processing tasks as a persistent job queue: the upload to process, retries
+-- with backoff, batches and per-stage timings
+ALTER TABLE processing_tasks
+    ADD COLUMN IF NOT EXISTS batch_id TEXT,
+    ADD COLUMN IF NOT EXISTS payload JSONB,
+    ADD COLUMN IF NOT EXISTS result JSONB,
+    ADD COLUMN IF NOT EXISTS attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS max_attempts INTEGER NOT NULL DEFAULT 5,
+    ADD COLUMN IF NOT EXISTS run_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    ADD COLUMN IF NOT EXISTS locked_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS stages JSONB NOT NULL DEFAULT '[]';
+
+CREATE INDEX IF NOT EXISTS idx_processing_tasks_claim ON processing_tasks(run_at) WHERE status IN ('pending', 'retrying');
+CREATE INDEX IF NOT EXISTS idx_processing_tasks_batch ON processing_tasks(batch_id);
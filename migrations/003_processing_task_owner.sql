@@ -0,0 +1,2 @@
+-- Worker holding a processing task's lock; only it may complete or fail the task
+ALTER TABLE processing_tasks ADD COLUMN locked_by TEXT;
@@ -0,0 +1,2 @@
+-- Raw bytes of file uploads, kept out of the JSON payload
+ALTER TABLE processing_tasks ADD COLUMN content BYTEA;
@@ -0,0 +1,113 @@
+-- Full-text search in each document's detected language, replacing the
+-- English-only search_config indexes
+DROP INDEX IF EXISTS documents_content_search_idx;
+DROP INDEX IF EXISTS documents_title_search_idx;
+DROP FUNCTION IF EXISTS search_documents(TEXT, INTEGER, INTEGER);
+
+ALTER TABLE documents
+    -- Text search configuration of the document's detected language
+    ADD COLUMN text_config REGCONFIG NOT NULL DEFAULT 'simple',
+    -- Title and content with CJK runs split into bigrams; NULL without CJK text
+    ADD COLUMN segmented_title TEXT,
+    ADD COLUMN segmented_content TEXT;
+
+ALTER TABLE documents ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector(text_config, COALESCE(segmented_title, title)), 'A')
+    || setweight(to_tsvector(text_config, COALESCE(segmented_content, content)), 'B')
+) STORED;
+
+CREATE INDEX documents_search_idx ON documents USING GIN (search_vector);
+
+-- A query analyzed in one language, or in every supported language when
+-- none is given, so it matches documents stemmed in their own language.
+-- CJK queries must be segmented into bigrams like the documents are.
+CREATE OR REPLACE FUNCTION search_tsquery(
+    query_text TEXT,
+    config REGCONFIG DEFAULT NULL
+) RETURNS TSQUERY AS $$
+    SELECT CASE
+        WHEN config IS NOT NULL THEN
+            plainto_tsquery(config, query_text) || plainto_tsquery('simple', query_text)
+        ELSE
+            plainto_tsquery('english', query_text) || plainto_tsquery('german', query_text)
+            || plainto_tsquery('french', query_text) || plainto_tsquery('simple', query_text)
+    END
+$$ LANGUAGE sql IMMUTABLE;
+
+CREATE OR REPLACE FUNCTION search_documents(
+    query_text TEXT,
+    limit_val INTEGER DEFAULT 10,
+    offset_val INTEGER DEFAULT 0,
+    query_config REGCONFIG DEFAULT NULL
+) RETURNS TABLE (
+    id UUID,
+    title TEXT,
+    content TEXT,
+    similarity FLOAT,
+    metadata JSONB
+) AS $$
+BEGIN
+    RETURN QUERY
+    SELECT 
+        d.id,
+        d.title,
+        d.content,
+        ts_rank_cd(d.search_vector, search_tsquery(query_text, query_config))::FLOAT AS similarity,
+        d.metadata
+    FROM documents d
+    WHERE d.search_vector @@ search_tsquery(query_text, query_config)
+    ORDER BY similarity DESC
+    LIMIT limit_val
+    OFFSET offset_val;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION hybrid_search_documents(
+    query_text TEXT,
+    query_embedding vector(384),
+    text_weight FLOAT DEFAULT 0.4,
+    vector_weight FLOAT DEFAULT 0.6,
+    limit_val INTEGER DEFAULT 10
+) RETURNS TABLE (
+    id UUID,
+    title TEXT,
+    content TEXT,
+    text_similarity FLOAT,
+    vector_similarity FLOAT,
+    final_score FLOAT,
+    metadata JSONB
+) AS $$
+BEGIN
+    RETURN QUERY
+    WITH text_search AS (
+        SELECT 
+            d.id,
+            d.title,
+            d.content,
+            d.metadata,
+            ts_rank_cd(d.search_vector, search_tsquery(query_text))::FLOAT AS text_sim
+        FROM documents d
+        WHERE d.search_vector @@ search_tsquery(query_text)
+    ),
+    vector_search AS (
+        SELECT 
+            d.id,
+            1 - (d.vector_embedding <=> query_embedding) as vector_sim
+        FROM documents d
+    )
+    SELECT 
+        ts.id,
+        ts.title,
+        ts.content,
+        ts.text_sim as text_similarity,
+        COALESCE(vs.vector_sim, 0) as vector_similarity,
+        (ts.text_sim * text_weight + COALESCE(vs.vector_sim, 0) * vector_weight) as final_score,
+        ts.metadata
+    FROM text_search ts
+    LEFT JOIN vector_search vs ON ts.id = vs.id
+    ORDER BY final_score DESC
+    LIMIT limit_val;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TEXT SEARCH CONFIGURATION IF EXISTS search_config;
@@ -0,0 +1,45 @@
+-- Results shown for each search, for feedback and ranking
+ALTER TABLE search_history
+    ADD COLUMN IF NOT EXISTS result_ids TEXT[] NOT NULL DEFAULT '{}',  -- Documents shown, in order
+    ADD COLUMN IF NOT EXISTS result_features JSONB;                    -- Ranking features of each, for training
+
+CREATE INDEX IF NOT EXISTS idx_search_history_user ON search_history(user_id, timestamp) WHERE user_id IS NOT NULL;
+
+-- Clicks on and ratings of search results. Position is 1-based in the
+-- results shown; dwell time is how long the opened result was viewed.
+CREATE TABLE IF NOT EXISTS search_feedback (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    search_id UUID NOT NULL REFERENCES search_history(id) ON DELETE CASCADE,
+    document_id TEXT NOT NULL,
+    position INTEGER,
+    clicked BOOLEAN NOT NULL DEFAULT FALSE,
+    dwell_time_ms INTEGER,
+    rating SMALLINT CHECK (rating IN (-1, 1)),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_search_feedback_search ON search_feedback(search_id);
+
+-- The application refreshes popular searches periodically instead of after
+-- every search
+DROP TRIGGER IF EXISTS refresh_popular_searches_trigger ON search_history;
+DROP FUNCTION IF EXISTS refresh_popular_searches();
+
+-- Popular searches over the last 90 days, each search counting half as much
+-- per week of age. Only searches that found something are suggested.
+DROP MATERIALIZED VIEW IF EXISTS popular_searches;
+
+CREATE MATERIALIZED VIEW popular_searches AS
+SELECT
+    query,
+    COUNT(*) as search_count,
+    SUM(POWER(0.5, EXTRACT(EPOCH FROM NOW() - timestamp)::float8 / 604800)) as popularity,
+    AVG(execution_time_ms) as avg_execution_time,
+    MAX(timestamp) as last_searched
+FROM search_history
+WHERE timestamp > NOW() - INTERVAL '90 days'
+  AND result_count > 0
+GROUP BY query;
+
+CREATE UNIQUE INDEX popular_searches_query_idx ON popular_searches(query);
+CREATE INDEX popular_searches_popularity_idx ON popular_searches(popularity DESC);
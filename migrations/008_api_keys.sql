@@ -0,0 +1,32 @@
+-- API keys identify the user a request acts for; only their SHA-256 is kept
+CREATE TABLE IF NOT EXISTS api_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    key_hash TEXT NOT NULL UNIQUE,
+    user_id UUID NOT NULL,
+    name TEXT,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_user ON api_keys(user_id);
+
+-- A query only becomes a popular suggestion once several users have searched
+-- it, so one client cannot push suggestions to everyone
+DROP MATERIALIZED VIEW IF EXISTS popular_searches;
+
+CREATE MATERIALIZED VIEW popular_searches AS
+SELECT
+    query,
+    COUNT(*) as search_count,
+    COUNT(DISTINCT user_id) as user_count,
+    SUM(POWER(0.5, EXTRACT(EPOCH FROM NOW() - timestamp)::float8 / 604800)) as popularity,
+    AVG(execution_time_ms) as avg_execution_time,
+    MAX(timestamp) as last_searched
+FROM search_history
+WHERE timestamp > NOW() - INTERVAL '90 days'
+  AND result_count > 0
+GROUP BY query
+HAVING COUNT(DISTINCT user_id) >= 3;
+
+CREATE UNIQUE INDEX popular_searches_query_idx ON popular_searches(query);
+CREATE INDEX popular_searches_popularity_idx ON popular_searches(popularity DESC);
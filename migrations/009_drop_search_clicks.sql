@@ -0,0 +1,2 @@
+-- Clicks are recorded in search_feedback, which click-through is computed from
+DROP TABLE IF EXISTS search_clicks;
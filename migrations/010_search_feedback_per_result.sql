@@ -0,0 +1,28 @@
+-- Keep one feedback row per result of a search, so a later dwell time or
+-- rating updates the click it belongs to instead of adding a row
+WITH merged AS (
+    SELECT
+        search_id,
+        document_id,
+        MIN(position) AS position,
+        bool_or(clicked) AS clicked,
+        MAX(dwell_time_ms) AS dwell_time_ms,
+        (array_agg(rating ORDER BY created_at DESC) FILTER (WHERE rating IS NOT NULL))[1] AS rating,
+        (array_agg(id ORDER BY created_at, id))[1] AS keep
+    FROM search_feedback
+    GROUP BY search_id, document_id
+    HAVING COUNT(*) > 1
+)
+UPDATE search_feedback f
+SET position = m.position, clicked = m.clicked, dwell_time_ms = m.dwell_time_ms, rating = m.rating
+FROM merged m
+WHERE f.id = m.keep;
+
+DELETE FROM search_feedback f
+USING search_feedback earlier
+WHERE f.search_id = earlier.search_id
+  AND f.document_id = earlier.document_id
+  AND (earlier.created_at, earlier.id::text) < (f.created_at, f.id::text);
+
+DROP INDEX IF EXISTS idx_search_feedback_search;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_search_feedback_result ON search_feedback(search_id, document_id);
@@ -0,0 +1,3 @@
+-- Admin keys may also use the management routes: dead letters, webhooks,
+-- synonyms and analytics
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS admin BOOLEAN NOT NULL DEFAULT FALSE;
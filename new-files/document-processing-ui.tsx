@@ -11,22 +11,20 @@ const ProcessingStatus = ({ processingId }) => {
   const [status, setStatus] = useState(null);
 
   useEffect(() => {
-    const checkStatus = async () => {
-      try {
-        const response = await fetch(`/api/documents/status/${processingId}`);
-        const data = await response.json();
+    // The server pushes every status change and closes the stream once the task is finished
+    const events = new EventSource(`/api/documents/status/${processingId}/events`);
+    ['pending', 'processing', 'retrying', 'completed', 'dead'].forEach((name) =>
+      events.addEventListener(name, (event) => {
+        const data = JSON.parse(event.data);
         setStatus(data);
-
-        // Continue polling if not completed or errored
-        if (data.status !== 'completed' && data.status !== 'error') {
-          setTimeout(checkStatus, 1000);
+        if (data.status === 'completed' || data.status === 'dead') {
+          events.close();
         }
-      } catch (error) {
-        console.error('Error checking status:', error);
-      }
-    };
+      })
+    );
+    events.onerror = (error) => console.error('Error streaming status:', error);
 
-    checkStatus();
+    return () => events.close();
   }, [processingId]);
 
   if (!status) return null;
@@ -36,8 +34,8 @@ const ProcessingStatus = ({ processingId }) => {
       <CardHeader>
         <CardTitle className="flex items-center gap-2">
           {status.status === 'completed' && <CheckCircle className="w-5 h-5 text-green-500" />}
-          {status.status === 'error' && <XCircle className="w-5 h-5 text-red-500" />}
-          {status.status === 'started' && <Loader2 className="w-5 h-5 animate-spin" />}
+          {status.status === 'dead' && <XCircle className="w-5 h-5 text-red-500" />}
+          {(status.status === 'processing' || status.status === 'retrying') && <Loader2 className="w-5 h-5 animate-spin" />}
           Processing Status
         </CardTitle>
       </CardHeader>
@@ -48,7 +46,7 @@ const ProcessingStatus = ({ processingId }) => {
             <div className="w-full bg-gray-200 rounded-full h-2 mt-1">
               <div 
                 className={`h-2 rounded-full ${
-                  status.status === 'error' ? 'bg-red-500' :
+                  status.status === 'dead' ? 'bg-red-500' :
                   status.status === 'completed' ? 'bg-green-500' : 'bg-blue-500'
                 }`}
                 style={{ width: `${status.progress}%` }}
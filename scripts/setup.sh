@@ -30,6 +30,7 @@ setup_database() {
     # Run migrations
     echo "Running database migrations..."
     psql -d search_engine -f migrations/init.sql
+    for migration in migrations/[0-9]*.sql; do psql -d search_engine -f "$migration"; done
 
     # Install vector extension
     echo "Installing vector extension..."
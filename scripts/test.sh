@@ -26,6 +26,7 @@ setup_test_db() {
     
     # Run migrations
     psql -d "$TEST_DB_NAME" -f migrations/init.sql
+    for migration in migrations/[0-9]*.sql; do psql -d "$TEST_DB_NAME" -f "$migration"; done
     
     # Set test database URL
     export DATABASE_URL="postgres://localhost/$TEST_DB_NAME"
@@ -0,0 +1,87 @@
+use crate::utils::helpers::hash_bytes;
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Who a request's API key acts for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Caller {
+    pub user_id: Uuid,
+    pub admin: bool,  // May manage documents, webhooks, synonyms and read analytics
+}
+
+/// API keys, each acting for one user. Only a SHA-256 hash of a key is
+/// stored, so a key cannot be shown again once issued.
+#[derive(Clone)]
+pub struct ApiKeys {
+    pool: PgPool,
+}
+
+impl ApiKeys {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Create a key for `user_id` and return it
+    pub async fn issue(&self, user_id: Uuid, name: Option<&str>, admin: bool) -> Result<String> {
+        let key = format!("sk_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+
+        sqlx::query!(
+            "INSERT INTO api_keys (key_hash, user_id, name, admin) VALUES ($1, $2, $3, $4)",
+            hash_bytes(key.as_bytes()),
+            user_id,
+            name,
+            admin
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(key)
+    }
+
+    /// Who a key acts for, or `None` when it is unknown or revoked
+    pub async fn authenticate(&self, key: &str) -> Result<Option<Caller>> {
+        let caller = sqlx::query_as!(
+            Caller,
+            "SELECT user_id, admin FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL",
+            hash_bytes(key.as_bytes())
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(caller)
+    }
+
+    /// Stop accepting a key. Returns whether it was active.
+    pub async fn revoke(&self, key: &str) -> Result<bool> {
+        let revoked = sqlx::query!(
+            "UPDATE api_keys SET revoked_at = NOW() WHERE key_hash = $1 AND revoked_at IS NULL",
+            hash_bytes(key.as_bytes())
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(revoked > 0)
+    }
+}
+
+/// The key of an `Authorization: Bearer <key>` header value
+pub fn bearer_token(header: &str) -> Option<&str> {
+    let (scheme, token) = header.trim().split_once(' ')?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bearer_token() {
+        assert_eq!(bearer_token("Bearer sk_abc"), Some("sk_abc"));
+        assert_eq!(bearer_token("bearer  sk_abc "), Some("sk_abc"));
+        assert_eq!(bearer_token("Basic dXNlcjpwYXNz"), None);
+        assert_eq!(bearer_token("Bearer "), None);
+    }
+}
@@ -0,0 +1,322 @@
+use crate::api::processor::{DocumentProcessor, DocumentUpload, FileContent};
+use anyhow::{Context, Result};
+use futures::{Stream, TryStreamExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use warp::http::StatusCode;
+use warp::multipart::{FormData, Part};
+use warp::Buf;
+
+/// Uploads handed to `process_batch` at a time, bounding what a bulk
+/// request holds in memory
+const BULK_CHUNK: usize = 32;
+
+/// Content bytes after which pending uploads are enqueued early, so a
+/// chunk of large files is not held in memory all at once
+const BULK_CHUNK_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Serialize)]
+pub struct BulkUploadResponse {
+    pub batch_id: String,
+    pub accepted: Vec<AcceptedUpload>,
+    pub rejected: Vec<RejectedUpload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,  // Why the request stopped part way
+}
+
+#[derive(Debug, Serialize)]
+pub struct AcceptedUpload {
+    pub name: String,  // The filename, or `line N` for NDJSON
+    pub processing_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RejectedUpload {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Collects the uploads of one bulk request into a single batch, enqueueing
+/// them in chunks as they are parsed
+pub struct BulkBatch {
+    processor: Arc<DocumentProcessor>,
+    pending: Vec<(String, DocumentUpload)>,
+    pending_bytes: usize,
+    response: BulkUploadResponse,
+    enqueue_failed: bool,
+}
+
+impl BulkBatch {
+    pub fn new(processor: Arc<DocumentProcessor>, batch_id: Option<String>) -> Self {
+        Self {
+            processor,
+            pending: Vec::with_capacity(BULK_CHUNK),
+            pending_bytes: 0,
+            response: BulkUploadResponse {
+                batch_id: batch_id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+                accepted: Vec::new(),
+                rejected: Vec::new(),
+                error: None,
+            },
+            enqueue_failed: false,
+        }
+    }
+
+    pub async fn add(&mut self, name: String, upload: DocumentUpload) -> Result<()> {
+        self.pending_bytes += upload.content_len();
+        self.pending.push((name, upload));
+        if self.pending.len() >= BULK_CHUNK || self.pending_bytes >= BULK_CHUNK_BYTES {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    pub fn reject(&mut self, name: String, reason: impl Into<String>) {
+        self.response.rejected.push(RejectedUpload { name, reason: reason.into() });
+    }
+
+    /// Enqueue what is left and respond. When the request stopped part way,
+    /// on `error` or failing to enqueue, uploads already enqueued stay queued
+    /// and the response lists them alongside the error.
+    pub async fn finish(mut self, error: Option<anyhow::Error>) -> (StatusCode, BulkUploadResponse) {
+        // Uploads parsed before a bad part or line are still good
+        let flushed = if self.enqueue_failed { Ok(()) } else { self.flush().await };
+
+        let status = if self.enqueue_failed {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else if error.is_some() {
+            StatusCode::BAD_REQUEST
+        } else {
+            StatusCode::ACCEPTED
+        };
+        self.response.error = error.or(flushed.err()).map(|e| format!("{:#}", e));
+
+        (status, self.response)
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        self.pending_bytes = 0;
+        let (names, uploads): (Vec<_>, Vec<_>) = self.pending.drain(..).unzip();
+        let ids = match self.processor
+            .process_batch(uploads, Some(self.response.batch_id.clone()))
+            .await
+        {
+            Ok(ids) => ids,
+            Err(e) => {
+                self.enqueue_failed = true;
+                let reason = format!("Failed to enqueue: {}", e);
+                self.response.rejected.extend(
+                    names.into_iter().map(|name| RejectedUpload { name, reason: reason.clone() }),
+                );
+                return Err(e);
+            }
+        };
+
+        self.response.accepted.extend(
+            names.into_iter()
+                .zip(ids)
+                .map(|(name, processing_id)| AcceptedUpload { name, processing_id }),
+        );
+        Ok(())
+    }
+}
+
+/// Every part with a filename becomes a file upload. A `metadata` field
+/// holding a JSON object of strings applies to the files after it.
+pub async fn read_multipart(mut form: FormData, batch: &mut BulkBatch, max_file_size: usize) -> Result<()> {
+    let mut metadata: Option<HashMap<String, String>> = None;
+
+    while let Some(part) = form.try_next().await.context("Malformed multipart body")? {
+        let field = part.name().to_string();
+        let filename = part.filename().map(str::to_string);
+        let data = read_part(part, max_file_size).await?;
+
+        match (filename, data) {
+            (Some(filename), Some(data)) => {
+                let upload = DocumentUpload::File {
+                    content: FileContent(data),
+                    filename: filename.clone(),
+                    metadata: metadata.clone(),
+                };
+                batch.add(filename, upload).await?;
+            }
+            (Some(filename), None) => {
+                batch.reject(filename, format!("Larger than {} bytes", max_file_size));
+            }
+            (None, Some(data)) if field == "metadata" => {
+                metadata = Some(serde_json::from_slice(&data).context("Invalid metadata field")?);
+            }
+            (None, _) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// The part's bytes, or None when it is over `max_size`
+async fn read_part(part: Part, max_size: usize) -> Result<Option<Vec<u8>>> {
+    let mut data = Vec::new();
+    let mut too_large = false;
+    let mut chunks = part.stream();
+
+    // Oversized parts are still drained so the next part can be read
+    while let Some(chunk) = chunks.try_next().await? {
+        if too_large || data.len() + chunk.remaining() > max_size {
+            too_large = true;
+            continue;
+        }
+        append_buf(&mut data, chunk);
+    }
+
+    Ok((!too_large).then_some(data))
+}
+
+/// Each non-empty line is one upload in the same JSON form as a single
+/// `POST /documents`. Bad lines are rejected without failing the rest.
+pub async fn read_ndjson<S, B>(
+    body: S,
+    batch: &mut BulkBatch,
+    max_line_length: usize,
+    max_body_size: u64,
+) -> Result<()>
+where
+    S: Stream<Item = Result<B, warp::Error>>,
+    B: Buf,
+{
+    let mut body = Box::pin(body);
+    let mut splitter = LineSplitter::new(max_line_length);
+    let mut line_number = 0;
+    let mut read = 0u64;
+
+    while let Some(chunk) = body.try_next().await.context("Failed to read request body")? {
+        read += chunk.remaining() as u64;
+        if read > max_body_size {
+            anyhow::bail!("Request body is larger than {} bytes", max_body_size);
+        }
+
+        let mut bytes = Vec::with_capacity(chunk.remaining());
+        append_buf(&mut bytes, chunk);
+
+        for line in splitter.push(&bytes) {
+            line_number += 1;
+            add_line(batch, line_number, line).await?;
+        }
+    }
+
+    if let Some(line) = splitter.finish() {
+        add_line(batch, line_number + 1, line).await?;
+    }
+
+    Ok(())
+}
+
+async fn add_line(batch: &mut BulkBatch, number: usize, line: Option<Vec<u8>>) -> Result<()> {
+    let name = format!("line {}", number);
+    let Some(line) = line else {
+        batch.reject(name, "Line too long");
+        return Ok(());
+    };
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return Ok(());
+    }
+
+    match serde_json::from_slice::<DocumentUpload>(&line) {
+        Ok(upload) => batch.add(name, upload).await,
+        Err(e) => {
+            batch.reject(name, format!("Invalid upload: {}", e));
+            Ok(())
+        }
+    }
+}
+
+fn append_buf(out: &mut Vec<u8>, mut buf: impl Buf) {
+    while buf.has_remaining() {
+        let chunk = buf.chunk();
+        out.extend_from_slice(chunk);
+        let read = chunk.len();
+        buf.advance(read);
+    }
+}
+
+/// Splits a byte stream into lines without holding more than one line.
+/// Lines over the limit come out as None and their bytes are dropped.
+struct LineSplitter {
+    buffer: Vec<u8>,
+    max_length: usize,
+    overflow: bool,
+}
+
+impl LineSplitter {
+    fn new(max_length: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            max_length,
+            overflow: false,
+        }
+    }
+
+    fn push(&mut self, mut bytes: &[u8]) -> Vec<Option<Vec<u8>>> {
+        let mut lines = Vec::new();
+        while let Some(end) = bytes.iter().position(|b| *b == b'\n') {
+            self.extend(&bytes[..end]);
+            lines.push(self.take());
+            bytes = &bytes[end + 1..];
+        }
+        self.extend(bytes);
+        lines
+    }
+
+    /// The last line when the input does not end in a newline
+    fn finish(mut self) -> Option<Option<Vec<u8>>> {
+        (self.overflow || !self.buffer.is_empty()).then(|| self.take())
+    }
+
+    fn extend(&mut self, bytes: &[u8]) {
+        if self.overflow {
+            return;
+        }
+        if self.buffer.len() + bytes.len() > self.max_length {
+            self.overflow = true;
+            self.buffer = Vec::new();
+        } else {
+            self.buffer.extend_from_slice(bytes);
+        }
+    }
+
+    fn take(&mut self) -> Option<Vec<u8>> {
+        let overflow = std::mem::take(&mut self.overflow);
+        let line = std::mem::take(&mut self.buffer);
+        (!overflow).then_some(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_split_across_chunks() {
+        let mut splitter = LineSplitter::new(64);
+
+        assert!(splitter.push(b"{\"type\":").is_empty());
+        let lines = splitter.push(b"\"text\"}\n\n{\"a\"");
+        assert_eq!(lines, vec![Some(b"{\"type\":\"text\"}".to_vec()), Some(Vec::new())]);
+        assert_eq!(splitter.finish(), Some(Some(b"{\"a\"".to_vec())));
+    }
+
+    #[test]
+    fn test_overlong_line_is_dropped() {
+        let mut splitter = LineSplitter::new(4);
+
+        let lines = splitter.push(b"abc\nabcdefgh\nxy");
+        assert_eq!(lines, vec![Some(b"abc".to_vec()), None]);
+        assert_eq!(splitter.push(b"\n"), vec![Some(b"xy".to_vec())]);
+        assert_eq!(splitter.finish(), None);
+    }
+}
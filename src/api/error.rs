@@ -20,6 +20,9 @@ pub enum ApiError {
     #[error("Authentication error: {0}")]
     AuthError(String),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Internal server error: {0}")]
     InternalError(anyhow::Error),
 
@@ -68,6 +71,11 @@ impl ApiError {
                 message: msg.clone(),
                 details: None,
             },
+            ApiError::Forbidden(msg) => ErrorResponse {
+                code: "FORBIDDEN".to_string(),
+                message: msg.clone(),
+                details: None,
+            },
             ApiError::InternalError(e) => ErrorResponse {
                 code: "INTERNAL_ERROR".to_string(),
                 message: "An internal error occurred".to_string(),
@@ -108,6 +116,7 @@ pub async fn handle_rejection(err: warp::Rejection) -> Result<impl warp::Reply,
             ApiError::DocumentNotFound(_) => warp::http::StatusCode::NOT_FOUND,
             ApiError::InvalidRequest(_) => warp::http::StatusCode::BAD_REQUEST,
             ApiError::AuthError(_) => warp::http::StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => warp::http::StatusCode::FORBIDDEN,
             _ => warp::http::StatusCode::INTERNAL_SERVER_ERROR,
         };
         message = response.message;
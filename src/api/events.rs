@@ -0,0 +1,148 @@
+use crate::api::processor::{DocumentProcessor, ProcessingStatus};
+use crate::api::queue::JobQueue;
+use anyhow::Result;
+use futures::Stream;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
+/// Status updates buffered per subscriber before it is considered lagging
+pub const STATUS_CHANNEL_CAPACITY: usize = 256;
+
+/// What a status stream follows
+#[derive(Debug, Clone)]
+pub enum StatusTopic {
+    Task(String),
+    Batch(String),
+}
+
+impl StatusTopic {
+    pub fn matches(&self, status: &ProcessingStatus) -> bool {
+        match self {
+            StatusTopic::Task(id) => status.id == *id,
+            StatusTopic::Batch(batch_id) => status.batch_id.as_deref() == Some(batch_id.as_str()),
+        }
+    }
+}
+
+/// Completed and dead-lettered tasks see no further updates unless retried
+pub fn is_terminal(status: &ProcessingStatus) -> bool {
+    matches!(status.status.as_str(), "completed" | "dead")
+}
+
+/// Broadcast a task's stored status, skipping the lookup when nobody listens
+pub(crate) async fn publish(queue: &JobQueue, events: &broadcast::Sender<ProcessingStatus>, id: &str) {
+    if events.receiver_count() == 0 {
+        return;
+    }
+
+    match queue.status(id).await {
+        Ok(Some(status)) => {
+            let _ = events.send(status);
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Failed to load status of task {} for subscribers: {}", id, e),
+    }
+}
+
+/// Status transitions for a topic: the current state of every task first,
+/// then each change as it is broadcast. Ends once all tasks are finished.
+pub struct StatusSubscription {
+    processor: Arc<DocumentProcessor>,
+    topic: StatusTopic,
+    events: broadcast::Receiver<ProcessingStatus>,
+    backlog: VecDeque<ProcessingStatus>,
+    unfinished: HashSet<String>,
+}
+
+impl StatusSubscription {
+    /// Fails when the task or batch does not exist
+    pub async fn open(processor: Arc<DocumentProcessor>, topic: StatusTopic) -> Result<Self> {
+        // Subscribe before the snapshot so nothing falls in between
+        let events = processor.subscribe();
+        let backlog = snapshot(&processor, &topic).await?.into();
+
+        Ok(Self {
+            processor,
+            topic,
+            events,
+            backlog,
+            unfinished: HashSet::new(),
+        })
+    }
+
+    pub async fn next(&mut self) -> Option<ProcessingStatus> {
+        loop {
+            if let Some(status) = self.backlog.pop_front() {
+                if is_terminal(&status) {
+                    self.unfinished.remove(&status.id);
+                } else {
+                    self.unfinished.insert(status.id.clone());
+                }
+                return Some(status);
+            }
+
+            if self.unfinished.is_empty() {
+                return None;
+            }
+
+            match self.events.recv().await {
+                Ok(status) if self.topic.matches(&status) => self.backlog.push_back(status),
+                Ok(_) => {}
+                // Missed updates; the stored state is what matters
+                Err(RecvError::Lagged(_)) => match snapshot(&self.processor, &self.topic).await {
+                    Ok(statuses) => self.backlog.extend(statuses),
+                    Err(e) => warn!("Failed to resync {:?}: {}", self.topic, e),
+                },
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    pub fn into_stream(self) -> impl Stream<Item = ProcessingStatus> + Send {
+        futures::stream::unfold(self, |mut subscription| async move {
+            subscription.next().await.map(|status| (status, subscription))
+        })
+    }
+}
+
+async fn snapshot(processor: &DocumentProcessor, topic: &StatusTopic) -> Result<Vec<ProcessingStatus>> {
+    match topic {
+        StatusTopic::Task(id) => Ok(vec![processor.get_processing_status(id).await?]),
+        StatusTopic::Batch(batch_id) => Ok(processor.get_batch_status(batch_id).await?.tasks),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(id: &str, batch_id: Option<&str>, state: &str) -> ProcessingStatus {
+        ProcessingStatus {
+            id: id.to_string(),
+            batch_id: batch_id.map(str::to_string),
+            status: state.to_string(),
+            progress: 0.0,
+            stage: None,
+            stages: Vec::new(),
+            message: None,
+            error: None,
+            attempts: 0,
+            next_attempt_at: None,
+            result: None,
+        }
+    }
+
+    #[test]
+    fn test_topic_matching() {
+        let in_batch = status("a", Some("b1"), "processing");
+        let alone = status("c", None, "completed");
+
+        assert!(StatusTopic::Task("a".to_string()).matches(&in_batch));
+        assert!(StatusTopic::Batch("b1".to_string()).matches(&in_batch));
+        assert!(!StatusTopic::Batch("b1".to_string()).matches(&alone));
+        assert!(is_terminal(&alone));
+        assert!(!is_terminal(&status("d", None, "retrying")));
+    }
+}
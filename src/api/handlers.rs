@@ -1,13 +1,24 @@
 use crate::search::engine::SearchEngine;
-use crate::document::processor::DocumentProcessor;
+use crate::api::processor::{DocumentProcessor, DocumentUpload, ProcessedDocument};
 use crate::vector::store::VectorStore;
 use crate::api::error::ApiError;
+use crate::api::bulk::{read_multipart, read_ndjson, BulkBatch};
+use crate::api::events::{StatusSubscription, StatusTopic};
+use crate::webhooks::{Webhook, WebhookDispatcher, WebhookEvent};
+use crate::query_expander::{QueryExpander, SynonymRule};
+use crate::search::analytics::QueryAnalytics;
+use crate::search::history::{Feedback, SearchHistory, SearchRecord};
+use crate::suggestions::query_weight;
+use crate::trie::Completion;
 
+use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use warp::ws::{Message, WebSocket, Ws};
 use warp::{Reply, Rejection};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use anyhow::Result;
+use tracing::warn;
 use uuid::Uuid;
 
 #[derive(Debug, Deserialize)]
@@ -17,8 +28,6 @@ pub struct SearchQuery {
     pub limit: usize,
     #[serde(default)]
     pub offset: usize,
-    #[serde(default)]
-    pub fields: Option<Vec<String>>,
 }
 
 fn default_limit() -> usize {
@@ -27,6 +36,7 @@ fn default_limit() -> usize {
 
 #[derive(Debug, Serialize)]
 pub struct SearchResponse {
+    query_id: String,  // For feedback on the results
     query: QueryInfo,
     results: Vec<SearchResult>,
     analytics: SearchAnalytics,
@@ -35,9 +45,10 @@ pub struct SearchResponse {
 #[derive(Debug, Serialize)]
 pub struct QueryInfo {
     original: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    did_you_mean: Option<String>,
+    corrected: bool,  // Results are for `did_you_mean` as the original matched nothing
     expanded: String,
-    vector_query: bool,
-    fields: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -46,9 +57,7 @@ pub struct SearchResult {
     title: String,
     content: String,
     author: Option<String>,
-    tags: Vec<String>,
     scores: ScoreBreakdown,
-    highlights: Vec<String>,
     metadata: DocumentMetadata,
 }
 
@@ -57,6 +66,8 @@ pub struct ScoreBreakdown {
     text_score: f32,
     vector_score: f32,
     final_score: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rerank_score: Option<f32>,  // Cross-encoder relevance, for reranked results
 }
 
 #[derive(Debug, Serialize)]
@@ -64,8 +75,6 @@ pub struct SearchAnalytics {
     execution_time_ms: u64,
     total_results: usize,
     max_score: f32,
-    search_type: String,
-    vector_query: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -73,62 +82,124 @@ pub struct DocumentMetadata {
     source_type: String,
     word_count: usize,
     created_at: chrono::DateTime<chrono::Utc>,
-    last_modified: chrono::DateTime<chrono::Utc>,
 }
 
 pub async fn handle_search(
     query: SearchQuery,
+    user_id: Option<Uuid>,
     search_engine: Arc<SearchEngine>,
+    history: Arc<SearchHistory>,
 ) -> Result<impl Reply, Rejection> {
     let start_time = std::time::Instant::now();
 
-    // Execute search
-    let search_result = search_engine.search(
-        &query.q,
-        query.limit,
-        query.offset,
-        query.fields.as_deref(),
-    ).await.map_err(|e| warp::reject::custom(ApiError::SearchError(e)))?;
+    // Execute search, correcting misspelled queries
+    let search = search_engine
+        .search_with_correction(&query.q, Some(query.limit), Some(query.offset))
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::SearchError(e)))?;
+
+    let result_features: Vec<Option<Vec<f32>>> = search.results
+        .iter()
+        .map(|doc| doc.features.clone())
+        .collect();
+    let max_score = search.results.iter().map(|doc| doc.scores.final_score).fold(0.0f32, f32::max);
+    let total_results = search.results.len();
 
     // Format response
+    let query_id = Uuid::new_v4();
     let response = SearchResponse {
+        query_id: query_id.to_string(),
         query: QueryInfo {
             original: query.q.clone(),
-            expanded: search_result.expanded_query,
-            vector_query: search_result.vector_query,
-            fields: search_result.searched_fields,
+            did_you_mean: search.did_you_mean,
+            corrected: search.corrected,
+            expanded: search.expanded_query,
         },
-        results: search_result.documents.into_iter().map(|doc| SearchResult {
+        results: search.results.into_iter().map(|doc| SearchResult {
             id: doc.id,
             title: doc.title,
             content: doc.content,
-            author: doc.author,
-            tags: doc.tags,
+            author: doc.metadata.author,
             scores: ScoreBreakdown {
                 text_score: doc.scores.text_score,
                 vector_score: doc.scores.vector_score,
                 final_score: doc.scores.final_score,
+                rerank_score: doc.scores.rerank_score,
             },
-            highlights: doc.highlights,
             metadata: DocumentMetadata {
                 source_type: doc.metadata.source_type,
                 word_count: doc.metadata.word_count,
                 created_at: doc.metadata.created_at,
-                last_modified: doc.metadata.last_modified,
             },
         }).collect(),
         analytics: SearchAnalytics {
             execution_time_ms: start_time.elapsed().as_millis() as u64,
-            total_results: search_result.total_results,
-            max_score: search_result.max_score,
-            search_type: search_result.search_type,
-            vector_query: search_result.vector_query,
+            total_results,
+            max_score,
         },
     };
 
+    // Recorded before responding so feedback on the results finds the
+    // search, but a failure does not fail the search
+    let record = SearchRecord {
+        id: query_id,
+        query: query.q,
+        user_id,
+        result_count: response.analytics.total_results,
+        execution_time_ms: response.analytics.execution_time_ms,
+        result_ids: response.results.iter().map(|result| result.id.clone()).collect(),
+        result_features,
+    };
+    if let Err(e) = history.record(record).await {
+        warn!("Failed to record search: {}", e);
+    }
+
     Ok(warp::reply::json(&response))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SuggestQuery {
+    pub prefix: String,
+    #[serde(default = "default_suggest_limit")]
+    pub limit: usize,
+}
+
+fn default_suggest_limit() -> usize {
+    5
+}
+
+/// The caller's own past queries come first, then popular queries and
+/// indexed words
+pub async fn handle_suggest(
+    query: SuggestQuery,
+    user_id: Option<Uuid>,
+    search_engine: Arc<SearchEngine>,
+    history: Arc<SearchHistory>,
+) -> Result<impl Reply, Rejection> {
+    let limit = query.limit.min(50);
+
+    let mut completions: Vec<Completion> = match user_id {
+        Some(user_id) => history.user_suggestions(user_id, &query.prefix, limit)
+            .await
+            .map_err(|e| warp::reject::custom(ApiError::DatabaseError(e)))?
+            .into_iter()
+            .map(|(term, popularity)| Completion { term, weight: query_weight(popularity) })
+            .collect(),
+        None => Vec::new(),
+    };
+    for completion in search_engine.suggest(&query.prefix, limit).await {
+        if !completions.iter().any(|c| c.term == completion.term) {
+            completions.push(completion);
+        }
+    }
+    completions.truncate(limit);
+
+    Ok(warp::reply::json(&serde_json::json!({
+        "prefix": query.prefix,
+        "suggestions": completions,
+    })))
+}
+
 pub async fn handle_document_upload(
     processor: Arc<DocumentProcessor>,
     document: DocumentUpload,
@@ -158,6 +229,166 @@ pub async fn handle_status_check(
     Ok(warp::reply::json(&status))
 }
 
+pub async fn handle_batch_status(
+    batch_id: String,
+    processor: Arc<DocumentProcessor>,
+) -> Result<impl Reply, Rejection> {
+    let status = processor.get_batch_status(&batch_id)
+        .await
+        .map_err(|_| warp::reject::custom(ApiError::DocumentNotFound(batch_id)))?;
+
+    Ok(warp::reply::json(&status))
+}
+
+/// Server-sent events with one `data` payload per status change; the event
+/// name is the task status
+pub async fn handle_status_events(
+    topic: StatusTopic,
+    processor: Arc<DocumentProcessor>,
+) -> Result<impl Reply, Rejection> {
+    let subscription = open_subscription(topic, processor).await?;
+
+    let events = subscription.into_stream().map(|status| {
+        warp::sse::Event::default()
+            .id(status.id.clone())
+            .event(status.status.clone())
+            .json_data(&status)
+    });
+
+    Ok(warp::sse::reply(warp::sse::keep_alive().stream(events)))
+}
+
+/// The same updates as the SSE stream, one JSON text frame each
+pub async fn handle_status_socket(
+    topic: StatusTopic,
+    ws: Ws,
+    processor: Arc<DocumentProcessor>,
+) -> Result<impl Reply, Rejection> {
+    let subscription = open_subscription(topic, processor).await?;
+
+    Ok(ws.on_upgrade(move |socket| forward_statuses(socket, subscription)))
+}
+
+async fn open_subscription(
+    topic: StatusTopic,
+    processor: Arc<DocumentProcessor>,
+) -> Result<StatusSubscription, Rejection> {
+    let id = match &topic {
+        StatusTopic::Task(id) | StatusTopic::Batch(id) => id.clone(),
+    };
+
+    StatusSubscription::open(processor, topic)
+        .await
+        .map_err(|_| warp::reject::custom(ApiError::DocumentNotFound(id)))
+}
+
+async fn forward_statuses(socket: WebSocket, mut subscription: StatusSubscription) {
+    let (mut sink, mut incoming) = socket.split();
+
+    loop {
+        tokio::select! {
+            status = subscription.next() => {
+                let Some(status) = status else { break };
+                let Ok(text) = serde_json::to_string(&status) else { continue };
+                if sink.send(Message::text(text)).await.is_err() {
+                    return;
+                }
+            }
+            // Clients only ever close; anything else they send is ignored
+            message = incoming.next() => match message {
+                Some(Ok(message)) if !message.is_close() => {}
+                _ => return,
+            },
+        }
+    }
+
+    let _ = sink.send(Message::close()).await;
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkQuery {
+    pub batch_id: Option<String>,  // Add to an existing batch instead of starting one
+}
+
+/// `multipart/form-data` with one part per file
+pub async fn handle_bulk_multipart(
+    query: BulkQuery,
+    form: warp::multipart::FormData,
+    processor: Arc<DocumentProcessor>,
+    max_file_size: usize,
+) -> Result<impl Reply, Rejection> {
+    let mut batch = BulkBatch::new(processor, query.batch_id);
+    let read = read_multipart(form, &mut batch, max_file_size).await;
+
+    Ok(finish_bulk(batch, read).await)
+}
+
+/// `application/x-ndjson` with one JSON upload per line, read as it streams
+/// in. Chunked bodies are fine; the size cap is enforced while reading.
+pub async fn handle_bulk_ndjson<S, B>(
+    query: BulkQuery,
+    body: S,
+    processor: Arc<DocumentProcessor>,
+    max_file_size: usize,
+    max_body_size: u64,
+) -> Result<impl Reply, Rejection>
+where
+    S: futures::Stream<Item = Result<B, warp::Error>>,
+    B: warp::Buf,
+{
+    let mut batch = BulkBatch::new(processor, query.batch_id);
+
+    // Lines carry base64, which is a third larger than the file itself
+    let max_line_length = max_file_size / 3 * 4 + 64 * 1024;
+    let read = read_ndjson(body, &mut batch, max_line_length, max_body_size).await;
+
+    Ok(finish_bulk(batch, read).await)
+}
+
+/// A body that fails part way still gets the batch id and what was accepted
+async fn finish_bulk(batch: BulkBatch, read: anyhow::Result<()>) -> warp::reply::WithStatus<warp::reply::Json> {
+    let (status, response) = batch.finish(read.err()).await;
+    warp::reply::with_status(warp::reply::json(&response), status)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PageQuery {
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// Tasks that ran out of attempts, newest first
+pub async fn handle_dead_letters(
+    query: PageQuery,
+    processor: Arc<DocumentProcessor>,
+) -> Result<impl Reply, Rejection> {
+    let tasks = processor.dead_letters(query.limit as i64, query.offset as i64)
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::DatabaseError(e)))?;
+
+    Ok(warp::reply::json(&tasks))
+}
+
+pub async fn handle_retry_task(
+    processing_id: String,
+    processor: Arc<DocumentProcessor>,
+) -> Result<impl Reply, Rejection> {
+    let requeued = processor.retry_task(&processing_id)
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::DatabaseError(e)))?;
+
+    if !requeued {
+        return Err(warp::reject::custom(ApiError::DocumentNotFound(processing_id)));
+    }
+
+    Ok(warp::reply::json(&serde_json::json!({
+        "id": processing_id,
+        "status": "pending",
+    })))
+}
+
 #[derive(Debug, Serialize)]
 pub struct ProcessingResponse {
     id: String,
@@ -183,4 +414,249 @@ pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
     } else {
         Err(err)
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    pub events: Vec<String>,
+    pub secret: Option<String>,  // Generated when omitted
+}
+
+/// The signing secret is only ever returned here
+#[derive(Debug, Serialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+pub async fn handle_create_webhook(
+    request: CreateWebhookRequest,
+    webhooks: Arc<WebhookDispatcher>,
+) -> Result<impl Reply, Rejection> {
+    let events = request.events
+        .iter()
+        .map(|event| event.parse::<WebhookEvent>())
+        .collect::<Result<Vec<_>>>()
+        .map_err(|e| warp::reject::custom(ApiError::InvalidRequest(e.to_string())))?;
+
+    let webhook = webhooks.subscribe(&request.url, &events, request.secret)
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::InvalidRequest(e.to_string())))?;
+    let secret = webhook.secret.clone();
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&CreatedWebhook { webhook, secret }),
+        warp::http::StatusCode::CREATED,
+    ))
+}
+
+pub async fn handle_list_webhooks(webhooks: Arc<WebhookDispatcher>) -> Result<impl Reply, Rejection> {
+    let list = webhooks.list()
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::DatabaseError(e)))?;
+
+    Ok(warp::reply::json(&list))
+}
+
+pub async fn handle_delete_webhook(
+    webhook_id: String,
+    webhooks: Arc<WebhookDispatcher>,
+) -> Result<impl Reply, Rejection> {
+    let deleted = webhooks.unsubscribe(&webhook_id)
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::DatabaseError(e)))?;
+
+    if !deleted {
+        return Err(warp::reject::custom(ApiError::DocumentNotFound(webhook_id)));
+    }
+
+    Ok(warp::reply::with_status(warp::reply(), warp::http::StatusCode::NO_CONTENT))
+}
+
+/// Delivery log of a webhook, newest first
+pub async fn handle_webhook_deliveries(
+    webhook_id: String,
+    query: PageQuery,
+    webhooks: Arc<WebhookDispatcher>,
+) -> Result<impl Reply, Rejection> {
+    let deliveries = webhooks.deliveries(&webhook_id, query.limit as i64, query.offset as i64)
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::DatabaseError(e)))?;
+
+    Ok(warp::reply::json(&deliveries))
+}
+
+/// The caller's searches, newest first
+pub async fn handle_search_history(
+    query: PageQuery,
+    user_id: Option<Uuid>,
+    history: Arc<SearchHistory>,
+) -> Result<impl Reply, Rejection> {
+    let user_id = require_user(user_id)?;
+    let entries = history.user_history(user_id, query.limit as i64, query.offset as i64)
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::DatabaseError(e)))?;
+
+    Ok(warp::reply::json(&entries))
+}
+
+pub async fn handle_clear_search_history(
+    user_id: Option<Uuid>,
+    history: Arc<SearchHistory>,
+) -> Result<impl Reply, Rejection> {
+    let user_id = require_user(user_id)?;
+    let deleted = history.delete_user_history(user_id)
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::DatabaseError(e)))?;
+
+    Ok(warp::reply::json(&serde_json::json!({ "deleted": deleted })))
+}
+
+pub async fn handle_delete_search_history_entry(
+    entry_id: String,
+    user_id: Option<Uuid>,
+    history: Arc<SearchHistory>,
+) -> Result<impl Reply, Rejection> {
+    let user_id = require_user(user_id)?;
+    let deleted = history.delete_entry(user_id, &entry_id)
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::DatabaseError(e)))?;
+
+    if !deleted {
+        return Err(warp::reject::custom(ApiError::DocumentNotFound(entry_id)));
+    }
+
+    Ok(warp::reply::with_status(warp::reply(), warp::http::StatusCode::NO_CONTENT))
+}
+
+fn require_user(user_id: Option<Uuid>) -> Result<Uuid, Rejection> {
+    user_id.ok_or_else(|| warp::reject::custom(ApiError::AuthError("An API key is required".to_string())))
+}
+
+/// A click, dwell time or rating for a result of one of the caller's searches
+pub async fn handle_feedback(
+    feedback: Feedback,
+    user_id: Option<Uuid>,
+    history: Arc<SearchHistory>,
+) -> Result<impl Reply, Rejection> {
+    let user_id = require_user(user_id)?;
+    feedback.validate()
+        .map_err(|e| warp::reject::custom(ApiError::InvalidRequest(e.to_string())))?;
+
+    let id = history.record_feedback(&feedback, user_id)
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::DatabaseError(e)))?
+        .ok_or_else(|| warp::reject::custom(ApiError::DocumentNotFound(feedback.query_id.clone())))?;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({ "id": id })),
+        warp::http::StatusCode::CREATED,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsQuery {
+    #[serde(default = "default_window_days")]
+    pub days: u32,
+    #[serde(default = "default_report_limit")]
+    pub limit: usize,
+    #[serde(default = "default_min_searches")]
+    pub min_searches: usize,  // Click-through only
+}
+
+fn default_window_days() -> u32 {
+    7
+}
+
+fn default_report_limit() -> usize {
+    20
+}
+
+fn default_min_searches() -> usize {
+    5
+}
+
+pub async fn handle_top_queries(
+    query: AnalyticsQuery,
+    analytics: Arc<QueryAnalytics>,
+) -> Result<impl Reply, Rejection> {
+    let queries = analytics.top_queries(query.days, query.limit.min(1000) as i64)
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::DatabaseError(e)))?;
+
+    Ok(warp::reply::json(&queries))
+}
+
+pub async fn handle_zero_result_queries(
+    query: AnalyticsQuery,
+    analytics: Arc<QueryAnalytics>,
+) -> Result<impl Reply, Rejection> {
+    let queries = analytics.zero_result_queries(query.days, query.limit.min(1000) as i64)
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::DatabaseError(e)))?;
+
+    Ok(warp::reply::json(&queries))
+}
+
+pub async fn handle_click_through(
+    query: AnalyticsQuery,
+    analytics: Arc<QueryAnalytics>,
+) -> Result<impl Reply, Rejection> {
+    let queries = analytics.click_through(query.days, query.min_searches as i64, query.limit.min(1000) as i64)
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::DatabaseError(e)))?;
+
+    Ok(warp::reply::json(&queries))
+}
+
+pub async fn handle_latency(
+    query: AnalyticsQuery,
+    analytics: Arc<QueryAnalytics>,
+) -> Result<impl Reply, Rejection> {
+    let latency = analytics.latency(query.days)
+        .await
+        .map_err(|e| warp::reject::custom(ApiError::DatabaseError(e)))?;
+
+    Ok(warp::reply::json(&latency))
+}
+
+pub async fn handle_list_synonyms(expander: Arc<QueryExpander>) -> Result<impl Reply, Rejection> {
+    Ok(warp::reply::json(&expander.rules()))
+}
+
+pub async fn handle_create_synonym(
+    rule: SynonymRule,
+    expander: Arc<QueryExpander>,
+) -> Result<impl Reply, Rejection> {
+    let rule = expander.add_rule(rule)
+        .map_err(|e| warp::reject::custom(ApiError::InvalidRequest(e.to_string())))?;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&rule),
+        warp::http::StatusCode::CREATED,
+    ))
+}
+
+pub async fn handle_delete_synonym(
+    rule_id: String,
+    expander: Arc<QueryExpander>,
+) -> Result<impl Reply, Rejection> {
+    let deleted = expander.remove_rule(&rule_id)
+        .map_err(|e| warp::reject::custom(ApiError::InternalError(e)))?;
+
+    if !deleted {
+        return Err(warp::reject::custom(ApiError::DocumentNotFound(rule_id)));
+    }
+
+    Ok(warp::reply::with_status(warp::reply(), warp::http::StatusCode::NO_CONTENT))
+}
+
+/// Pick up edits made to the synonym file by hand
+pub async fn handle_reload_synonyms(expander: Arc<QueryExpander>) -> Result<impl Reply, Rejection> {
+    let rules = expander.reload()
+        .map_err(|e| warp::reject::custom(ApiError::InvalidRequest(e.to_string())))?;
+
+    Ok(warp::reply::json(&serde_json::json!({ "rules": rules })))
+}
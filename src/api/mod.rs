@@ -1,10 +1,19 @@
 pub mod routes;
+pub mod auth;
 pub mod handlers;
 pub mod error;
+pub mod processor;
+pub mod queue;
+pub mod events;
+pub mod bulk;
 
+pub use self::auth::ApiKeys;
 pub use self::error::{ApiError, ErrorResponse};
 pub use self::handlers::{handle_search, handle_document_upload, handle_status_check};
-pub use self::routes::create_routes;
+pub use self::routes::{
+    analytics_routes, bulk_routes, create_routes, document_routes, feedback_routes, history_routes, search_routes,
+    suggest_routes, synonym_routes, webhook_routes,
+};
 
 /// API response type alias for common results
 pub type ApiResult<T> = Result<T, ApiError>;
\ No newline at end of file
@@ -1,18 +1,37 @@
 use crate::vector::store::VectorStore;
+use crate::search::analysis::detect_language;
 use crate::search::engine::SearchEngine;
+use crate::config::DedupConfig;
+use crate::document::{Attachment, CodeBlock, ExtractedContent, OutboundLink};
+use crate::document::ingestion::IngestionOptions;
+use crate::utils::helpers::is_valid_file_type;
+use crate::document::{Document as StoredDocument, DocumentMetadata as StoredMetadata};
+use crate::document::dedup::{store_deduplicated, Deduplicated};
+use crate::document::store::DocumentStore;
+use crate::document::extractor::{ContentExtractor, ExtractionContext, ExtractorRegistry, PageProgress};
+use crate::document::ocr::{OcrEngine, OcrSummary};
+use crate::api::events::{self, STATUS_CHANNEL_CAPACITY};
+use crate::api::queue::{Job, JobQueue};
+use crate::webhooks::{WebhookDispatcher, WebhookEvent};
 use anyhow::{Result, Context};
+use futures::future::{BoxFuture, FutureExt};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::task::JoinHandle;
 use std::collections::HashMap;
 use chrono::{DateTime, Utc};
+use tracing::{error, warn};
+use uuid::Uuid;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum DocumentUpload {
     #[serde(rename = "pdf")]
     Pdf {
-        base64_content: String,
+        #[serde(rename = "base64_content")]
+        content: FileContent,
         filename: String,
         metadata: Option<HashMap<String, String>>,
     },
@@ -28,9 +47,89 @@ pub enum DocumentUpload {
         title: String,
         metadata: Option<HashMap<String, String>>,
     },
+    #[serde(rename = "image")]
+    Image {
+        #[serde(rename = "base64_content")]
+        content: FileContent,
+        filename: String,
+        metadata: Option<HashMap<String, String>>,
+    },
+    /// Any format known to the extractor registry, detected from the
+    /// content and filename
+    #[serde(rename = "file")]
+    File {
+        #[serde(rename = "base64_content")]
+        content: FileContent,
+        filename: String,
+        metadata: Option<HashMap<String, String>>,
+    },
 }
 
-#[derive(Debug, Serialize, Clone)]
+impl DocumentUpload {
+    /// Bytes of content the upload holds in memory
+    pub fn content_len(&self) -> usize {
+        match self {
+            DocumentUpload::Pdf { content, .. }
+            | DocumentUpload::Image { content, .. }
+            | DocumentUpload::File { content, .. } => content.0.len(),
+            DocumentUpload::Html { content, .. } | DocumentUpload::Text { content, .. } => content.len(),
+        }
+    }
+
+    /// Take the bytes out of a file upload, leaving it empty
+    pub fn take_content(&mut self) -> Option<Vec<u8>> {
+        match self {
+            DocumentUpload::Pdf { content, .. }
+            | DocumentUpload::Image { content, .. }
+            | DocumentUpload::File { content, .. } => Some(std::mem::take(&mut content.0)),
+            DocumentUpload::Html { .. } | DocumentUpload::Text { .. } => None,
+        }
+    }
+
+    /// Put back bytes taken with `take_content`
+    pub fn set_content(&mut self, bytes: Vec<u8>) {
+        match self {
+            DocumentUpload::Pdf { content, .. }
+            | DocumentUpload::Image { content, .. }
+            | DocumentUpload::File { content, .. } => content.0 = bytes,
+            DocumentUpload::Html { .. } | DocumentUpload::Text { .. } => {}
+        }
+    }
+}
+
+/// Bytes of a file upload. JSON carries them as base64; the queue stores
+/// them raw, outside the task's JSON payload.
+#[derive(Debug, Default)]
+pub struct FileContent(pub Vec<u8>);
+
+impl Serialize for FileContent {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for FileContent {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encoded)
+            .map(FileContent)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Text and metadata pulled out of an upload before embedding and indexing
+struct ExtractedUpload {
+    content: String,
+    title: String,
+    source_type: String,
+    metadata: HashMap<String, String>,
+    code_blocks: Vec<CodeBlock>,
+    links: Vec<OutboundLink>,
+    ocr: Option<OcrSummary>,
+    attachments: Vec<Attachment>,  // Of emails; processed as child documents
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProcessedDocument {
     pub id: String,
     pub title: String,
@@ -42,7 +141,7 @@ pub struct ProcessedDocument {
     pub processing_info: ProcessingInfo,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DocumentMetadata {
     pub source_type: String,
     pub author: Option<String>,
@@ -51,64 +150,229 @@ pub struct DocumentMetadata {
     pub language: Option<String>,
     pub tags: Vec<String>,
     pub custom_metadata: HashMap<String, String>,
+    #[serde(default)]
+    pub code_blocks: Vec<CodeBlock>,
+    #[serde(default)]
+    pub links: Vec<OutboundLink>,  // Outbound links of web pages
+    #[serde(default)]
+    pub parent_id: Option<String>,  // Set on attachments
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProcessingInfo {
     pub processing_time_ms: u64,
     pub word_count: usize,
     pub language_detected: Option<String>,
     pub content_hash: String,
     pub vector_dimension: usize,
+    pub ocr: Option<OcrSummary>,
 }
 
-pub struct DocumentProcessor {
-    vector_store: Arc<RwLock<VectorStore>>,
-    search_engine: Arc<SearchEngine>,
-    language_detector: whatlang::Detector,
+/// Minimum time between progress writes within one stage
+const PROGRESS_WRITE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Where a task is in the pipeline. Extraction reports pages for paged
+/// formats; `pages` is 0 for everything else.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum ProcessingStage {
+    Received,
+    Extracting { page: u32, pages: u32 },
+    Embedding,
+    Indexing,
+    Done,
 }
 
-impl DocumentProcessor {
-    pub fn new(
-        vector_store: Arc<RwLock<VectorStore>>,
-        search_engine: Arc<SearchEngine>,
-    ) -> Self {
+impl ProcessingStage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProcessingStage::Received => "received",
+            ProcessingStage::Extracting { .. } => "extracting",
+            ProcessingStage::Embedding => "embedding",
+            ProcessingStage::Indexing => "indexing",
+            ProcessingStage::Done => "done",
+        }
+    }
+
+    /// Overall percentage when this stage starts. Extraction and embedding
+    /// take most of the time, so they get most of the range.
+    pub fn progress(&self) -> f32 {
+        match self {
+            ProcessingStage::Received => 0.0,
+            ProcessingStage::Extracting { page, pages } => 5.0 + 45.0 * fraction(*page as usize, *pages as usize),
+            ProcessingStage::Embedding => 50.0,
+            ProcessingStage::Indexing => 90.0,
+            ProcessingStage::Done => 100.0,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            ProcessingStage::Received => "Received".to_string(),
+            ProcessingStage::Extracting { pages: 0, .. } => "Extracting content".to_string(),
+            ProcessingStage::Extracting { page, pages } => format!("Extracting page {} of {}", page, pages),
+            ProcessingStage::Embedding => "Embedding".to_string(),
+            ProcessingStage::Indexing => "Indexing".to_string(),
+            ProcessingStage::Done => "Done".to_string(),
+        }
+    }
+}
+
+/// Share of a stage already behind us while working on item `current` (1-based)
+fn fraction(current: usize, total: usize) -> f32 {
+    if total == 0 {
+        return 0.0;
+    }
+    current.saturating_sub(1).min(total) as f32 / total as f32
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageTiming {
+    pub stage: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Progress handle for a claimed task. Stage changes are written through
+/// immediately; updates within a stage are throttled.
+pub struct ProcessingTask {
+    id: String,
+    queue: JobQueue,
+    events: Option<broadcast::Sender<ProcessingStatus>>,
+    stages: Vec<StageTiming>,
+    last_write: Option<Instant>,
+}
+
+impl ProcessingTask {
+    pub fn new(id: String, queue: JobQueue, received_at: DateTime<Utc>) -> Self {
         Self {
-            vector_store,
-            search_engine,
-            language_detector: whatlang::Detector::new(),
+            id,
+            queue,
+            events: None,
+            stages: vec![StageTiming {
+                stage: ProcessingStage::Received.name().to_string(),
+                started_at: received_at,
+                finished_at: None,
+            }],
+            last_write: None,
+        }
+    }
+
+    /// Broadcast every recorded update to status subscribers
+    pub fn with_events(mut self, events: broadcast::Sender<ProcessingStatus>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    pub async fn update_progress(&mut self, stage: &ProcessingStage) -> Result<()> {
+        let changed = record_stage(&mut self.stages, stage, Utc::now());
+        if !changed && self.last_write.map_or(false, |at| at.elapsed() < PROGRESS_WRITE_INTERVAL) {
+            return Ok(());
+        }
+
+        self.last_write = Some(Instant::now());
+        self.queue
+            .update_progress(&self.id, stage.progress(), &stage.describe(), &self.stages)
+            .await?;
+
+        if let Some(events) = &self.events {
+            events::publish(&self.queue, events, &self.id).await;
         }
+        Ok(())
     }
+}
 
+/// Close the running stage and open `stage` if it is a new one. Returns
+/// whether the stage changed.
+fn record_stage(stages: &mut Vec<StageTiming>, stage: &ProcessingStage, now: DateTime<Utc>) -> bool {
+    if stages.last().map_or(false, |timing| timing.stage == stage.name()) {
+        return false;
+    }
+
+    if let Some(running) = stages.last_mut() {
+        running.finished_at = Some(now);
+    }
+    stages.push(StageTiming {
+        stage: stage.name().to_string(),
+        started_at: now,
+        finished_at: (*stage == ProcessingStage::Done).then_some(now),
+    });
+    true
+}
+
+impl DocumentProcessor {
     pub async fn process_document(&self, upload: DocumentUpload) -> Result<ProcessedDocument> {
-        let start_time = std::time::Instant::now();
+        self.process_document_with_progress(upload, &|_| {}).await
+    }
+
+    /// Process an upload, calling `report` as it moves through the stages
+    pub async fn process_document_with_progress(
+        &self,
+        upload: DocumentUpload,
+        report: &(dyn Fn(ProcessingStage) + Send + Sync),
+    ) -> Result<ProcessedDocument> {
+        let start_time = Instant::now();
 
         // Process based on document type
-        let (content, title, source_type, metadata) = match upload {
-            DocumentUpload::Pdf { base64_content, filename, metadata } => {
-                self.process_pdf(&base64_content, &filename, metadata).await?
+        let mut extracted = match upload {
+            DocumentUpload::Pdf { content, filename, metadata }
+            | DocumentUpload::Image { content, filename, metadata }
+            | DocumentUpload::File { content, filename, metadata } => {
+                let on_page = |page, pages| report(ProcessingStage::Extracting { page, pages });
+                self.process_file(&content.0, &filename, metadata, PageProgress(&on_page)).await?
             },
             DocumentUpload::Html { content, url, metadata } => {
+                report(ProcessingStage::Extracting { page: 0, pages: 0 });
                 self.process_html(&content, url.as_deref(), metadata).await?
             },
             DocumentUpload::Text { content, title, metadata } => {
+                report(ProcessingStage::Extracting { page: 0, pages: 0 });
                 self.process_text(&content, &title, metadata).await?
             },
         };
+        let attachments = std::mem::take(&mut extracted.attachments);
 
-        // Generate vector embedding
-        let vector_store = self.vector_store.read().await;
-        let vector_embedding = vector_store.generate_embedding(&content).await?;
+        let processed = self.process_extracted(extracted, None, start_time, report).await?;
 
-        // Detect language
-        let language = self.detect_language(&content);
+        // Attachments become documents of their own, as in the directory ingester
+        self.process_attachments(&processed.id, attachments, 1).await;
+
+        report(ProcessingStage::Done);
+        Ok(processed)
+    }
+
+    /// Embed, store and index an extracted upload
+    async fn process_extracted(
+        &self,
+        extracted: ExtractedUpload,
+        parent_id: Option<String>,
+        start_time: Instant,
+        report: &(dyn Fn(ProcessingStage) + Send + Sync),
+    ) -> Result<ProcessedDocument> {
+        let ExtractedUpload { content, title, source_type, metadata, code_blocks, links, ocr, .. } = extracted;
+
+        // Image-only input without OCR would otherwise get a meaningless embedding
+        if content.trim().is_empty() {
+            anyhow::bail!("No text could be extracted from the document");
+        }
+
+        report(ProcessingStage::Embedding);
+        let vector_embedding = self.vector_store.read().await
+            .generate_embedding(&content)
+            .await?;
+
+        // Detect language, unless the source declared it
+        let language = metadata.get("language")
+            .cloned()
+            .or_else(|| detect_language(&content));
 
         // Calculate content hash
         let content_hash = calculate_hash(&content);
 
         // Create processed document
         let word_count = content.split_whitespace().count();
-        
+        let vector_dimension = vector_embedding.len();
+
         let processed = ProcessedDocument {
             id: Uuid::new_v4().to_string(),
             title,
@@ -124,52 +388,98 @@ impl DocumentProcessor {
                 language: language.clone(),
                 tags: extract_tags(&metadata),
                 custom_metadata: metadata,
+                code_blocks,
+                links,
+                parent_id,
             },
             processing_info: ProcessingInfo {
                 processing_time_ms: start_time.elapsed().as_millis() as u64,
                 word_count,
                 language_detected: language,
                 content_hash,
-                vector_dimension: vector_embedding.len(),
+                vector_dimension,
+                ocr,
             },
         };
 
-        // Index the document
+        report(ProcessingStage::Indexing);
+        if let Some(documents) = &self.documents {
+            match store_deduplicated(documents, stored_document(&processed), &self.dedup).await? {
+                Deduplicated::Stored(_) => {}
+                // The stored copy is indexed already
+                Deduplicated::Skipped(id) | Deduplicated::Merged(id) => {
+                    return Ok(ProcessedDocument { id, ..processed });
+                }
+            }
+        }
         self.index_document(&processed).await?;
 
         Ok(processed)
     }
 
-    async fn process_pdf(
-        &self,
-        base64_content: &str,
-        filename: &str,
-        metadata: Option<HashMap<String, String>>,
-    ) -> Result<(String, String, String, HashMap<String, String>)> {
-        let pdf_bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, base64_content)
-            .context("Failed to decode PDF content")?;
-
-        let doc = lopdf::Document::load_mem(&pdf_bytes)
-            .context("Failed to load PDF document")?;
-
-        // Extract text content
-        let mut content = String::new();
-        for page_num in 1..=doc.get_pages().len() {
-            if let Ok(page_text) = doc.extract_text(&[page_num]) {
-                content.push_str(&page_text);
-                content.push('\n');
+    /// Process the supported attachments of `parent_id` as its children,
+    /// down to the ingester's `max_attachment_depth`. A failed attachment is
+    /// logged and does not fail its parent.
+    fn process_attachments<'a>(
+        &'a self,
+        parent_id: &'a str,
+        attachments: Vec<Attachment>,
+        depth: usize,
+    ) -> BoxFuture<'a, ()> {
+        async move {
+            if depth > self.attachment_options.max_attachment_depth {
+                return;
             }
-        }
 
-        let title = doc.get_metadata().title
-            .unwrap_or_else(|| filename_to_title(filename));
+            for attachment in attachments {
+                if !is_valid_file_type(&attachment.filename, &self.attachment_options.attachment_types)
+                    || self.extractors.resolve(&attachment.data, &attachment.filename).is_none()
+                {
+                    continue;
+                }
+
+                let child = async {
+                    let no_progress = |_: u32, _: u32| {};
+                    let mut extracted = self
+                        .process_file(&attachment.data, &attachment.filename, None, PageProgress(&no_progress))
+                        .await?;
+                    let attachments = std::mem::take(&mut extracted.attachments);
+                    let processed = self
+                        .process_extracted(extracted, Some(parent_id.to_string()), Instant::now(), &|_| {})
+                        .await?;
+
+                    if let Some(webhooks) = &self.webhooks {
+                        webhooks.emit(WebhookEvent::DocumentProcessed, serde_json::json!({
+                            "document_id": processed.id,
+                            "title": processed.title,
+                            "content_type": processed.content_type,
+                            "word_count": processed.word_count,
+                            "parent_id": parent_id,
+                        })).await;
+                    }
+                    self.process_attachments(&processed.id, attachments, depth + 1).await;
+                    Ok::<_, anyhow::Error>(())
+                };
 
-        let mut meta = metadata.unwrap_or_default();
-        if let Some(author) = doc.get_metadata().author {
-            meta.insert("author".to_string(), author);
+                if let Err(e) = child.await {
+                    warn!("Failed to process attachment {} of {}: {}", attachment.filename, parent_id, e);
+                }
+            }
         }
+        .boxed()
+    }
+
+    async fn process_file(
+        &self,
+        bytes: &[u8],
+        filename: &str,
+        metadata: Option<HashMap<String, String>>,
+        on_page: PageProgress<'_>,
+    ) -> Result<ExtractedUpload> {
+        let context = ExtractionContext { filename, base_url: None, on_page: Some(on_page) };
+        let extracted = self.extractors.extract(bytes, &context).await?;
 
-        Ok((content, title, "pdf".to_string(), meta))
+        upload_from_extracted(extracted, filename_to_title(filename), metadata)
     }
 
     async fn process_html(
@@ -177,30 +487,15 @@ impl DocumentProcessor {
         content: &str,
         url: Option<&str>,
         metadata: Option<HashMap<String, String>>,
-    ) -> Result<(String, String, String, HashMap<String, String>)> {
-        let document = scraper::Html::parse_document(content);
-
-        // Extract text content
-        let text_selector = scraper::Selector::parse("body").unwrap();
-        let mut text_content = String::new();
-        
-        if let Some(body) = document.select(&text_selector).next() {
-            text_content = body.text().collect::<Vec<_>>().join(" ");
-        }
-
-        // Extract title
-        let title_selector = scraper::Selector::parse("title").unwrap();
-        let title = document.select(&title_selector)
-            .next()
-            .map(|el| el.text().collect::<String>())
-            .unwrap_or_else(|| "Untitled Document".to_string());
+    ) -> Result<ExtractedUpload> {
+        let extractor = self.extractors
+            .for_mime("text/html")
+            .context("No HTML extractor registered")?;
 
-        let mut meta = metadata.unwrap_or_default();
-        if let Some(url) = url {
-            meta.insert("url".to_string(), url.to_string());
-        }
+        let context = ExtractionContext { filename: url.unwrap_or_default(), base_url: url, on_page: None };
+        let extracted = extractor.extract(content.as_bytes(), &context).await?;
 
-        Ok((text_content, title, "html".to_string(), meta))
+        upload_from_extracted(extracted, "Untitled Document".to_string(), metadata)
     }
 
     async fn process_text(
@@ -208,13 +503,17 @@ impl DocumentProcessor {
         content: &str,
         title: &str,
         metadata: Option<HashMap<String, String>>,
-    ) -> Result<(String, String, String, HashMap<String, String>)> {
-        Ok((
-            content.to_string(),
-            title.to_string(),
-            "text".to_string(),
-            metadata.unwrap_or_default(),
-        ))
+    ) -> Result<ExtractedUpload> {
+        Ok(ExtractedUpload {
+            content: content.to_string(),
+            title: title.to_string(),
+            source_type: "text".to_string(),
+            metadata: metadata.unwrap_or_default(),
+            code_blocks: Vec::new(),
+            links: Vec::new(),
+            ocr: None,
+            attachments: Vec::new(),
+        })
     }
 
     async fn index_document(&self, document: &ProcessedDocument) -> Result<()> {
@@ -232,27 +531,83 @@ impl DocumentProcessor {
         Ok(())
     }
 
-    fn detect_language(&self, text: &str) -> Option<String> {
-        self.language_detector.detect_lang(text)
-            .map(|lang| lang.code().to_string())
-    }
-
-    pub async fn get_processing_status(&self, processing_id: &str) -> Result<ProcessingStatus> {
-        // Implement status tracking
-        todo!("Implement processing status tracking")
-    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Clone)]
 pub struct ProcessingStatus {
     pub id: String,
-    pub status: String,
+    pub batch_id: Option<String>,
+    pub status: String,  // pending, processing, retrying, completed or dead
     pub progress: f32,
+    pub stage: Option<String>,  // Current stage, see `ProcessingStage::name`
+    pub stages: Vec<StageTiming>,
     pub message: Option<String>,
+    pub error: Option<String>,  // Last failure, kept while retrying
+    pub attempts: u32,
+    pub next_attempt_at: Option<DateTime<Utc>>,
     pub result: Option<ProcessedDocument>,
 }
 
 // Helper functions
+
+/// Flatten extractor output into the upload's string metadata map.
+/// Uploaded metadata wins over what the document declares about itself.
+fn upload_from_extracted(
+    extracted: ExtractedContent,
+    fallback_title: String,
+    metadata: Option<HashMap<String, String>>,
+) -> Result<ExtractedUpload> {
+    let harvested = extracted.metadata;
+
+    let mut meta = harvested.custom_metadata;
+    if let Some(author) = harvested.author {
+        meta.insert("author".to_string(), author);
+    }
+    if let Some(language) = harvested.language {
+        meta.insert("language".to_string(), language);
+    }
+    if !harvested.tags.is_empty() {
+        meta.insert("tags".to_string(), harvested.tags.join(","));
+    }
+    meta.extend(metadata.unwrap_or_default());
+
+    Ok(ExtractedUpload {
+        content: extracted.text,
+        title: extracted.title.unwrap_or(fallback_title),
+        source_type: harvested.source_type,
+        metadata: meta,
+        code_blocks: harvested.code_blocks,
+        links: harvested.links,
+        ocr: extracted.ocr,
+        attachments: extracted.attachments,
+    })
+}
+
+/// The document store's form of a processed upload
+fn stored_document(processed: &ProcessedDocument) -> StoredDocument {
+    let metadata = &processed.metadata;
+    StoredDocument {
+        id: processed.id.clone(),
+        title: processed.title.clone(),
+        content: processed.content.clone(),
+        content_type: processed.content_type.clone(),
+        metadata: StoredMetadata {
+            source_type: metadata.source_type.clone(),
+            author: metadata.author.clone(),
+            created_at: metadata.created_at,
+            last_modified: metadata.last_modified,
+            language: metadata.language.clone(),
+            tags: metadata.tags.clone(),
+            custom_metadata: metadata.custom_metadata.clone(),
+            code_blocks: metadata.code_blocks.clone(),
+            links: metadata.links.clone(),
+            parent_id: metadata.parent_id.clone(),
+            ..Default::default()
+        },
+        vector_embedding: Some(processed.vector_embedding.clone()),
+    }
+}
+
 fn calculate_hash(content: &str) -> String {
     use sha2::{Sha256, Digest};
     let mut hasher = Sha256::new();
@@ -263,8 +618,10 @@ fn calculate_hash(content: &str) -> String {
 // Continuing from previous code...
 
 fn filename_to_title(filename: &str) -> String {
-    filename
-        .trim_end_matches(".pdf")
+    std::path::Path::new(filename)
+        .file_stem()
+        .and_then(std::ffi::OsStr::to_str)
+        .unwrap_or(filename)
         .replace('_', " ")
         .replace('-', " ")
         .split_whitespace()
@@ -289,186 +646,123 @@ fn extract_tags(metadata: &HashMap<String, String>) -> Vec<String> {
         .unwrap_or_default()
 }
 
-// Add processing queue for status tracking
-#[derive(Debug, Clone)]
-pub struct ProcessingTask {
-    pub id: String,
-    pub status: ProcessingStatus,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
-}
-
-impl ProcessingTask {
-    fn new(id: String) -> Self {
-        let now = Utc::now();
-        Self {
-            id,
-            status: ProcessingStatus {
-                id: id.clone(),
-                status: "pending".to_string(),
-                progress: 0.0,
-                message: None,
-                result: None,
-            },
-            created_at: now,
-            updated_at: now,
-        }
-    }
-
-    fn update_progress(&mut self, progress: f32, message: Option<String>) {
-        self.status.progress = progress;
-        self.status.message = message;
-        self.updated_at = Utc::now();
-    }
-
-    fn complete(&mut self, document: ProcessedDocument) {
-        self.status.status = "completed".to_string();
-        self.status.progress = 100.0;
-        self.status.result = Some(document);
-        self.updated_at = Utc::now();
-    }
-
-    fn fail(&mut self, error: String) {
-        self.status.status = "failed".to_string();
-        self.status.message = Some(error);
-        self.updated_at = Utc::now();
-    }
-}
-
-// Add to DocumentProcessor struct
+#[derive(Clone)]
 pub struct DocumentProcessor {
     vector_store: Arc<RwLock<VectorStore>>,
     search_engine: Arc<SearchEngine>,
-    language_detector: whatlang::Detector,
-    processing_queue: Arc<RwLock<HashMap<String, ProcessingTask>>>,
+    queue: JobQueue,
+    events: broadcast::Sender<ProcessingStatus>,
+    extractors: Arc<ExtractorRegistry>,
+    webhooks: Option<WebhookDispatcher>,
+    documents: Option<Arc<RwLock<DocumentStore>>>,
+    dedup: DedupConfig,
+    attachment_options: IngestionOptions,  // Which attachments are processed, and how deep
 }
 
 impl DocumentProcessor {
-    // Update new() function
     pub fn new(
         vector_store: Arc<RwLock<VectorStore>>,
         search_engine: Arc<SearchEngine>,
+        queue: JobQueue,
     ) -> Self {
         Self {
             vector_store,
             search_engine,
-            language_detector: whatlang::Detector::new(),
-            processing_queue: Arc::new(RwLock::new(HashMap::new())),
+            queue,
+            events: broadcast::channel(STATUS_CHANNEL_CAPACITY).0,
+            extractors: Arc::new(ExtractorRegistry::default()),
+            webhooks: None,
+            documents: None,
+            dedup: DedupConfig::default(),
+            attachment_options: IngestionOptions::default(),
         }
     }
 
-    // Add async processing method
+    /// Enable OCR for image uploads and PDF pages without a text layer
+    pub fn with_ocr_engine(mut self, engine: Arc<dyn OcrEngine>) -> Self {
+        Arc::make_mut(&mut self.extractors).register_ocr(engine);
+        self
+    }
+
+    /// Accept an additional upload format, or replace a built-in one
+    pub fn with_extractor(mut self, extractor: Arc<dyn ContentExtractor>) -> Self {
+        Arc::make_mut(&mut self.extractors).register(extractor);
+        self
+    }
+
+    /// Record processed uploads in the document store, deduplicated against
+    /// everything stored there the same way directory ingestion is
+    pub fn with_document_store(mut self, documents: Arc<RwLock<DocumentStore>>, dedup: DedupConfig) -> Self {
+        self.documents = Some(documents);
+        self.dedup = dedup;
+        self
+    }
+
+    /// Send `document.processed` and `document.failed` events for finished tasks
+    pub fn with_webhooks(mut self, webhooks: WebhookDispatcher) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// Queue an upload for the worker pool and return its task id
     pub async fn process_document_async(&self, upload: DocumentUpload) -> Result<String> {
-        let processing_id = Uuid::new_v4().to_string();
-        let task = ProcessingTask::new(processing_id.clone());
-        
-        // Add to queue
-        {
-            let mut queue = self.processing_queue.write().await;
-            queue.insert(processing_id.clone(), task);
-        }
+        let id = self.queue.enqueue(upload, None).await?;
+        self.publish(&id).await;
+        Ok(id)
+    }
 
-        // Clone necessary components for async processing
-        let processor = self.clone();
-        let processing_id_clone = processing_id.clone();
-
-        // Spawn processing task
-        tokio::spawn(async move {
-            let result = processor.process_document(upload).await;
-            
-            let mut queue = processor.processing_queue.write().await;
-            if let Some(task) = queue.get_mut(&processing_id_clone) {
-                match result {
-                    Ok(doc) => task.complete(doc),
-                    Err(e) => task.fail(e.to_string()),
-                }
-            }
-        });
+    /// Status changes of every task handled by this processor's workers
+    pub fn subscribe(&self) -> broadcast::Receiver<ProcessingStatus> {
+        self.events.subscribe()
+    }
 
-        Ok(processing_id)
+    async fn publish(&self, id: &str) {
+        events::publish(&self.queue, &self.events, id).await;
     }
 
-    // Update status check method
     pub async fn get_processing_status(&self, processing_id: &str) -> Result<ProcessingStatus> {
-        let queue = self.processing_queue.read().await;
-        queue.get(processing_id)
-            .map(|task| task.status.clone())
+        self.queue
+            .status(processing_id)
+            .await?
             .ok_or_else(|| anyhow::anyhow!("Processing task not found"))
     }
 
-    // Add cleanup method for completed tasks
     pub async fn cleanup_old_tasks(&self, max_age_hours: i64) -> Result<()> {
-        let now = Utc::now();
-        let mut queue = self.processing_queue.write().await;
-        
-        queue.retain(|_, task| {
-            let age = now - task.updated_at;
-            let hours = age.num_hours();
-            
-            // Keep task if it's still pending or younger than max age
-            task.status.status == "pending" || hours < max_age_hours
-        });
-
+        self.queue.cleanup(max_age_hours).await?;
         Ok(())
     }
 
-    // Add batch processing method
     pub async fn process_batch(
         &self,
         documents: Vec<DocumentUpload>,
         batch_id: Option<String>,
     ) -> Result<Vec<String>> {
         let batch_id = batch_id.unwrap_or_else(|| Uuid::new_v4().to_string());
-        let mut processing_ids = Vec::new();
-
-        for (index, doc) in documents.into_iter().enumerate() {
-            let processing_id = format!("{}-{}", batch_id, index);
-            let task = ProcessingTask::new(processing_id.clone());
-            
-            {
-                let mut queue = self.processing_queue.write().await;
-                queue.insert(processing_id.clone(), task);
-            }
+        let mut processing_ids = Vec::with_capacity(documents.len());
 
-            processing_ids.push(processing_id.clone());
-
-            // Process document asynchronously
-            let processor = self.clone();
-            let processing_id_clone = processing_id.clone();
-
-            tokio::spawn(async move {
-                let result = processor.process_document(doc).await;
-                
-                let mut queue = processor.processing_queue.write().await;
-                if let Some(task) = queue.get_mut(&processing_id_clone) {
-                    match result {
-                        Ok(doc) => task.complete(doc),
-                        Err(e) => task.fail(e.to_string()),
-                    }
-                }
-            });
+        for doc in documents {
+            processing_ids.push(self.queue.enqueue(doc, Some(&batch_id)).await?);
+        }
+        for id in &processing_ids {
+            self.publish(id).await;
         }
 
         Ok(processing_ids)
     }
 
-    // Add batch status check method
     pub async fn get_batch_status(&self, batch_id: &str) -> Result<BatchStatus> {
-        let queue = self.processing_queue.read().await;
-        let batch_tasks: Vec<_> = queue.values()
-            .filter(|task| task.id.starts_with(batch_id))
-            .collect();
+        let tasks = self.queue.batch(batch_id).await?;
 
-        if batch_tasks.is_empty() {
+        if tasks.is_empty() {
             return Err(anyhow::anyhow!("Batch not found"));
         }
 
-        let total = batch_tasks.len();
-        let completed = batch_tasks.iter()
-            .filter(|task| task.status.status == "completed")
+        let total = tasks.len();
+        let completed = tasks.iter()
+            .filter(|task| task.status == "completed")
             .count();
-        let failed = batch_tasks.iter()
-            .filter(|task| task.status.status == "failed")
+        let failed = tasks.iter()
+            .filter(|task| task.status == "dead")
             .count();
         let in_progress = total - completed - failed;
 
@@ -479,9 +773,137 @@ impl DocumentProcessor {
             failed,
             in_progress,
             overall_progress: (completed as f32 / total as f32) * 100.0,
-            tasks: batch_tasks.into_iter().map(|t| t.status.clone()).collect(),
+            tasks,
         })
     }
+
+    pub async fn dead_letters(&self, limit: i64, offset: i64) -> Result<Vec<ProcessingStatus>> {
+        self.queue.dead_letters(limit, offset).await
+    }
+
+    /// Requeue a dead-lettered task; false if it is not dead-lettered
+    pub async fn retry_task(&self, processing_id: &str) -> Result<bool> {
+        let requeued = self.queue.retry(processing_id).await?;
+        if requeued {
+            self.publish(processing_id).await;
+        }
+        Ok(requeued)
+    }
+
+    /// Start `count` workers that claim queued uploads, normally
+    /// `ProcessingConfig::processing_threads` of them
+    pub fn start_workers(&self, count: usize) -> Vec<JoinHandle<()>> {
+        (0..count.max(1))
+            .map(|worker| {
+                let processor = self.clone();
+                tokio::spawn(async move { processor.run_worker(worker).await })
+            })
+            .collect()
+    }
+
+    async fn run_worker(&self, worker: usize) {
+        loop {
+            match self.queue.claim(worker).await {
+                Ok(Some(job)) => {
+                    self.publish(&job.id).await;
+                    self.run_job(job).await
+                }
+                Ok(None) => self.queue.wait().await,
+                Err(e) => {
+                    error!("Worker {} failed to claim a job: {}", worker, e);
+                    self.queue.wait().await;
+                }
+            }
+        }
+    }
+
+    async fn run_job(&self, mut job: Job) {
+        let mut upload: DocumentUpload = match serde_json::from_value(job.payload.clone()) {
+            Ok(upload) => upload,
+            Err(e) => {
+                let error = format!("Unreadable payload: {}", e);
+                if let Err(e) = self.queue.dead_letter(&job, &error).await {
+                    error!("Failed to dead-letter task {}: {}", job.id, e);
+                }
+                self.publish(&job.id).await;
+                self.emit_failed(&job, &error).await;
+                return;
+            }
+        };
+        if let Some(content) = job.content.take() {
+            upload.set_content(content);
+        }
+
+        // Stages are written by a separate task so reporting never blocks processing
+        let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+        let mut task = ProcessingTask::new(job.id.clone(), self.queue.clone(), job.received_at)
+            .with_events(self.events.clone());
+        let recorder = tokio::spawn(async move {
+            while let Some(stage) = progress_rx.recv().await {
+                if let Err(e) = task.update_progress(&stage).await {
+                    warn!("Failed to record progress for task {}: {}", task.id, e);
+                }
+            }
+        });
+
+        // A panic in extraction fails the job instead of taking the worker down
+        let processor = self.clone();
+        let result = tokio::spawn(async move {
+            let report = move |stage| {
+                let _ = progress_tx.send(stage);
+            };
+            processor.process_document_with_progress(upload, &report).await
+        })
+        .await
+        .unwrap_or_else(|e| Err(anyhow::anyhow!("Processing panicked: {}", e)));
+
+        // Let every stage land before the final status is written
+        let _ = recorder.await;
+
+        match result {
+            Ok(document) => {
+                match self.queue.complete(&job, &document).await {
+                    Ok(true) => {}
+                    Ok(false) => warn!("Task {} was reclaimed before it finished; its result is discarded", job.id),
+                    Err(e) => error!("Failed to record the outcome of task {}: {}", job.id, e),
+                }
+                self.publish(&job.id).await;
+
+                if let Some(webhooks) = &self.webhooks {
+                    webhooks.emit(WebhookEvent::DocumentProcessed, serde_json::json!({
+                        "task_id": job.id,
+                        "document_id": document.id,
+                        "title": document.title,
+                        "content_type": document.content_type,
+                        "word_count": document.word_count,
+                    })).await;
+                }
+            }
+            Err(e) => {
+                warn!("Task {} failed on attempt {}/{}: {}", job.id, job.attempts, job.max_attempts, e);
+                let dead = self.queue.fail(&job, &e.to_string()).await.unwrap_or_else(|record_error| {
+                    error!("Failed to record the outcome of task {}: {}", job.id, record_error);
+                    false
+                });
+                self.publish(&job.id).await;
+
+                // Retries are internal; subscribers only hear about giving up
+                if dead {
+                    self.emit_failed(&job, &e.to_string()).await;
+                }
+            }
+        }
+    }
+
+    async fn emit_failed(&self, job: &Job, error: &str) {
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.emit(WebhookEvent::DocumentFailed, serde_json::json!({
+                "task_id": job.id,
+                "attempts": job.attempts,
+                "error": error,
+            })).await;
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -496,13 +918,34 @@ pub struct BatchStatus {
 }
 
 // Implement Clone for DocumentProcessor
-impl Clone for DocumentProcessor {
-    fn clone(&self) -> Self {
-        Self {
-            vector_store: self.vector_store.clone(),
-            search_engine: self.search_engine.clone(),
-            language_detector: whatlang::Detector::new(),
-            processing_queue: self.processing_queue.clone(),
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_progress() {
+        let first_page = ProcessingStage::Extracting { page: 1, pages: 40 };
+        let last_page = ProcessingStage::Extracting { page: 40, pages: 40 };
+
+        assert_eq!(first_page.progress(), 5.0);
+        assert!(last_page.progress() < ProcessingStage::Embedding.progress());
+        assert_eq!(last_page.describe(), "Extracting page 40 of 40");
+        assert_eq!(ProcessingStage::Extracting { page: 0, pages: 0 }.progress(), 5.0);
+    }
+
+    #[test]
+    fn test_record_stage_timings() {
+        let start = Utc::now();
+        let mut stages = Vec::new();
+
+        assert!(record_stage(&mut stages, &ProcessingStage::Extracting { page: 1, pages: 2 }, start));
+        assert!(!record_stage(&mut stages, &ProcessingStage::Extracting { page: 2, pages: 2 }, start));
+        let end = start + chrono::Duration::seconds(3);
+        assert!(record_stage(&mut stages, &ProcessingStage::Done, end));
+
+        let names: Vec<&str> = stages.iter().map(|timing| timing.stage.as_str()).collect();
+        assert_eq!(names, vec!["extracting", "done"]);
+        assert_eq!(stages[0].finished_at, Some(end));
+        assert_eq!(stages[1].finished_at, Some(end));
     }
-}
\ No newline at end of file
+}
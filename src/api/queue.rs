@@ -0,0 +1,355 @@
+use crate::api::processor::{DocumentUpload, ProcessingStatus, StageTiming};
+use crate::config::QueueConfig;
+use crate::utils::helpers::exponential_backoff;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// An upload claimed by a worker. It stays locked until it is completed,
+/// failed, or the lock goes stale because the worker died.
+#[derive(Debug)]
+pub struct Job {
+    pub id: String,
+    pub payload: serde_json::Value,
+    pub content: Option<Vec<u8>>,  // Bytes of a file upload, stored apart from the payload
+    pub attempts: u32,
+    pub max_attempts: u32,
+    pub received_at: DateTime<Utc>,
+    pub locked_by: String,  // The claiming worker; nobody else may finish the job
+}
+
+/// Persistent upload queue in the `processing_tasks` table. Jobs move through
+/// `pending` -> `processing` -> `completed`, or back to `retrying` with
+/// exponential backoff, and end up `dead` once out of attempts.
+#[derive(Clone)]
+pub struct JobQueue {
+    pool: PgPool,
+    config: QueueConfig,
+    notify: Arc<Notify>,
+    instance: Uuid,  // Tells this process's workers apart from other processes'
+}
+
+struct TaskRow {
+    id: String,
+    batch_id: Option<String>,
+    status: String,
+    progress: Option<f64>,
+    message: Option<String>,
+    error: Option<String>,
+    result: Option<serde_json::Value>,
+    attempts: i32,
+    run_at: Option<DateTime<Utc>>,
+    stages: serde_json::Value,
+}
+
+impl From<TaskRow> for ProcessingStatus {
+    fn from(row: TaskRow) -> Self {
+        let next_attempt_at = match row.status.as_str() {
+            "retrying" => row.run_at,
+            _ => None,
+        };
+
+        let stages: Vec<StageTiming> = serde_json::from_value(row.stages).unwrap_or_default();
+
+        Self {
+            id: row.id,
+            batch_id: row.batch_id,
+            status: row.status,
+            stage: stages.last().map(|timing| timing.stage.clone()),
+            stages,
+            progress: row.progress.unwrap_or_default() as f32,
+            message: row.message,
+            error: row.error,
+            attempts: row.attempts.max(0) as u32,
+            next_attempt_at,
+            result: row.result.and_then(|r| serde_json::from_value(r).ok()),
+        }
+    }
+}
+
+impl JobQueue {
+    pub fn new(pool: PgPool, config: QueueConfig) -> Self {
+        Self {
+            pool,
+            config,
+            notify: Arc::new(Notify::new()),
+            instance: Uuid::new_v4(),
+        }
+    }
+
+    pub async fn enqueue(&self, mut upload: DocumentUpload, batch_id: Option<&str>) -> Result<String> {
+        let id = Uuid::new_v4();
+
+        // File bytes go in a BYTEA column rather than as base64 in the payload
+        let content = upload.take_content();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO processing_tasks (id, status, payload, content, batch_id, max_attempts)
+            VALUES ($1, 'pending', $2, $3, $4, $5)
+            "#,
+            id,
+            serde_json::to_value(&upload)?,
+            content,
+            batch_id,
+            self.config.max_attempts as i32
+        )
+        .execute(&self.pool)
+        .await?;
+
+        self.notify.notify_one();
+        Ok(id.to_string())
+    }
+
+    /// Claim the next runnable job for `worker`. Jobs whose worker stopped
+    /// heartbeating are reclaimed, which counts as another attempt; those
+    /// already out of attempts are dead-lettered instead.
+    pub async fn claim(&self, worker: usize) -> Result<Option<Job>> {
+        self.bury_stale().await?;
+
+        let locked_by = format!("{}/{}", self.instance, worker);
+        let record = sqlx::query!(
+            r#"
+            UPDATE processing_tasks
+            SET status = 'processing', attempts = attempts + 1, locked_at = NOW(), locked_by = $2,
+                progress = 0, stages = '[]'
+            WHERE id = (
+                SELECT id FROM processing_tasks
+                WHERE (status IN ('pending', 'retrying') AND run_at <= NOW())
+                   OR (status = 'processing' AND locked_at < NOW() - make_interval(secs => $1)
+                       AND attempts < max_attempts)
+                ORDER BY run_at
+                FOR UPDATE SKIP LOCKED
+                LIMIT 1
+            )
+            RETURNING id::text AS "id!", payload AS "payload!", content, attempts, max_attempts,
+                      created_at AS "created_at!"
+            "#,
+            self.config.stale_after_secs as f64,
+            locked_by
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(record.map(|r| Job {
+            id: r.id,
+            payload: r.payload,
+            content: r.content,
+            attempts: r.attempts.max(0) as u32,
+            max_attempts: r.max_attempts.max(0) as u32,
+            received_at: r.created_at,
+            locked_by,
+        }))
+    }
+
+    /// Dead-letter stale jobs whose last attempt died with its worker, so a
+    /// job that crashes workers is not reclaimed forever
+    async fn bury_stale(&self) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE processing_tasks
+            SET status = 'dead', error = COALESCE(error, 'Worker stopped responding'),
+                locked_at = NULL, locked_by = NULL
+            WHERE status = 'processing' AND locked_at < NOW() - make_interval(secs => $1)
+              AND attempts >= max_attempts
+            "#,
+            self.config.stale_after_secs as f64
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Wait for an enqueue or the poll interval, whichever comes first
+    pub async fn wait(&self) {
+        let poll = Duration::from_millis(self.config.poll_interval_ms);
+        let _ = tokio::time::timeout(poll, self.notify.notified()).await;
+    }
+
+    /// Record stage progress; this also keeps the worker's lock fresh
+    pub async fn update_progress(
+        &self,
+        id: &str,
+        progress: f32,
+        message: &str,
+        stages: &[StageTiming],
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE processing_tasks
+            SET progress = $2, message = $3, stages = $4, locked_at = NOW()
+            WHERE id = $1::uuid AND status = 'processing'
+            "#,
+            id,
+            progress as f64,
+            message,
+            serde_json::to_value(stages)?
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record the result, unless the job was reclaimed from this worker.
+    /// Returns whether it was recorded.
+    pub async fn complete<T: Serialize>(&self, job: &Job, result: &T) -> Result<bool> {
+        let updated = sqlx::query!(
+            r#"
+            UPDATE processing_tasks
+            SET status = 'completed', progress = 100, result = $2, error = NULL,
+                locked_at = NULL, locked_by = NULL, content = NULL
+            WHERE id = $1::uuid AND locked_by = $3
+            "#,
+            job.id,
+            serde_json::to_value(result)?,
+            job.locked_by
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(updated > 0)
+    }
+
+    /// Schedule a retry, or dead-letter the job once it is out of attempts.
+    /// Returns whether the job was dead-lettered.
+    pub async fn fail(&self, job: &Job, error: &str) -> Result<bool> {
+        if job.attempts >= job.max_attempts {
+            return self.dead_letter(job, error).await;
+        }
+
+        let delay = exponential_backoff(self.config.base_backoff_secs, self.config.max_backoff_secs, job.attempts);
+        sqlx::query!(
+            r#"
+            UPDATE processing_tasks
+            SET status = 'retrying', error = $2, locked_at = NULL, locked_by = NULL,
+                run_at = NOW() + make_interval(secs => $3)
+            WHERE id = $1::uuid AND locked_by = $4
+            "#,
+            job.id,
+            error,
+            delay.as_secs_f64(),
+            job.locked_by
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(false)
+    }
+
+    /// Park a job that can never succeed, e.g. one with an unreadable payload.
+    /// Returns whether it was parked; a reclaimed job is left to its new worker.
+    pub async fn dead_letter(&self, job: &Job, error: &str) -> Result<bool> {
+        let updated = sqlx::query!(
+            r#"
+            UPDATE processing_tasks
+            SET status = 'dead', error = $2, locked_at = NULL, locked_by = NULL
+            WHERE id = $1::uuid AND locked_by = $3
+            "#,
+            job.id,
+            error,
+            job.locked_by
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(updated > 0)
+    }
+
+    /// Put a dead-lettered job back in the queue with fresh attempts
+    pub async fn retry(&self, id: &str) -> Result<bool> {
+        let updated = sqlx::query!(
+            r#"
+            UPDATE processing_tasks
+            SET status = 'pending', attempts = 0, error = NULL, run_at = NOW()
+            WHERE id = $1::uuid AND status = 'dead'
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        if updated > 0 {
+            self.notify.notify_one();
+        }
+        Ok(updated > 0)
+    }
+
+    pub async fn status(&self, id: &str) -> Result<Option<ProcessingStatus>> {
+        let row = sqlx::query_as!(
+            TaskRow,
+            r#"
+            SELECT id::text AS "id!", batch_id, status, progress, message, error, result, attempts, run_at, stages
+            FROM processing_tasks
+            WHERE id = $1::uuid
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(ProcessingStatus::from))
+    }
+
+    pub async fn batch(&self, batch_id: &str) -> Result<Vec<ProcessingStatus>> {
+        let rows = sqlx::query_as!(
+            TaskRow,
+            r#"
+            SELECT id::text AS "id!", batch_id, status, progress, message, error, result, attempts, run_at, stages
+            FROM processing_tasks
+            WHERE batch_id = $1
+            ORDER BY created_at
+            "#,
+            batch_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(ProcessingStatus::from).collect())
+    }
+
+    pub async fn dead_letters(&self, limit: i64, offset: i64) -> Result<Vec<ProcessingStatus>> {
+        let rows = sqlx::query_as!(
+            TaskRow,
+            r#"
+            SELECT id::text AS "id!", batch_id, status, progress, message, error, result, attempts, run_at, stages
+            FROM processing_tasks
+            WHERE status = 'dead'
+            ORDER BY updated_at DESC
+            LIMIT $1 OFFSET $2
+            "#,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(ProcessingStatus::from).collect())
+    }
+
+    /// Drop completed jobs older than `max_age_hours`. Dead letters are kept
+    /// until someone retries them.
+    pub async fn cleanup(&self, max_age_hours: i64) -> Result<u64> {
+        let deleted = sqlx::query!(
+            r#"
+            DELETE FROM processing_tasks
+            WHERE status = 'completed' AND updated_at < NOW() - make_interval(hours => $1)
+            "#,
+            max_age_hours as i32
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(deleted)
+    }
+}
@@ -1,54 +1,397 @@
 // routes.rs
+use crate::api::events::StatusTopic;
+use crate::api::handlers::{
+    handle_document_upload, handle_batch_status, handle_dead_letters, handle_retry_task, handle_status_check,
+    handle_status_events, handle_status_socket, PageQuery,
+    handle_create_webhook, handle_delete_webhook, handle_list_webhooks, handle_webhook_deliveries,
+    handle_bulk_multipart, handle_bulk_ndjson, BulkQuery,
+    handle_create_synonym, handle_delete_synonym, handle_list_synonyms, handle_reload_synonyms,
+    handle_suggest, SuggestQuery,
+    handle_search, handle_feedback, SearchQuery,
+    handle_clear_search_history, handle_delete_search_history_entry, handle_search_history,
+    handle_click_through, handle_latency, handle_top_queries, handle_zero_result_queries, AnalyticsQuery,
+};
+use crate::api::auth::{bearer_token, ApiKeys, Caller};
+use crate::api::error::ApiError;
+use crate::config::ProcessingConfig;
+use crate::api::processor::DocumentProcessor;
+use crate::query_expander::QueryExpander;
+use crate::search::analytics::QueryAnalytics;
+use crate::search::engine::SearchEngine;
+use crate::search::history::SearchHistory;
+use crate::webhooks::WebhookDispatcher;
 use warp::{Filter, Reply, Rejection};
+use std::convert::Infallible;
 use std::sync::Arc;
+use uuid::Uuid;
 
+/// Every route group of the API, as served by `main`
 pub fn create_routes(
-    processor: Arc<IntegratedProcessor>,
-    search_executor: Arc<SearchExecutor>,
+    search_engine: Arc<SearchEngine>,
+    processor: Arc<DocumentProcessor>,
+    history: Arc<SearchHistory>,
+    analytics: Arc<QueryAnalytics>,
+    webhooks: Arc<WebhookDispatcher>,
+    expander: Arc<QueryExpander>,
+    api_keys: ApiKeys,
+    config: &ProcessingConfig,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    let search = warp::path("search")
+    let upload = warp::path!("documents")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_document_processor(processor.clone()))
+        .and_then(|document, processor| handle_document_upload(processor, document));
+
+    search_routes(search_engine.clone(), history.clone(), api_keys.clone())
+        .or(suggest_routes(search_engine, history.clone(), api_keys.clone()))
+        .or(feedback_routes(history.clone(), api_keys.clone()))
+        .or(history_routes(history, api_keys.clone()))
+        .or(upload)
+        .or(bulk_routes(processor.clone(), config))
+        .or(document_routes(processor, api_keys.clone()))
+        .or(webhook_routes(webhooks, api_keys.clone()))
+        .or(analytics_routes(analytics, api_keys.clone()))
+        .or(synonym_routes(expander, api_keys))
+}
+
+/// Task status, live status streams and dead-letter management for the
+/// persistent processing queue. Dead letters are for admins only.
+pub fn document_routes(
+    processor: Arc<DocumentProcessor>,
+    api_keys: ApiKeys,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let status = warp::path!("documents" / "status" / String)
+        .and(warp::get())
+        .and(with_document_processor(processor.clone()))
+        .and_then(|id, processor| handle_status_check(processor, id));
+
+    let batch = warp::path!("documents" / "batches" / String)
+        .and(warp::get())
+        .and(with_document_processor(processor.clone()))
+        .and_then(handle_batch_status);
+
+    // `/events` streams over SSE and `/ws` over a WebSocket, for a task or a batch
+    let topic = warp::path!("documents" / "status" / String / ..)
+        .map(StatusTopic::Task)
+        .or(warp::path!("documents" / "batches" / String / ..).map(StatusTopic::Batch))
+        .unify();
+
+    let events = topic.clone()
+        .and(warp::path!("events"))
+        .and(warp::get())
+        .and(with_document_processor(processor.clone()))
+        .and_then(handle_status_events);
+
+    let socket = topic
+        .and(warp::path!("ws"))
+        .and(warp::ws())
+        .and(with_document_processor(processor.clone()))
+        .and_then(handle_status_socket);
+
+    let dead_letters = warp::path!("documents" / "dead-letters")
+        .and(warp::get())
+        .and(with_admin(api_keys.clone()))
+        .and(warp::query::<PageQuery>())
+        .and(with_document_processor(processor.clone()))
+        .and_then(handle_dead_letters);
+
+    let retry = warp::path!("documents" / "dead-letters" / String / "retry")
+        .and(warp::post())
+        .and(with_admin(api_keys))
+        .and(with_document_processor(processor))
+        .and_then(handle_retry_task);
+
+    status.or(batch).or(events).or(socket).or(dead_letters).or(retry)
+}
+
+/// `POST /documents/bulk` as multipart/form-data or NDJSON. Files join one
+/// batch whose id is returned for `/documents/batches/{id}`.
+pub fn bulk_routes(
+    processor: Arc<DocumentProcessor>,
+    config: &ProcessingConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let (max_file_size, max_body_size) = (config.max_document_size, config.max_bulk_request_size);
+
+    let ndjson = warp::path!("documents" / "bulk")
+        .and(warp::post())
+        .and(warp::query::<BulkQuery>())
+        .and(ndjson_content_type())
+        .and(warp::body::stream())
+        .and(with_document_processor(processor.clone()))
+        .and_then(move |query, body, processor| {
+            handle_bulk_ndjson(query, body, processor, max_file_size, max_body_size)
+        });
+
+    let multipart = warp::path!("documents" / "bulk")
+        .and(warp::post())
+        .and(warp::query::<BulkQuery>())
+        .and(warp::multipart::form().max_length(max_body_size))
+        .and(with_document_processor(processor))
+        .and_then(move |query, form, processor| handle_bulk_multipart(query, form, processor, max_file_size));
+
+    ndjson.or(multipart)
+}
+
+fn ndjson_content_type() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::<String>("content-type")
+        .and_then(|content_type: String| async move {
+            let mime = content_type.split(';').next().unwrap_or_default().trim().to_lowercase();
+            match mime.as_str() {
+                "application/x-ndjson" | "application/jsonl" => Ok(()),
+                _ => Err(warp::reject()),
+            }
+        })
+        .untuple_one()
+}
+
+/// Webhook subscriptions and their delivery logs, for admins only
+pub fn webhook_routes(
+    webhooks: Arc<WebhookDispatcher>,
+    api_keys: ApiKeys,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let create = warp::path!("webhooks")
+        .and(warp::post())
+        .and(with_admin(api_keys.clone()))
+        .and(warp::body::json())
+        .and(with_webhooks(webhooks.clone()))
+        .and_then(handle_create_webhook);
+
+    let list = warp::path!("webhooks")
+        .and(warp::get())
+        .and(with_admin(api_keys.clone()))
+        .and(with_webhooks(webhooks.clone()))
+        .and_then(handle_list_webhooks);
+
+    let delete = warp::path!("webhooks" / String)
+        .and(warp::delete())
+        .and(with_admin(api_keys.clone()))
+        .and(with_webhooks(webhooks.clone()))
+        .and_then(handle_delete_webhook);
+
+    let deliveries = warp::path!("webhooks" / String / "deliveries")
+        .and(warp::get())
+        .and(with_admin(api_keys))
+        .and(warp::query::<PageQuery>())
+        .and(with_webhooks(webhooks))
+        .and_then(handle_webhook_deliveries);
+
+    create.or(list).or(delete).or(deliveries)
+}
+
+/// Search, recorded in the caller's history
+pub fn search_routes(
+    search_engine: Arc<SearchEngine>,
+    history: Arc<SearchHistory>,
+    api_keys: ApiKeys,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("search")
         .and(warp::get())
         .and(warp::query::<SearchQuery>())
-        .and(with_search_executor(search_executor.clone()))
-        .and_then(handle_search);
+        .and(with_user(api_keys))
+        .and(with_search_engine(search_engine))
+        .and(with_search_history(history))
+        .and_then(handle_search)
+}
 
-    let upload = warp::path("documents")
+/// Type-ahead completions
+pub fn suggest_routes(
+    search_engine: Arc<SearchEngine>,
+    history: Arc<SearchHistory>,
+    api_keys: ApiKeys,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("suggest")
+        .and(warp::get())
+        .and(warp::query::<SuggestQuery>())
+        .and(with_user(api_keys))
+        .and(with_search_engine(search_engine))
+        .and(with_search_history(history))
+        .and_then(handle_suggest)
+}
+
+/// Clicks on and ratings of search results, by `query_id`, from the user who
+/// searched
+pub fn feedback_routes(
+    history: Arc<SearchHistory>,
+    api_keys: ApiKeys,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("feedback")
         .and(warp::post())
         .and(warp::body::json())
-        .and(with_processor(processor.clone()))
-        .and_then(handle_upload);
+        .and(with_user(api_keys))
+        .and(with_search_history(history))
+        .and_then(handle_feedback)
+}
 
-    let status = warp::path!("documents" / "status" / String)
+/// The caller's own search history, which they may delete
+pub fn history_routes(
+    history: Arc<SearchHistory>,
+    api_keys: ApiKeys,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let list = warp::path!("history")
         .and(warp::get())
-        .and(with_processor(processor.clone()))
-        .and_then(handle_status);
+        .and(warp::query::<PageQuery>())
+        .and(with_user(api_keys.clone()))
+        .and(with_search_history(history.clone()))
+        .and_then(handle_search_history);
+
+    let clear = warp::path!("history")
+        .and(warp::delete())
+        .and(with_user(api_keys.clone()))
+        .and(with_search_history(history.clone()))
+        .and_then(handle_clear_search_history);
+
+    let delete = warp::path!("history" / String)
+        .and(warp::delete())
+        .and(with_user(api_keys))
+        .and(with_search_history(history))
+        .and_then(handle_delete_search_history_entry);
+
+    list.or(clear).or(delete)
+}
+
+/// Who the `Authorization: Bearer` API key acts for. Requests without the
+/// header are anonymous; an unknown or revoked key is rejected.
+fn with_caller(api_keys: ApiKeys) -> impl Filter<Extract = (Option<Caller>,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and_then(move |header: Option<String>| {
+            let api_keys = api_keys.clone();
+            async move {
+                let Some(header) = header else {
+                    return Ok(None);
+                };
+                let key = bearer_token(&header).ok_or_else(|| {
+                    warp::reject::custom(ApiError::AuthError("Expected a Bearer API key".to_string()))
+                })?;
+                match api_keys.authenticate(key).await {
+                    Ok(Some(caller)) => Ok(Some(caller)),
+                    Ok(None) => Err(warp::reject::custom(ApiError::AuthError("Invalid API key".to_string()))),
+                    Err(e) => Err(warp::reject::custom(ApiError::DatabaseError(e))),
+                }
+            }
+        })
+}
 
-    search.or(upload).or(status)
+/// The user the API key acts for, if any
+fn with_user(api_keys: ApiKeys) -> impl Filter<Extract = (Option<Uuid>,), Error = Rejection> + Clone {
+    with_caller(api_keys).map(|caller: Option<Caller>| caller.map(|caller| caller.user_id))
 }
 
-async fn handle_upload(
-    upload: DocumentUpload,
-    processor: Arc<IntegratedProcessor>,
-) -> Result<impl Reply, Rejection> {
-    match processor.process_and_index(upload).await {
-        Ok(processed) => Ok(warp::reply::json(&processed)),
-        Err(e) => Err(warp::reject::custom(ApiError(e.to_string()))),
-    }
+/// Only lets requests with an admin API key through
+fn with_admin(api_keys: ApiKeys) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    with_caller(api_keys)
+        .and_then(|caller: Option<Caller>| async move {
+            match caller {
+                Some(caller) if caller.admin => Ok(()),
+                Some(_) => Err(warp::reject::custom(ApiError::Forbidden("An admin API key is required".to_string()))),
+                None => Err(warp::reject::custom(ApiError::AuthError("An admin API key is required".to_string()))),
+            }
+        })
+        .untuple_one()
 }
 
-// main.rs
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Setup search system
-    let (processor, search_executor) = setup_search_system().await?;
+fn with_search_history(
+    history: Arc<SearchHistory>,
+) -> impl Filter<Extract = (Arc<SearchHistory>,), Error = Infallible> + Clone {
+    warp::any().map(move || history.clone())
+}
 
-    // Create routes
-    let routes = create_routes(processor, search_executor);
+fn with_search_engine(
+    search_engine: Arc<SearchEngine>,
+) -> impl Filter<Extract = (Arc<SearchEngine>,), Error = Infallible> + Clone {
+    warp::any().map(move || search_engine.clone())
+}
 
-    // Start server
-    warp::serve(routes)
-        .run(([127, 0, 0, 1], 3030))
-        .await;
+/// Reports on what is searched for, what is not found and how fast. They
+/// show every user's queries, so only admin keys may read them.
+pub fn analytics_routes(
+    analytics: Arc<QueryAnalytics>,
+    api_keys: ApiKeys,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let top = warp::path!("analytics" / "queries" / "top")
+        .and(warp::get())
+        .and(with_admin(api_keys.clone()))
+        .and(warp::query::<AnalyticsQuery>())
+        .and(with_analytics(analytics.clone()))
+        .and_then(handle_top_queries);
+
+    let zero_results = warp::path!("analytics" / "queries" / "zero-results")
+        .and(warp::get())
+        .and(with_admin(api_keys.clone()))
+        .and(warp::query::<AnalyticsQuery>())
+        .and(with_analytics(analytics.clone()))
+        .and_then(handle_zero_result_queries);
+
+    let click_through = warp::path!("analytics" / "queries" / "click-through")
+        .and(warp::get())
+        .and(with_admin(api_keys.clone()))
+        .and(warp::query::<AnalyticsQuery>())
+        .and(with_analytics(analytics.clone()))
+        .and_then(handle_click_through);
+
+    let latency = warp::path!("analytics" / "latency")
+        .and(warp::get())
+        .and(with_admin(api_keys))
+        .and(warp::query::<AnalyticsQuery>())
+        .and(with_analytics(analytics))
+        .and_then(handle_latency);
+
+    top.or(zero_results).or(click_through).or(latency)
+}
+
+fn with_analytics(
+    analytics: Arc<QueryAnalytics>,
+) -> impl Filter<Extract = (Arc<QueryAnalytics>,), Error = Infallible> + Clone {
+    warp::any().map(move || analytics.clone())
+}
+
+/// Admin management of the query expansion synonym list. Changes are
+/// written to the synonyms file, so only admin keys may use these.
+pub fn synonym_routes(
+    expander: Arc<QueryExpander>,
+    api_keys: ApiKeys,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let list = warp::path!("admin" / "synonyms")
+        .and(warp::get())
+        .and(with_admin(api_keys.clone()))
+        .and(with_query_expander(expander.clone()))
+        .and_then(handle_list_synonyms);
+
+    let create = warp::path!("admin" / "synonyms")
+        .and(warp::post())
+        .and(with_admin(api_keys.clone()))
+        .and(warp::body::json())
+        .and(with_query_expander(expander.clone()))
+        .and_then(handle_create_synonym);
+
+    let reload = warp::path!("admin" / "synonyms" / "reload")
+        .and(warp::post())
+        .and(with_admin(api_keys.clone()))
+        .and(with_query_expander(expander.clone()))
+        .and_then(handle_reload_synonyms);
+
+    let delete = warp::path!("admin" / "synonyms" / String)
+        .and(warp::delete())
+        .and(with_admin(api_keys))
+        .and(with_query_expander(expander))
+        .and_then(handle_delete_synonym);
+
+    list.or(create).or(reload).or(delete)
+}
+
+fn with_query_expander(
+    expander: Arc<QueryExpander>,
+) -> impl Filter<Extract = (Arc<QueryExpander>,), Error = Infallible> + Clone {
+    warp::any().map(move || expander.clone())
+}
+
+fn with_webhooks(
+    webhooks: Arc<WebhookDispatcher>,
+) -> impl Filter<Extract = (Arc<WebhookDispatcher>,), Error = Infallible> + Clone {
+    warp::any().map(move || webhooks.clone())
+}
 
-    Ok(())
+fn with_document_processor(
+    processor: Arc<DocumentProcessor>,
+) -> impl Filter<Extract = (Arc<DocumentProcessor>,), Error = Infallible> + Clone {
+    warp::any().map(move || processor.clone())
 }
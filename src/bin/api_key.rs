@@ -0,0 +1,90 @@
+//! Issue and revoke API keys.
+//!
+//! ```text
+//! api_key issue --user <uuid> [--name NAME] [--admin] [--database-url URL]
+//! api_key revoke --key <key> [--database-url URL]
+//! ```
+//!
+//! The database URL defaults to `DATABASE_URL`. An issued key is printed once;
+//! only its hash is stored. `--admin` keys may also use the management routes.
+
+use modern_search_engine::api::auth::ApiKeys;
+
+use anyhow::{Context, Result};
+use sqlx::postgres::PgPoolOptions;
+use uuid::Uuid;
+
+enum Command {
+    Issue { user_id: Uuid, name: Option<String>, admin: bool },
+    Revoke { key: String },
+}
+
+struct Args {
+    database_url: String,
+    command: Command,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut database_url = std::env::var("DATABASE_URL").ok();
+    let mut user_id = None;
+    let mut name = None;
+    let mut key = None;
+    let mut admin = false;
+
+    let mut args = std::env::args().skip(1);
+    let command = args.next().context("Pass issue or revoke")?;
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().with_context(|| format!("{} needs a value", flag));
+        match flag.as_str() {
+            "--database-url" => database_url = Some(value()?),
+            "--user" => user_id = Some(Uuid::parse_str(&value()?).context("--user must be a UUID")?),
+            "--name" => name = Some(value()?),
+            "--key" => key = Some(value()?),
+            "--admin" => admin = true,
+            _ => anyhow::bail!("Unknown argument {}", flag),
+        }
+    }
+
+    let command = match command.as_str() {
+        "issue" => Command::Issue {
+            user_id: user_id.context("Pass --user for the key to act for")?,
+            name,
+            admin,
+        },
+        "revoke" => Command::Revoke {
+            key: key.context("Pass --key to revoke")?,
+        },
+        _ => anyhow::bail!("Unknown command {}", command),
+    };
+
+    Ok(Args {
+        database_url: database_url.context("Pass --database-url or set DATABASE_URL")?,
+        command,
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = parse_args()?;
+
+    let pool = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&args.database_url)
+        .await?;
+    let keys = ApiKeys::new(pool);
+
+    match args.command {
+        Command::Issue { user_id, name, admin } => {
+            let key = keys.issue(user_id, name.as_deref(), admin).await?;
+            println!("{}", key);
+        }
+        Command::Revoke { key } => {
+            if !keys.revoke(&key).await? {
+                anyhow::bail!("No active key matches");
+            }
+            println!("Revoked");
+        }
+    }
+
+    Ok(())
+}
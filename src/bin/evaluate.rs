@@ -0,0 +1,140 @@
+//! Measure search relevance against judged queries, optionally comparing
+//! two configurations.
+//!
+//! ```text
+//! evaluate --judgments judgments.csv [--config baseline.toml] [--compare candidate.toml]
+//!          [--text-index data/index] [--k 10] [--depth 100] [--json]
+//! ```
+//!
+//! Judgments have a `query,document_id,relevance` header (tab separated for
+//! `.tsv`). Reports NDCG@k, MRR, recall@k and MAP; with `--compare`, the
+//! same judgments are run against both configurations side by side.
+
+use modern_search_engine::config::Config;
+use modern_search_engine::search::cross_encoder::Reranker;
+use modern_search_engine::search::engine::{SearchConfig, SearchEngine};
+use modern_search_engine::search::evaluation::{evaluate, Comparison, EvaluationReport, Judgments};
+use modern_search_engine::search::ranking::RankingModel;
+use modern_search_engine::search::TextIndex;
+use modern_search_engine::vector::store::VectorStore;
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+struct Args {
+    judgments: PathBuf,
+    config: Option<PathBuf>,
+    compare: Option<PathBuf>,
+    text_index: Option<PathBuf>,
+    k: usize,
+    depth: usize,
+    json: bool,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut judgments = None;
+    let mut config = None;
+    let mut compare = None;
+    let mut text_index = None;
+    let mut k = 10;
+    let mut depth = 100;
+    let mut json = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().with_context(|| format!("{} needs a value", flag));
+        match flag.as_str() {
+            "--judgments" => judgments = Some(PathBuf::from(value()?)),
+            "--config" => config = Some(PathBuf::from(value()?)),
+            "--compare" => compare = Some(PathBuf::from(value()?)),
+            "--text-index" => text_index = Some(PathBuf::from(value()?)),
+            "--k" => k = value()?.parse()?,
+            "--depth" => depth = value()?.parse()?,
+            "--json" => json = true,
+            _ => anyhow::bail!("Unknown argument {}", flag),
+        }
+    }
+
+    Ok(Args {
+        judgments: judgments.context("Pass --judgments with the judged queries")?,
+        config,
+        compare,
+        text_index,
+        k,
+        depth,
+        json,
+    })
+}
+
+fn load_config(path: Option<&PathBuf>) -> Result<Config> {
+    match path {
+        Some(path) => Config::from_file(&path.to_string_lossy())
+            .with_context(|| format!("Failed to load config from {}", path.display())),
+        None => Ok(Config::default()),
+    }
+}
+
+/// An engine searching the shared index the way `config` ranks results
+fn build_engine(
+    config: &Config,
+    vector_store: Arc<RwLock<VectorStore>>,
+    text_index: Option<Arc<TextIndex>>,
+) -> Result<SearchEngine> {
+    let mut engine = SearchEngine::new(vector_store, SearchConfig::from(&config.search));
+    if let Some(text_index) = text_index {
+        engine = engine.with_text_index(text_index);
+    }
+    if let Some(path) = &config.search.ranking.model_path {
+        engine = engine.with_ranking_model(Arc::new(RankingModel::load(path)?));
+    }
+    if config.search.cross_encoder.enabled {
+        engine = engine.with_reranker(Arc::new(Reranker::from_config(config.search.cross_encoder.clone())?));
+    }
+    Ok(engine)
+}
+
+fn name(path: Option<&PathBuf>) -> String {
+    path.and_then(|path| path.file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = parse_args()?;
+
+    let judgments = Judgments::load(&args.judgments)?;
+    if judgments.is_empty() {
+        anyhow::bail!("No judgments in {}", args.judgments.display());
+    }
+
+    let vector_store = Arc::new(RwLock::new(VectorStore::new().await?));
+    let text_index = match &args.text_index {
+        Some(path) => Some(Arc::new(TextIndex::open(path)?)),
+        None => None,
+    };
+
+    let mut reports: Vec<EvaluationReport> = Vec::new();
+    for path in std::iter::once(args.config.as_ref()).chain(args.compare.as_ref().map(Some)) {
+        let config = load_config(path)?;
+        let engine = build_engine(&config, vector_store.clone(), text_index.clone())?;
+        reports.push(evaluate(&name(path), &engine, &judgments, args.k, args.depth).await?);
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+        return Ok(());
+    }
+
+    match reports.as_slice() {
+        [baseline, candidate] => print!("{}", Comparison { baseline, candidate }),
+        reports => reports.iter().for_each(|report| print!("{}", report)),
+    }
+    if let Some(skipped) = reports.first().map(|report| report.skipped.len()).filter(|skipped| *skipped > 0) {
+        println!("\n{} queries without a relevant document were skipped", skipped);
+    }
+
+    Ok(())
+}
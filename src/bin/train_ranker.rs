@@ -0,0 +1,85 @@
+//! Train the learning-to-rank model from logged searches and feedback.
+//!
+//! ```text
+//! train_ranker --output models/ranker.json [--database-url URL] [--days 90]
+//!              [--trees 100] [--depth 4] [--learning-rate 0.1] [--min-samples-leaf 5]
+//! ```
+//!
+//! The database URL defaults to `DATABASE_URL`. Point `search.ranking.model_path`
+//! at the output to rerank with it.
+
+use modern_search_engine::config::HistoryConfig;
+use modern_search_engine::search::history::SearchHistory;
+use modern_search_engine::search::lambdamart::{train, TrainingParams};
+
+use anyhow::{Context, Result};
+use sqlx::postgres::PgPoolOptions;
+use std::path::PathBuf;
+
+struct Args {
+    database_url: String,
+    output: PathBuf,
+    days: u32,
+    params: TrainingParams,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut database_url = std::env::var("DATABASE_URL").ok();
+    let mut output = None;
+    let mut days = 90;
+    let mut params = TrainingParams::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().with_context(|| format!("{} needs a value", flag));
+        match flag.as_str() {
+            "--database-url" => database_url = Some(value()?),
+            "--output" => output = Some(PathBuf::from(value()?)),
+            "--days" => days = value()?.parse()?,
+            "--trees" => params.trees = value()?.parse()?,
+            "--depth" => params.max_depth = value()?.parse()?,
+            "--learning-rate" => params.learning_rate = value()?.parse()?,
+            "--min-samples-leaf" => params.min_samples_leaf = value()?.parse()?,
+            _ => anyhow::bail!("Unknown argument {}", flag),
+        }
+    }
+
+    Ok(Args {
+        database_url: database_url.context("Pass --database-url or set DATABASE_URL")?,
+        output: output.context("Pass --output for the trained model")?,
+        days,
+        params,
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = parse_args()?;
+
+    let pool = PgPoolOptions::new()
+        .max_connections(2)
+        .connect(&args.database_url)
+        .await?;
+    let history = SearchHistory::new(pool, HistoryConfig::default());
+
+    let queries: Vec<_> = history
+        .logged_searches(args.days)
+        .await?
+        .iter()
+        .filter_map(|search| search.training_query())
+        .collect();
+    if queries.is_empty() {
+        anyhow::bail!("No searches with logged features and feedback in the last {} days", args.days);
+    }
+    let samples: usize = queries.iter().map(|query| query.labels.len()).sum();
+    println!("Training on {} searches with {} labelled results", queries.len(), samples);
+
+    let model = train(&queries, &args.params);
+    if let Some(parent) = args.output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&args.output, serde_json::to_string_pretty(&model.to_json())?)?;
+    println!("Wrote {} trees to {}", model.trees.len(), args.output.display());
+
+    Ok(())
+}
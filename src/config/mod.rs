@@ -12,6 +12,8 @@ pub struct Config {
     pub vector: VectorConfig,
     pub processing: ProcessingConfig,
     pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +37,126 @@ pub struct SearchConfig {
     pub vector_weight: f32,
     pub text_weight: f32,
     pub use_query_expansion: bool,
+    #[serde(default = "default_collapse_duplicates")]
+    pub collapse_duplicates: bool,
+    #[serde(default)]
+    pub expansion: ExpansionConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub ranking: RankingConfig,
+    #[serde(default)]
+    pub cross_encoder: CrossEncoderConfig,
+    #[serde(default)]
+    pub text_index: TextIndexConfig,
+}
+
+fn default_collapse_duplicates() -> bool {
+    true
+}
+
+/// Synonym and embedding-neighbour expansion of search queries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpansionConfig {
+    pub synonyms_path: Option<PathBuf>,  // Solr format; admin edits are saved back to it
+    pub synonym_weight: f32,             // Boost of synonyms relative to the query's own terms
+    pub neighbour_expansion: bool,       // Also add the closest vocabulary words by embedding
+    pub neighbour_vocabulary: usize,     // Most frequent words embedded for neighbour lookup
+    pub neighbour_min_similarity: f32,
+    pub max_neighbours: usize,           // Per query word
+    pub neighbour_weight: f32,           // Scaled by similarity
+}
+
+impl Default for ExpansionConfig {
+    fn default() -> Self {
+        Self {
+            synonyms_path: None,
+            synonym_weight: 0.5,
+            neighbour_expansion: false,
+            neighbour_vocabulary: 5000,
+            neighbour_min_similarity: 0.75,
+            max_neighbours: 2,
+            neighbour_weight: 0.3,
+        }
+    }
+}
+
+/// Search history and the popular query suggestions drawn from it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryConfig {
+    pub refresh_interval_secs: u64,  // Popular searches are recomputed this often
+    pub popular_limit: usize,        // Most popular queries loaded for completion
+    pub click_window_days: u32,      // Clicks counted towards click-through rates
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            refresh_interval_secs: 300,
+            popular_limit: 10_000,
+            click_window_days: 30,
+        }
+    }
+}
+
+/// The tantivy full-text index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextIndexConfig {
+    pub path: PathBuf,
+    pub commit_interval_ms: u64,  // Changes become searchable at most this late
+    pub commit_batch: usize,      // Or as soon as this many have built up
+}
+
+impl Default for TextIndexConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("data/index"),
+            commit_interval_ms: 1000,
+            commit_batch: 1000,
+        }
+    }
+}
+
+/// Learning-to-rank reranking of the top results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankingConfig {
+    pub model_path: Option<PathBuf>,  // Trained by `train_ranker`, or an XGBoost JSON dump
+    pub rerank_depth: usize,
+    pub log_features: bool,           // Record features with every search, for training
+}
+
+impl Default for RankingConfig {
+    fn default() -> Self {
+        Self {
+            model_path: None,
+            rerank_depth: 50,
+            log_features: true,
+        }
+    }
+}
+
+/// Cross-encoder rescoring of the top hybrid results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossEncoderConfig {
+    pub enabled: bool,
+    pub model_path: PathBuf,  // config.json, vocab.txt and rust_model.ot
+    pub top_n: usize,         // Candidates rescored per search
+    pub budget_ms: u64,       // No batch is started after this; the rest keep their order
+    pub batch_size: usize,
+    pub max_length: usize,    // Tokens of query and passage together
+}
+
+impl Default for CrossEncoderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model_path: PathBuf::from("models/ms-marco-MiniLM-L-6-v2"),
+            top_n: 20,
+            budget_ms: 150,
+            batch_size: 8,
+            max_length: 512,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,6 +173,129 @@ pub struct ProcessingConfig {
     pub supported_types: Vec<String>,
     pub processing_threads: usize,
     pub cleanup_interval: u64,
+    #[serde(default)]
+    pub ocr: OcrConfig,
+    #[serde(default)]
+    pub dedup: DedupConfig,
+    #[serde(default)]
+    pub queue: QueueConfig,
+    #[serde(default = "default_max_bulk_request_size")]
+    pub max_bulk_request_size: u64,  // Whole multipart or NDJSON body of a bulk upload; split larger imports by batch_id
+    #[serde(default)]
+    pub watch_directories: Vec<PathBuf>,  // Kept in sync with the index while the server runs
+}
+
+fn default_max_bulk_request_size() -> u64 {
+    64 * 1024 * 1024
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrConfig {
+    pub enabled: bool,
+    pub tesseract_path: PathBuf,
+    pub languages: String,  // Tesseract language codes, e.g. "eng+deu"
+    pub timeout_secs: u64,
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tesseract_path: PathBuf::from("tesseract"),
+            languages: "eng".to_string(),
+            timeout_secs: 120,
+        }
+    }
+}
+
+/// Retry and polling behaviour of the persistent processing queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueConfig {
+    pub max_attempts: u32,       // Attempts before a job is dead-lettered
+    pub base_backoff_secs: u64,  // Doubled after every failed attempt
+    pub max_backoff_secs: u64,
+    pub poll_interval_ms: u64,   // Idle workers re-check this often
+    pub stale_after_secs: u64,   // Jobs locked longer than this are reclaimed
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_backoff_secs: 5,
+            max_backoff_secs: 3600,
+            poll_interval_ms: 1000,
+            stale_after_secs: 900,
+        }
+    }
+}
+
+/// What to do when a document's content exactly matches a stored one
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicatePolicy {
+    Reject,  // Fail the ingestion
+    Skip,    // Return the existing document's id
+    Merge,   // Fold tags and metadata into the existing document
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupConfig {
+    pub enabled: bool,
+    pub policy: DuplicatePolicy,
+    pub near_duplicates: bool,
+    pub max_distance: u32,  // SimHash bits that may differ, at most 3
+}
+
+impl DedupConfig {
+    /// Near-duplicate candidates are found by one of four 16-bit SimHash
+    /// bands matching exactly, which only holds for up to 3 differing bits
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_distance > 3 {
+            return Err(ConfigError::Message(format!(
+                "processing.dedup.max_distance must be at most 3, got {}",
+                self.max_distance
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        // Off unless configured, so re-uploading the same content still
+        // creates a document as it always has
+        Self {
+            enabled: false,
+            policy: DuplicatePolicy::Skip,
+            near_duplicates: true,
+            max_distance: 3,
+        }
+    }
+}
+
+/// Delivery behaviour of outbound webhooks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub max_attempts: u32,       // Attempts before a delivery is given up
+    pub base_backoff_secs: u64,  // Doubled after every failed attempt
+    pub max_backoff_secs: u64,
+    pub timeout_secs: u64,       // Per request
+    pub poll_interval_ms: u64,
+    pub concurrency: usize,      // Deliveries in flight per dispatcher
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            base_backoff_secs: 10,
+            max_backoff_secs: 6 * 3600,
+            timeout_secs: 10,
+            poll_interval_ms: 1000,
+            concurrency: 16,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,14 +323,20 @@ impl Config {
             
             .build()?;
 
-        config_builder.try_deserialize()
+        config_builder.try_deserialize::<Self>()?.validated()
     }
 
     pub fn from_file(path: &str) -> Result<Self, ConfigError> {
         ConfigBuilder::builder()
             .add_source(File::with_name(path))
             .build()?
-            .try_deserialize()
+            .try_deserialize::<Self>()?
+            .validated()
+    }
+
+    fn validated(self) -> Result<Self, ConfigError> {
+        self.processing.dedup.validate()?;
+        Ok(self)
     }
 }
 
@@ -108,6 +359,12 @@ impl Default for Config {
                 vector_weight: 0.6,
                 text_weight: 0.4,
                 use_query_expansion: true,
+                collapse_duplicates: true,
+                expansion: ExpansionConfig::default(),
+                history: HistoryConfig::default(),
+                ranking: RankingConfig::default(),
+                cross_encoder: CrossEncoderConfig::default(),
+                text_index: TextIndexConfig::default(),
             },
             vector: VectorConfig {
                 dimension: 384,
@@ -117,9 +374,31 @@ impl Default for Config {
             },
             processing: ProcessingConfig {
                 max_document_size: 10 * 1024 * 1024, // 10MB
-                supported_types: vec!["pdf".to_string(), "html".to_string(), "txt".to_string()],
+                supported_types: vec![
+                    "pdf".to_string(),
+                    "html".to_string(),
+                    "txt".to_string(),
+                    "md".to_string(),
+                    "markdown".to_string(),
+                    "eml".to_string(),
+                    "mbox".to_string(),
+                    "csv".to_string(),
+                    "json".to_string(),
+                    "ndjson".to_string(),
+                    "jsonl".to_string(),
+                    "png".to_string(),
+                    "jpg".to_string(),
+                    "jpeg".to_string(),
+                    "tif".to_string(),
+                    "tiff".to_string(),
+                ],
                 processing_threads: num_cpus::get(),
                 cleanup_interval: 3600, // 1 hour
+                ocr: OcrConfig::default(),
+                dedup: DedupConfig::default(),
+                queue: QueueConfig::default(),
+                max_bulk_request_size: default_max_bulk_request_size(),
+                watch_directories: Vec::new(),
             },
             telemetry: TelemetryConfig {
                 metrics_enabled: true,
@@ -127,6 +406,7 @@ impl Default for Config {
                 log_level: "info".to_string(),
                 metrics_port: 9090,
             },
+            webhooks: WebhookConfig::default(),
         }
     }
 }
\ No newline at end of file
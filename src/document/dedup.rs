@@ -0,0 +1,184 @@
+use crate::config::{DedupConfig, DuplicatePolicy};
+use crate::document::store::DocumentStore;
+use crate::document::{Document, DocumentMetadata};
+use crate::utils::helpers::calculate_hash;
+use anyhow::Result;
+use tokio::sync::RwLock;
+
+/// Words per shingle
+const SHINGLE_SIZE: usize = 4;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// 64-bit SimHash over word shingles. Near-identical texts get fingerprints
+/// a few bits apart; the hash is stable across runs so it can be stored.
+pub fn simhash(text: &str) -> u64 {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    let shingle_size = SHINGLE_SIZE.min(words.len().max(1));
+    let mut weights = [0i32; 64];
+
+    for shingle in words.windows(shingle_size) {
+        let hash = fnv1a(shingle.join(" ").as_bytes());
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash >> bit & 1 == 1 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0u64, |fingerprint, (bit, _)| fingerprint | 1 << bit)
+}
+
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// The four 16-bit bands used to find candidates in the index
+pub fn simhash_bands(fingerprint: u64) -> [i64; 4] {
+    [0, 16, 32, 48].map(|shift| (fingerprint >> shift & 0xffff) as i64)
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// Fold an incoming duplicate's metadata into the stored document's:
+/// tags are unioned, unknown keys added, and missing fields filled in
+pub fn merge_metadata(existing: &mut DocumentMetadata, incoming: DocumentMetadata) {
+    for tag in incoming.tags {
+        if !existing.tags.contains(&tag) {
+            existing.tags.push(tag);
+        }
+    }
+    for (key, value) in incoming.custom_metadata {
+        existing.custom_metadata.entry(key).or_insert(value);
+    }
+
+    if existing.author.is_none() {
+        existing.author = incoming.author;
+    }
+    if existing.language.is_none() {
+        existing.language = incoming.language;
+    }
+    existing.last_modified = existing.last_modified.max(incoming.last_modified);
+}
+
+/// What `store_deduplicated` did with a document
+#[derive(Debug, Clone, PartialEq)]
+pub enum Deduplicated {
+    Stored(String),
+    Skipped(String),  // Exact duplicate of this stored document
+    Merged(String),   // Metadata folded into this stored document
+}
+
+impl Deduplicated {
+    pub fn id(&self) -> &str {
+        match self {
+            Deduplicated::Stored(id) | Deduplicated::Skipped(id) | Deduplicated::Merged(id) => id,
+        }
+    }
+}
+
+/// Store `document` unless its content exactly matches a stored one, in
+/// which case the policy decides. Near duplicates are stored pointing at
+/// the document they resemble. The check and the insert happen under one
+/// write lock, so two copies arriving together cannot both be stored.
+pub async fn store_deduplicated(
+    store: &RwLock<DocumentStore>,
+    mut document: Document,
+    config: &DedupConfig,
+) -> Result<Deduplicated> {
+    let mut store = store.write().await;
+
+    if config.enabled {
+        if let Some(mut existing) = store.find_by_content_hash(&calculate_hash(&document.content)).await? {
+            return match config.policy {
+                DuplicatePolicy::Reject => anyhow::bail!("Duplicate of document {}", existing.id),
+                DuplicatePolicy::Skip => Ok(Deduplicated::Skipped(existing.id)),
+                DuplicatePolicy::Merge => {
+                    merge_metadata(&mut existing.metadata, document.metadata);
+                    let id = existing.id.clone();
+                    store.update_document(&id, existing).await?;
+                    Ok(Deduplicated::Merged(id))
+                }
+            };
+        }
+
+        if config.near_duplicates {
+            document.metadata.duplicate_of = store
+                .find_near_duplicate(&document.content, config.max_distance)
+                .await?;
+        }
+    }
+
+    Ok(Deduplicated::Stored(store.store_document(document).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simhash_near_duplicates() {
+        let policy = "Employees may work remotely up to three days per week with manager approval. \
+                      Requests must be submitted through the HR portal at least one week in advance.";
+        let revised = "Employees may work remotely up to three days per week with manager approval. \
+                       Requests must be submitted through the HR portal at least two weeks in advance.";
+        let unrelated = "The quarterly revenue report shows growth in the European market driven by \
+                         new enterprise contracts and lower churn among existing customers.";
+
+        let a = simhash(policy);
+        assert_eq!(a, simhash(&policy.to_uppercase()));
+        assert!(hamming_distance(a, simhash(revised)) < hamming_distance(a, simhash(unrelated)));
+        assert!(hamming_distance(a, simhash(unrelated)) > 10);
+    }
+
+    #[test]
+    fn test_bands_share_one_within_three_bits() {
+        let fingerprint = 0x0123_4567_89ab_cdef_u64;
+        let flipped = fingerprint ^ (1 << 3) ^ (1 << 20) ^ (1 << 40);
+
+        let shared = simhash_bands(fingerprint)
+            .iter()
+            .zip(simhash_bands(flipped).iter())
+            .filter(|(a, b)| a == b)
+            .count();
+        assert_eq!(shared, 1);
+    }
+
+    #[test]
+    fn test_merge_metadata() {
+        let mut existing = DocumentMetadata {
+            tags: vec!["hr".to_string()],
+            ..Default::default()
+        };
+        existing.custom_metadata.insert("source_path".to_string(), "/a.pdf".to_string());
+
+        let mut incoming = DocumentMetadata {
+            author: Some("People Team".to_string()),
+            tags: vec!["hr".to_string(), "policy".to_string()],
+            ..Default::default()
+        };
+        incoming.custom_metadata.insert("source_path".to_string(), "/b.pdf".to_string());
+        incoming.custom_metadata.insert("department".to_string(), "HR".to_string());
+
+        merge_metadata(&mut existing, incoming);
+
+        assert_eq!(existing.tags, vec!["hr", "policy"]);
+        assert_eq!(existing.author.as_deref(), Some("People Team"));
+        assert_eq!(existing.custom_metadata["source_path"], "/a.pdf");
+        assert_eq!(existing.custom_metadata["department"], "HR");
+    }
+}
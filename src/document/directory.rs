@@ -0,0 +1,392 @@
+use crate::config::ProcessingConfig;
+use crate::document::ingestion::IngestionOptions;
+use crate::document::ProcessingStatus;
+use anyhow::{Context, Result};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::WalkBuilder;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct DirectoryOptions {
+    pub include: Vec<String>,        // Globs a file must match; empty means every file
+    pub exclude: Vec<String>,        // Globs that skip files and prune directories
+    pub max_file_size: u64,          // Applies to archive entries as well
+    pub max_archive_size: u64,       // Uncompressed total read from one archive
+    pub respect_ignore_files: bool,  // .gitignore, .ignore and .git/info/exclude
+    pub follow_links: bool,
+    pub extract_archives: bool,
+    pub ingestion: IngestionOptions,
+}
+
+impl DirectoryOptions {
+    pub fn from_config(config: &ProcessingConfig) -> Self {
+        Self {
+            max_file_size: config.max_document_size as u64,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for DirectoryOptions {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_file_size: 10 * 1024 * 1024,
+            max_archive_size: 1024 * 1024 * 1024,
+            respect_ignore_files: true,
+            follow_links: false,
+            extract_archives: true,
+            ingestion: IngestionOptions::default(),
+        }
+    }
+}
+
+/// Sent once per file or archive entry as a directory ingestion advances.
+/// `status` is `Completed` or `Failed` for files that were attempted and
+/// `Processing` with the overall percentage for files that were skipped.
+#[derive(Debug, Clone)]
+pub struct IngestionProgress {
+    pub path: String,
+    pub completed: usize,
+    pub total: usize,
+    pub status: ProcessingStatus,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DirectoryIngestionReport {
+    pub ingested: Vec<IngestedFile>,
+    pub skipped: Vec<FileIssue>,
+    pub failed: Vec<FileIssue>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IngestedFile {
+    pub path: String,  // Archive entries are reported as `archive.zip!/entry`
+    pub document_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileIssue {
+    pub path: String,
+    pub reason: String,
+}
+
+pub(crate) enum FileOutcome {
+    Ingested(String),
+    Skipped(String),
+    Failed(String),
+}
+
+impl FileOutcome {
+    pub(crate) fn status(&self, completed: usize, total: usize) -> ProcessingStatus {
+        match self {
+            FileOutcome::Ingested(id) => ProcessingStatus::Completed(id.clone()),
+            FileOutcome::Failed(e) => ProcessingStatus::Failed(e.clone()),
+            FileOutcome::Skipped(_) => ProcessingStatus::Processing(completed as f32 / total.max(1) as f32 * 100.0),
+        }
+    }
+}
+
+impl DirectoryIngestionReport {
+    pub(crate) fn record(&mut self, path: String, outcome: FileOutcome) {
+        match outcome {
+            FileOutcome::Ingested(document_id) => self.ingested.push(IngestedFile { path, document_id }),
+            FileOutcome::Skipped(reason) => self.skipped.push(FileIssue { path, reason }),
+            FileOutcome::Failed(reason) => self.failed.push(FileIssue { path, reason }),
+        }
+    }
+}
+
+/// Include/exclude globs, matched against paths under the walk root
+#[derive(Clone)]
+pub(crate) struct PathFilter {
+    include: Override,
+    exclude: Override,
+}
+
+impl PathFilter {
+    pub fn new(root: &Path, include: &[String], exclude: &[String]) -> Result<Self> {
+        let mut includes = OverrideBuilder::new(root);
+        for glob in include {
+            includes.add(glob).with_context(|| format!("Invalid include glob '{}'", glob))?;
+        }
+
+        // Override globs are whitelists unless negated
+        let mut excludes = OverrideBuilder::new(root);
+        for glob in exclude {
+            excludes.add(&format!("!{}", glob)).with_context(|| format!("Invalid exclude glob '{}'", glob))?;
+        }
+
+        Ok(Self {
+            include: includes.build()?,
+            exclude: excludes.build()?,
+        })
+    }
+
+    pub fn allows(&self, path: &Path) -> bool {
+        !self.exclude.matched(path, false).is_ignore() && !self.include.matched(path, false).is_ignore()
+    }
+}
+
+/// All files under `root` that pass the ignore files and globs. Archives
+/// bypass the include globs so that their entries can be matched instead.
+pub(crate) fn walk(
+    root: &Path,
+    options: &DirectoryOptions,
+    filter: &PathFilter,
+    max_depth: Option<usize>,
+) -> Result<Vec<PathBuf>> {
+    let walker = WalkBuilder::new(root)
+        .max_depth(max_depth)
+        .git_ignore(options.respect_ignore_files)
+        .git_exclude(options.respect_ignore_files)
+        .ignore(options.respect_ignore_files)
+        .git_global(false)
+        .require_git(false)
+        .follow_links(options.follow_links)
+        .overrides(filter.exclude.clone())
+        .build();
+
+    let mut files = Vec::new();
+    for entry in walker {
+        let entry = entry?;
+        if !entry.file_type().map_or(false, |t| t.is_file()) {
+            continue;
+        }
+
+        let path = entry.into_path();
+        let is_archive = options.extract_archives && ArchiveKind::detect(&path).is_some();
+        if is_archive || filter.allows(&path) {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+
+        if name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) struct ArchiveEntry {
+    pub name: String,
+    pub data: Option<Vec<u8>>,  // None when the entry is over the size limit
+}
+
+/// Hand the regular files of an archive that `accept` lets through to
+/// `on_entry` one at a time, as they are decompressed. Fails once the
+/// entries read add up to more than `max_total` bytes.
+pub(crate) fn read_archive(
+    path: &Path,
+    kind: ArchiveKind,
+    max_size: u64,
+    max_total: u64,
+    accept: impl Fn(&str) -> bool,
+    mut on_entry: impl FnMut(ArchiveEntry) -> Result<()>,
+) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut total = 0;
+    let mut read = |name: String, reader: &mut dyn Read, size: u64| -> Result<()> {
+        let (bytes_read, data) = read_entry(reader, size, max_size)?;
+        total += bytes_read;
+        if total > max_total {
+            anyhow::bail!("Archive expands to more than {} bytes", max_total);
+        }
+        on_entry(ArchiveEntry { name, data })
+    };
+
+    match kind {
+        ArchiveKind::Zip => {
+            let mut archive = zip::ZipArchive::new(BufReader::new(file))?;
+            for index in 0..archive.len() {
+                let mut entry = archive.by_index(index)?;
+                if entry.is_dir() {
+                    continue;
+                }
+                // Skips names that would escape the archive root
+                let Some(name) = entry.enclosed_name().map(|n| n.to_string_lossy().into_owned()) else {
+                    continue;
+                };
+                if !accept(&name) {
+                    continue;
+                }
+
+                let size = entry.size();
+                read(name, &mut entry, size)?;
+            }
+        }
+        ArchiveKind::Tar | ArchiveKind::TarGz => {
+            let reader: Box<dyn Read> = match kind {
+                ArchiveKind::TarGz => Box::new(flate2::read::GzDecoder::new(BufReader::new(file))),
+                _ => Box::new(BufReader::new(file)),
+            };
+
+            let mut archive = tar::Archive::new(reader);
+            for entry in archive.entries()? {
+                let mut entry = entry?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let name = entry.path()?.to_string_lossy().into_owned();
+                if !accept(&name) {
+                    continue;
+                }
+
+                let size = entry.size();
+                read(name, &mut entry, size)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Declared sizes can lie, so reads are capped as well. Returns the bytes
+/// decompressed along with the data, if it fit.
+fn read_entry(reader: impl Read, declared_size: u64, max_size: u64) -> Result<(u64, Option<Vec<u8>>)> {
+    if declared_size > max_size {
+        return Ok((0, None));
+    }
+
+    let mut data = Vec::with_capacity(declared_size as usize);
+    reader.take(max_size + 1).read_to_end(&mut data)?;
+    Ok((data.len() as u64, (data.len() as u64 <= max_size).then_some(data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn collect(
+        path: &Path,
+        kind: ArchiveKind,
+        max_size: u64,
+        max_total: u64,
+        accept: impl Fn(&str) -> bool,
+    ) -> Result<Vec<ArchiveEntry>> {
+        let mut entries = Vec::new();
+        read_archive(path, kind, max_size, max_total, accept, |entry| {
+            entries.push(entry);
+            Ok(())
+        })?;
+        Ok(entries)
+    }
+
+    #[test]
+    fn test_walk_respects_ignore_files_and_globs() {
+        let root = tempfile::tempdir().unwrap();
+        let write = |name: &str| {
+            let path = root.path().join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"content").unwrap();
+        };
+        std::fs::write(root.path().join(".gitignore"), "build/\n*.log\n").unwrap();
+        write("notes.md");
+        write("report.pdf");
+        write("debug.log");
+        write("build/out.md");
+        write("drafts/old.md");
+        write("bundle.zip");
+
+        let options = DirectoryOptions {
+            include: vec!["*.md".to_string()],
+            exclude: vec!["drafts/**".to_string()],
+            ..Default::default()
+        };
+        let filter = PathFilter::new(root.path(), &options.include, &options.exclude).unwrap();
+        let files: Vec<String> = walk(root.path(), &options, &filter, None)
+            .unwrap()
+            .iter()
+            .map(|p| p.strip_prefix(root.path()).unwrap().to_string_lossy().into_owned())
+            .collect();
+
+        assert_eq!(files, vec!["bundle.zip", "notes.md"]);
+    }
+
+    #[test]
+    fn test_read_zip_with_size_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("docs.zip");
+
+        let mut writer = zip::ZipWriter::new(File::create(&path).unwrap());
+        for (name, data) in [("a.txt", "short"), ("big.txt", "much longer content"), ("skip.bin", "x")] {
+            writer.start_file(name, zip::write::FileOptions::default()).unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let entries = collect(&path, ArchiveKind::Zip, 10, 1024, |name| name.ends_with(".txt")).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].data.as_deref(), Some(b"short".as_slice()));
+        assert!(entries[1].data.is_none());
+    }
+
+    #[test]
+    fn test_read_tar_gz() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("docs.tar.gz");
+
+        let encoder = flate2::write::GzEncoder::new(File::create(&path).unwrap(), flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, "nested/readme.md", b"hello".as_slice()).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        assert_eq!(ArchiveKind::detect(&path), Some(ArchiveKind::TarGz));
+        let entries = collect(&path, ArchiveKind::TarGz, 1024, 1024, |_| true).unwrap();
+
+        assert_eq!(entries[0].name, "nested/readme.md");
+        assert_eq!(entries[0].data.as_deref(), Some(b"hello".as_slice()));
+    }
+
+    #[test]
+    fn test_read_zip_stops_at_total_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bomb.zip");
+
+        let mut writer = zip::ZipWriter::new(File::create(&path).unwrap());
+        for index in 0..10 {
+            writer.start_file(format!("{}.txt", index), zip::write::FileOptions::default()).unwrap();
+            writer.write_all(&[b'a'; 100]).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut seen = 0;
+        let result = read_archive(&path, ArchiveKind::Zip, 100, 350, |_| true, |_| {
+            seen += 1;
+            Ok(())
+        });
+
+        assert!(result.is_err());
+        assert_eq!(seen, 3);
+    }
+}
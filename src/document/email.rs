@@ -0,0 +1,336 @@
+use crate::document::extractor::{ContentExtractor, ExtractionContext};
+use crate::document::{Attachment, DocumentMetadata, ExtractedContent};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use mailparse::{DispositionType, MailHeaderMap, ParsedMail};
+
+/// Headers copied verbatim into `custom_metadata` for threading and display
+const METADATA_HEADERS: [(&str, &str); 7] = [
+    ("From", "from"),
+    ("To", "to"),
+    ("Cc", "cc"),
+    ("Date", "date"),
+    ("Message-ID", "message_id"),
+    ("In-Reply-To", "in_reply_to"),
+    ("References", "references"),
+];
+
+pub struct EmailExtractor;
+
+impl EmailExtractor {
+    pub fn new() -> Self {
+        EmailExtractor
+    }
+
+    /// Extract a single RFC 5322 message (`.eml`)
+    pub fn extract(&self, content: &[u8]) -> Result<ExtractedContent> {
+        let mail = mailparse::parse_mail(content).context("Failed to parse email message")?;
+
+        let subject = mail.headers.get_first_value("Subject");
+        let mut metadata = DocumentMetadata {
+            source_type: "email".to_string(),
+            author: mail.headers.get_first_value("From"),
+            ..Default::default()
+        };
+
+        for (header, key) in METADATA_HEADERS {
+            if let Some(value) = mail.headers.get_first_value(header) {
+                let value = match key {
+                    "message_id" | "in_reply_to" => normalize_message_id(&value),
+                    _ => value.trim().to_string(),
+                };
+                metadata.custom_metadata.insert(key.to_string(), value);
+            }
+        }
+
+        if let Some(date) = mail.headers
+            .get_first_value("Date")
+            .and_then(|d| mailparse::dateparse(&d).ok())
+            .and_then(|ts| Utc.timestamp_opt(ts, 0).single())
+        {
+            metadata.created_at = date;
+            metadata.last_modified = date;
+        }
+
+        let mut parts = MessageParts::default();
+        collect_parts(&mail, &mut parts)?;
+
+        let body = match (parts.plain, parts.html) {
+            (Some(plain), _) => plain,
+            (None, Some(html)) => html_to_text(&html),
+            (None, None) => String::new(),
+        };
+
+        // Participants and subject are part of the searchable text
+        let mut text = String::new();
+        for (header, key) in &METADATA_HEADERS[..3] {
+            if let Some(value) = metadata.custom_metadata.get(*key) {
+                text.push_str(&format!("{}: {}\n", header, value));
+            }
+        }
+        if let Some(subject) = &subject {
+            text.push_str(&format!("Subject: {}\n", subject));
+        }
+        text.push('\n');
+        text.push_str(body.trim());
+
+        Ok(ExtractedContent {
+            title: subject,
+            text,
+            metadata,
+            attachments: parts.attachments,
+            ocr: None,
+        })
+    }
+
+    /// Extract an mbox archive; every message becomes a `.eml` attachment of
+    /// the mailbox document so it is ingested as a child
+    pub fn extract_mbox(&self, content: &[u8]) -> Result<ExtractedContent> {
+        let messages = split_mbox(content);
+        let mut text = String::new();
+        let mut attachments = Vec::with_capacity(messages.len());
+
+        for (index, message) in messages.into_iter().enumerate() {
+            if let Ok((headers, _)) = mailparse::parse_headers(&message) {
+                text.push_str(&format!(
+                    "{} - {}\n",
+                    headers.get_first_value("Subject").unwrap_or_default(),
+                    headers.get_first_value("From").unwrap_or_default(),
+                ));
+            }
+
+            attachments.push(Attachment {
+                filename: format!("message-{}.eml", index + 1),
+                content_type: Some("message/rfc822".to_string()),
+                data: message,
+            });
+        }
+
+        let mut metadata = DocumentMetadata {
+            source_type: "mbox".to_string(),
+            ..Default::default()
+        };
+        metadata.custom_metadata.insert("message_count".to_string(), attachments.len().to_string());
+
+        Ok(ExtractedContent {
+            title: None,
+            text,
+            metadata,
+            attachments,
+            ocr: None,
+        })
+    }
+}
+
+impl Default for EmailExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ContentExtractor for EmailExtractor {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn mime_types(&self) -> Vec<&str> {
+        vec!["message/rfc822"]
+    }
+
+    fn extensions(&self) -> Vec<&str> {
+        vec!["eml"]
+    }
+
+    async fn extract(&self, content: &[u8], _context: &ExtractionContext<'_>) -> Result<ExtractedContent> {
+        EmailExtractor::extract(self, content)
+    }
+}
+
+/// Registry adapter for mailbox archives, see [`EmailExtractor::extract_mbox`]
+#[derive(Default)]
+pub struct MboxExtractor;
+
+impl MboxExtractor {
+    pub fn new() -> Self {
+        MboxExtractor
+    }
+}
+
+#[async_trait]
+impl ContentExtractor for MboxExtractor {
+    fn name(&self) -> &str {
+        "mbox"
+    }
+
+    fn mime_types(&self) -> Vec<&str> {
+        vec!["application/mbox"]
+    }
+
+    fn extensions(&self) -> Vec<&str> {
+        vec!["mbox"]
+    }
+
+    async fn extract(&self, content: &[u8], _context: &ExtractionContext<'_>) -> Result<ExtractedContent> {
+        EmailExtractor::new().extract_mbox(content)
+    }
+}
+
+#[derive(Default)]
+struct MessageParts {
+    plain: Option<String>,
+    html: Option<String>,
+    attachments: Vec<Attachment>,
+}
+
+fn collect_parts(part: &ParsedMail, parts: &mut MessageParts) -> Result<()> {
+    let mimetype = part.ctype.mimetype.to_lowercase();
+
+    if mimetype.starts_with("multipart/") {
+        for subpart in &part.subparts {
+            collect_parts(subpart, parts)?;
+        }
+        return Ok(());
+    }
+
+    let disposition = part.get_content_disposition();
+    let filename = disposition.params.get("filename")
+        .or_else(|| part.ctype.params.get("name"))
+        .cloned();
+
+    let is_attachment = disposition.disposition == DispositionType::Attachment
+        || filename.is_some()
+        || mimetype == "message/rfc822";
+
+    if is_attachment {
+        let filename = filename.unwrap_or_else(|| match mimetype.as_str() {
+            "message/rfc822" => "attached-message.eml".to_string(),
+            _ => format!("attachment-{}", parts.attachments.len() + 1),
+        });
+
+        parts.attachments.push(Attachment {
+            filename,
+            content_type: Some(mimetype),
+            data: part.get_body_raw()?,
+        });
+    } else if mimetype == "text/plain" && parts.plain.is_none() {
+        parts.plain = Some(part.get_body()?);
+    } else if mimetype == "text/html" && parts.html.is_none() {
+        parts.html = Some(part.get_body()?);
+    }
+
+    Ok(())
+}
+
+fn html_to_text(html: &str) -> String {
+    let sanitized = ammonia::clean(html);
+    html2text::from_read(sanitized.as_bytes(), 80)
+}
+
+fn normalize_message_id(value: &str) -> String {
+    value.trim().trim_start_matches('<').trim_end_matches('>').to_string()
+}
+
+/// Split an mboxrd/mboxo archive on `From ` separator lines
+fn split_mbox(content: &[u8]) -> Vec<Vec<u8>> {
+    let mut messages = Vec::new();
+    let mut current: Option<Vec<u8>> = None;
+
+    for line in content.split_inclusive(|&b| b == b'\n') {
+        if line.starts_with(b"From ") {
+            if let Some(message) = current.take() {
+                messages.push(message);
+            }
+            current = Some(Vec::new());
+            continue;
+        }
+
+        if let Some(message) = current.as_mut() {
+            // Undo ">From " quoting applied by mbox writers
+            let quotes = line.iter().take_while(|&&b| b == b'>').count();
+            if quotes > 0 && line[quotes..].starts_with(b"From ") {
+                message.extend_from_slice(&line[1..]);
+            } else {
+                message.extend_from_slice(line);
+            }
+        }
+    }
+
+    if let Some(message) = current {
+        messages.push(message);
+    }
+
+    messages.retain(|message| message.iter().any(|b| !b.is_ascii_whitespace()));
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: &str = "From: Alice <alice@example.com>\r\n\
+To: support@example.com\r\n\
+Cc: bob@example.com\r\n\
+Subject: Invoice question\r\n\
+Date: Tue, 1 Oct 2024 10:00:00 +0000\r\n\
+Message-ID: <abc@example.com>\r\n\
+In-Reply-To: <xyz@example.com>\r\n\
+\r\n\
+Where is my invoice?\r\n";
+
+    #[test]
+    fn test_plain_message() {
+        let extracted = EmailExtractor::new().extract(PLAIN.as_bytes()).unwrap();
+        let meta = &extracted.metadata.custom_metadata;
+
+        assert_eq!(extracted.title.as_deref(), Some("Invoice question"));
+        assert_eq!(meta.get("message_id").unwrap(), "abc@example.com");
+        assert_eq!(meta.get("in_reply_to").unwrap(), "xyz@example.com");
+        assert_eq!(meta.get("cc").unwrap(), "bob@example.com");
+        assert!(extracted.text.contains("Where is my invoice?"));
+        assert!(extracted.text.contains("From: Alice <alice@example.com>"));
+        assert_eq!(extracted.metadata.created_at.to_rfc3339(), "2024-10-01T10:00:00+00:00");
+    }
+
+    #[test]
+    fn test_html_fallback_and_attachments() {
+        let message = "From: a@example.com\r\n\
+Subject: Report\r\n\
+Content-Type: multipart/mixed; boundary=\"b1\"\r\n\
+\r\n\
+--b1\r\n\
+Content-Type: text/html\r\n\
+\r\n\
+<p>Quarterly <b>numbers</b></p><script>alert(1)</script>\r\n\
+--b1\r\n\
+Content-Type: text/plain\r\n\
+Content-Disposition: attachment; filename=\"notes.txt\"\r\n\
+\r\n\
+attached notes\r\n\
+--b1--\r\n";
+
+        let extracted = EmailExtractor::new().extract(message.as_bytes()).unwrap();
+
+        assert!(extracted.text.contains("Quarterly numbers"));
+        assert!(!extracted.text.contains("alert"));
+        assert_eq!(extracted.attachments.len(), 1);
+        assert_eq!(extracted.attachments[0].filename, "notes.txt");
+    }
+
+    #[test]
+    fn test_split_mbox() {
+        let mbox = format!(
+            "From alice@example.com Tue Oct  1 10:00:00 2024\n{}\nFrom bob@example.com Tue Oct  1 11:00:00 2024\nSubject: Two\n\n>From the desk\n",
+            PLAIN
+        );
+        let messages = split_mbox(mbox.as_bytes());
+
+        assert_eq!(messages.len(), 2);
+        assert!(String::from_utf8_lossy(&messages[1]).contains("\nFrom the desk"));
+
+        let extracted = EmailExtractor::new().extract_mbox(mbox.as_bytes()).unwrap();
+        assert_eq!(extracted.attachments.len(), 2);
+        assert_eq!(extracted.metadata.custom_metadata.get("message_count").unwrap(), "2");
+    }
+}
@@ -0,0 +1,262 @@
+use crate::document::email::{EmailExtractor, MboxExtractor};
+use crate::document::html::HtmlExtractor;
+use crate::document::markdown::MarkdownExtractor;
+use crate::document::ocr::{ImageExtractor, OcrEngine};
+use crate::document::pdf::PdfExtractor;
+use crate::document::{DocumentMetadata, ExtractedContent};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// What an extractor knows about the input besides its bytes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExtractionContext<'a> {
+    pub filename: &'a str,
+    pub base_url: Option<&'a str>,  // Resolves relative links in markup formats
+    pub on_page: Option<PageProgress<'a>>,
+}
+
+/// Called with (page, pages) as paged formats work through their pages
+#[derive(Clone, Copy)]
+pub struct PageProgress<'a>(pub &'a (dyn Fn(u32, u32) + Send + Sync));
+
+impl PageProgress<'_> {
+    pub fn report(&self, page: u32, pages: u32) {
+        (self.0)(page, pages)
+    }
+}
+
+impl std::fmt::Debug for PageProgress<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PageProgress")
+    }
+}
+
+/// A format handler that can be registered with an [`ExtractorRegistry`]
+#[async_trait]
+pub trait ContentExtractor: Send + Sync {
+    fn name(&self) -> &str;
+    fn mime_types(&self) -> Vec<&str>;
+    fn extensions(&self) -> Vec<&str>;
+    async fn extract(&self, content: &[u8], context: &ExtractionContext<'_>) -> Result<ExtractedContent>;
+}
+
+/// Extractors keyed by MIME type and file extension. Lookups try the MIME
+/// type sniffed from the content first, then the extension, then fall back
+/// to plain text for anything that is valid UTF-8.
+#[derive(Clone)]
+pub struct ExtractorRegistry {
+    by_mime: HashMap<String, Arc<dyn ContentExtractor>>,
+    by_extension: HashMap<String, Arc<dyn ContentExtractor>>,
+    fallback: Arc<dyn ContentExtractor>,
+}
+
+impl ExtractorRegistry {
+    /// A registry with only the plain text fallback
+    pub fn new() -> Self {
+        Self {
+            by_mime: HashMap::new(),
+            by_extension: HashMap::new(),
+            fallback: Arc::new(PlainTextExtractor),
+        }
+    }
+
+    /// The built-in formats. Images are only handled when an OCR engine is given.
+    pub fn with_defaults(ocr_engine: Option<Arc<dyn OcrEngine>>) -> Self {
+        let mut registry = Self::new();
+        registry.register(Arc::new(PlainTextExtractor));
+        registry.register(Arc::new(HtmlExtractor::new()));
+        registry.register(Arc::new(MarkdownExtractor::new()));
+        registry.register(Arc::new(EmailExtractor::new()));
+        registry.register(Arc::new(MboxExtractor::new()));
+        registry.register(Arc::new(PdfExtractor::new(None)));
+        if let Some(engine) = ocr_engine {
+            registry.register_ocr(engine);
+        }
+        registry
+    }
+
+    /// Register an extractor for all of its MIME types and extensions,
+    /// replacing whatever was registered for them before
+    pub fn register(&mut self, extractor: Arc<dyn ContentExtractor>) {
+        for mime in extractor.mime_types() {
+            self.by_mime.insert(mime.to_lowercase(), extractor.clone());
+        }
+        for extension in extractor.extensions() {
+            self.by_extension.insert(extension.to_lowercase(), extractor.clone());
+        }
+    }
+
+    /// Route scanned PDFs and images through OCR
+    pub fn register_ocr(&mut self, engine: Arc<dyn OcrEngine>) {
+        self.register(Arc::new(PdfExtractor::new(Some(engine.clone()))));
+        self.register(Arc::new(ImageExtractor::new(engine)));
+    }
+
+    pub fn for_mime(&self, mime: &str) -> Option<Arc<dyn ContentExtractor>> {
+        self.by_mime.get(&mime.to_lowercase()).cloned()
+    }
+
+    pub fn resolve(&self, content: &[u8], filename: &str) -> Option<Arc<dyn ContentExtractor>> {
+        sniff_mime(content)
+            .and_then(|mime| self.for_mime(mime))
+            .or_else(|| {
+                extension(filename).and_then(|ext| self.by_extension.get(&ext).cloned())
+            })
+            .or_else(|| {
+                std::str::from_utf8(content).is_ok().then(|| self.fallback.clone())
+            })
+    }
+
+    pub async fn extract(&self, content: &[u8], context: &ExtractionContext<'_>) -> Result<ExtractedContent> {
+        let extractor = self.resolve(content, context.filename).ok_or_else(|| {
+            anyhow::anyhow!("No extractor registered for {}", context.filename)
+        })?;
+
+        extractor.extract(content, context).await
+    }
+}
+
+impl Default for ExtractorRegistry {
+    fn default() -> Self {
+        Self::with_defaults(None)
+    }
+}
+
+/// Treats the content as text, replacing invalid UTF-8
+pub struct PlainTextExtractor;
+
+#[async_trait]
+impl ContentExtractor for PlainTextExtractor {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn mime_types(&self) -> Vec<&str> {
+        vec!["text/plain"]
+    }
+
+    fn extensions(&self) -> Vec<&str> {
+        vec!["txt", "text", "log"]
+    }
+
+    async fn extract(&self, content: &[u8], _context: &ExtractionContext<'_>) -> Result<ExtractedContent> {
+        Ok(ExtractedContent {
+            title: None,
+            text: String::from_utf8_lossy(content).to_string(),
+            metadata: DocumentMetadata::default(),
+            attachments: Vec::new(),
+            ocr: None,
+        })
+    }
+}
+
+/// Headers that open an RFC 5322 message in practice
+const EMAIL_HEADERS: [&str; 6] = [
+    "return-path:", "received:", "message-id:", "mime-version:", "delivered-to:", "from:",
+];
+
+/// Best-effort MIME type from magic bytes. Formats without a reliable
+/// signature (Markdown, plain text) are left to the extension.
+pub fn sniff_mime(content: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: [(&[u8], &str); 6] = [
+        (b"%PDF-", "application/pdf"),
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xFF\xD8\xFF", "image/jpeg"),
+        (b"II*\0", "image/tiff"),
+        (b"MM\0*", "image/tiff"),
+        (b"PK\x03\x04", "application/zip"),
+    ];
+
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| content.starts_with(magic)) {
+        return Some(mime);
+    }
+
+    let head = String::from_utf8_lossy(&content[..content.len().min(512)]);
+    let head = head.trim_start_matches('\u{feff}').trim_start().to_lowercase();
+
+    if head.starts_with("<!doctype html") || head.starts_with("<html") {
+        Some("text/html")
+    } else if head.starts_with("from ") && head.lines().next().map_or(false, |l| l.contains('@')) {
+        Some("application/mbox")
+    } else if EMAIL_HEADERS.iter().any(|h| head.starts_with(h)) {
+        Some("message/rfc822")
+    } else {
+        None
+    }
+}
+
+fn extension(filename: &str) -> Option<String> {
+    std::path::Path::new(filename)
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .map(str::to_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct RtfExtractor;
+
+    #[async_trait]
+    impl ContentExtractor for RtfExtractor {
+        fn name(&self) -> &str {
+            "rtf"
+        }
+
+        fn mime_types(&self) -> Vec<&str> {
+            vec!["application/rtf"]
+        }
+
+        fn extensions(&self) -> Vec<&str> {
+            vec!["rtf"]
+        }
+
+        async fn extract(&self, _content: &[u8], _context: &ExtractionContext<'_>) -> Result<ExtractedContent> {
+            Ok(ExtractedContent {
+                title: None,
+                text: "rtf body".to_string(),
+                metadata: DocumentMetadata::default(),
+                attachments: Vec::new(),
+                ocr: None,
+            })
+        }
+    }
+
+    #[test]
+    fn test_sniff_mime() {
+        assert_eq!(sniff_mime(b"%PDF-1.7\n"), Some("application/pdf"));
+        assert_eq!(sniff_mime(b"\n  <!DOCTYPE html><html>"), Some("text/html"));
+        assert_eq!(sniff_mime(b"From alice@example.com Mon Jan 1\n"), Some("application/mbox"));
+        assert_eq!(sniff_mime(b"From the desk of the editor"), None);
+        assert_eq!(sniff_mime(b"Received: from mx\nSubject: hi\n"), Some("message/rfc822"));
+        assert_eq!(sniff_mime(b"# Heading"), None);
+    }
+
+    #[test]
+    fn test_content_wins_over_extension() {
+        let registry = ExtractorRegistry::default();
+
+        let html = registry.resolve(b"<html><body>hi</body></html>", "page.txt").unwrap();
+        let markdown = registry.resolve(b"# Title", "notes.md").unwrap();
+        let unknown = registry.resolve(b"plain words", "notes.unknown").unwrap();
+
+        assert_eq!(html.name(), "html");
+        assert_eq!(markdown.name(), "markdown");
+        assert_eq!(unknown.name(), "text");
+        assert!(registry.resolve(b"\x89PNG\r\n\x1a\n\xff\xfe", "scan.png").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_register_custom_format() {
+        let mut registry = ExtractorRegistry::default();
+        registry.register(Arc::new(RtfExtractor));
+
+        let context = ExtractionContext { filename: "letter.rtf", ..Default::default() };
+        let extracted = registry.extract(b"{\\rtf1 hello}", &context).await.unwrap();
+
+        assert_eq!(extracted.text, "rtf body");
+    }
+}
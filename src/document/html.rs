@@ -0,0 +1,535 @@
+use crate::document::extractor::{ContentExtractor, ExtractionContext};
+use crate::document::{DocumentMetadata, ExtractedContent, OutboundLink};
+use crate::utils::helpers::parse_date;
+use anyhow::Result;
+use async_trait::async_trait;
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+/// Elements whose subtree never contributes to the main content
+const BOILERPLATE_TAGS: [&str; 14] = [
+    "script", "style", "noscript", "template", "iframe", "svg", "canvas",
+    "nav", "footer", "header", "aside", "form", "button", "select",
+];
+
+const BLOCK_TAGS: [&str; 23] = [
+    "p", "div", "article", "section", "main", "li", "ul", "ol", "dl", "dt", "dd",
+    "pre", "blockquote", "table", "tr", "td", "th", "h1", "h2", "h3", "h4", "h5", "h6",
+];
+
+const POSITIVE_HINTS: [&str; 9] = [
+    "article", "body", "content", "entry", "main", "page", "post", "story", "text",
+];
+
+const NEGATIVE_HINTS: [&str; 15] = [
+    "banner", "breadcrumb", "comment", "cookie", "footer", "menu", "meta", "nav",
+    "popup", "promo", "related", "share", "sidebar", "social", "sponsor",
+];
+
+/// Paragraph-like elements that seed readability scores
+const SCORED_TAGS: [&str; 4] = ["p", "pre", "td", "blockquote"];
+
+/// Below this many characters a candidate is not trusted over the full body
+const MIN_CONTENT_LENGTH: usize = 140;
+
+pub struct HtmlExtractor;
+
+impl HtmlExtractor {
+    pub fn new() -> Self {
+        HtmlExtractor
+    }
+
+    /// Extract the main content and metadata of an HTML page. `base_url` is
+    /// used to resolve relative links when the page has no `<base>` or canonical URL.
+    pub fn extract(&self, html: &str, base_url: Option<&str>) -> Result<ExtractedContent> {
+        let document = Html::parse_document(html);
+        let root = document.root_element();
+
+        let mut metadata = DocumentMetadata {
+            source_type: "html".to_string(),
+            ..Default::default()
+        };
+
+        let mut title = select_text(&document, "title");
+
+        if let Some(lang) = root.value().attr("lang") {
+            metadata.language = Some(normalize_language(lang));
+        }
+
+        self.harvest_meta(&document, &mut metadata);
+        self.harvest_json_ld(&document, &mut metadata, &mut title);
+
+        let title = title
+            .or_else(|| metadata.custom_metadata.get("og:title").cloned())
+            .or_else(|| select_text(&document, "h1"));
+
+        // Resolve links against <base>, then canonical, then the caller's URL
+        let base = select_attr(&document, "base[href]", "href")
+            .or_else(|| metadata.custom_metadata.get("canonical_url").cloned())
+            .or_else(|| base_url.map(str::to_string))
+            .and_then(|u| Url::parse(&u).ok());
+        if let Some(url) = base_url {
+            metadata.custom_metadata.insert("url".to_string(), url.to_string());
+        }
+        metadata.links = extract_links(&document, base.as_ref());
+
+        let text = match find_main_content(&document) {
+            Some(main) => {
+                let text = element_text(main);
+                if text.chars().count() >= MIN_CONTENT_LENGTH {
+                    text
+                } else {
+                    body_text(&document)
+                }
+            }
+            None => body_text(&document),
+        };
+
+        Ok(ExtractedContent {
+            title,
+            text,
+            metadata,
+            attachments: Vec::new(),
+            ocr: None,
+        })
+    }
+
+    fn harvest_meta(&self, document: &Html, metadata: &mut DocumentMetadata) {
+        let selector = Selector::parse("meta[content]").unwrap();
+
+        for meta in document.select(&selector) {
+            let element = meta.value();
+            let content = element.attr("content").unwrap_or_default().trim();
+            if content.is_empty() {
+                continue;
+            }
+
+            let key = element.attr("name")
+                .or_else(|| element.attr("property"))
+                .map(str::to_lowercase);
+
+            match key.as_deref() {
+                Some("description") => {
+                    metadata.custom_metadata.insert("description".to_string(), content.to_string());
+                }
+                Some("keywords") => {
+                    push_tags(&mut metadata.tags, content.split(','));
+                }
+                Some("author") | Some("article:author") => {
+                    metadata.author.get_or_insert_with(|| content.to_string());
+                }
+                Some("article:published_time") => {
+                    if let Some(date) = parse_date(content) {
+                        metadata.created_at = date;
+                    }
+                }
+                Some("article:modified_time") => {
+                    if let Some(date) = parse_date(content) {
+                        metadata.last_modified = date;
+                    }
+                }
+                Some("article:tag") => push_tags(&mut metadata.tags, std::iter::once(content)),
+                Some(key) if key.starts_with("og:") || key.starts_with("twitter:") => {
+                    metadata.custom_metadata.insert(key.to_string(), content.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        if metadata.language.is_none() {
+            if let Some(locale) = metadata.custom_metadata.get("og:locale") {
+                metadata.language = Some(normalize_language(locale));
+            }
+        }
+
+        if let Some(canonical) = select_attr(document, "link[rel=canonical][href]", "href") {
+            metadata.custom_metadata.insert("canonical_url".to_string(), canonical);
+        }
+    }
+
+    fn harvest_json_ld(&self, document: &Html, metadata: &mut DocumentMetadata, title: &mut Option<String>) {
+        let selector = Selector::parse(r#"script[type="application/ld+json"]"#).unwrap();
+        let mut blocks = Vec::new();
+
+        for script in document.select(&selector) {
+            let raw = script.text().collect::<String>();
+            let Ok(value) = serde_json::from_str::<serde_json::Value>(&raw) else { continue };
+
+            // Top-level arrays and @graph both hold several entities
+            let entities: Vec<&serde_json::Value> = match &value {
+                serde_json::Value::Array(items) => items.iter().collect(),
+                other => match other.get("@graph").and_then(|g| g.as_array()) {
+                    Some(graph) => graph.iter().collect(),
+                    None => vec![other],
+                },
+            };
+
+            for entity in entities {
+                if title.is_none() {
+                    *title = entity.get("headline").and_then(|v| v.as_str()).map(str::to_string);
+                }
+                if metadata.author.is_none() {
+                    metadata.author = entity.get("author").and_then(json_ld_name);
+                }
+                if let Some(date) = entity.get("datePublished").and_then(|v| v.as_str()).and_then(parse_date) {
+                    metadata.created_at = date;
+                }
+                if let Some(date) = entity.get("dateModified").and_then(|v| v.as_str()).and_then(parse_date) {
+                    metadata.last_modified = date;
+                }
+                match entity.get("keywords") {
+                    Some(serde_json::Value::String(keywords)) => push_tags(&mut metadata.tags, keywords.split(',')),
+                    Some(serde_json::Value::Array(keywords)) => {
+                        push_tags(&mut metadata.tags, keywords.iter().filter_map(|k| k.as_str()))
+                    }
+                    _ => {}
+                }
+            }
+
+            blocks.push(value);
+        }
+
+        if !blocks.is_empty() {
+            let json_ld = if blocks.len() == 1 {
+                blocks.remove(0)
+            } else {
+                serde_json::Value::Array(blocks)
+            };
+            metadata.custom_metadata.insert("json_ld".to_string(), json_ld.to_string());
+        }
+    }
+}
+
+impl Default for HtmlExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ContentExtractor for HtmlExtractor {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn mime_types(&self) -> Vec<&str> {
+        vec!["text/html", "application/xhtml+xml"]
+    }
+
+    fn extensions(&self) -> Vec<&str> {
+        vec!["html", "htm", "xhtml"]
+    }
+
+    async fn extract(&self, content: &[u8], context: &ExtractionContext<'_>) -> Result<ExtractedContent> {
+        HtmlExtractor::extract(self, &String::from_utf8_lossy(content), context.base_url)
+    }
+}
+
+/// Readability-style scoring: paragraphs award points to their parent and
+/// grandparent, and the best container after link-density damping wins
+fn find_main_content(document: &Html) -> Option<ElementRef> {
+    let body_selector = Selector::parse("body").unwrap();
+    let body = document.select(&body_selector).next()?;
+
+    let mut scores: HashMap<ego_tree::NodeId, f32> = HashMap::new();
+
+    for node in body.descendants() {
+        let Some(element) = ElementRef::wrap(node) else { continue };
+        if !SCORED_TAGS.contains(&element.value().name()) || is_excluded(element) {
+            continue;
+        }
+
+        let text = element_text(element);
+        let length = text.chars().count();
+        if length < 25 {
+            continue;
+        }
+
+        let score = 1.0 + text.matches(',').count() as f32 + (length as f32 / 100.0).min(3.0);
+
+        let parent = element.parent().and_then(ElementRef::wrap);
+        let grandparent = parent.and_then(|p| p.parent()).and_then(ElementRef::wrap);
+
+        if let Some(parent) = parent {
+            *scores.entry(parent.id()).or_insert_with(|| initial_score(parent)) += score;
+        }
+        if let Some(grandparent) = grandparent {
+            *scores.entry(grandparent.id()).or_insert_with(|| initial_score(grandparent)) += score / 2.0;
+        }
+    }
+
+    scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let element = document.tree.get(id).and_then(ElementRef::wrap)?;
+            Some((element, score * (1.0 - link_density(element))))
+        })
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(element, _)| element)
+}
+
+fn initial_score(element: ElementRef) -> f32 {
+    let tag_score = match element.value().name() {
+        "article" | "main" => 10.0,
+        "div" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    tag_score + class_weight(element)
+}
+
+fn class_weight(element: ElementRef) -> f32 {
+    let value = element.value();
+    let hints = format!(
+        "{} {}",
+        value.attr("class").unwrap_or_default(),
+        value.id().unwrap_or_default(),
+    )
+    .to_lowercase();
+
+    let mut weight = 0.0;
+    if NEGATIVE_HINTS.iter().any(|hint| hints.contains(hint)) {
+        weight -= 25.0;
+    }
+    if POSITIVE_HINTS.iter().any(|hint| hints.contains(hint)) {
+        weight += 25.0;
+    }
+    weight
+}
+
+fn is_excluded(element: ElementRef) -> bool {
+    element.ancestors()
+        .filter_map(ElementRef::wrap)
+        .chain(std::iter::once(element))
+        .any(is_boilerplate)
+}
+
+fn is_boilerplate(element: ElementRef) -> bool {
+    let value = element.value();
+    BOILERPLATE_TAGS.contains(&value.name())
+        || value.attr("aria-hidden") == Some("true")
+        || value.attr("hidden").is_some()
+        || matches!(value.attr("role"), Some("navigation") | Some("banner") | Some("contentinfo"))
+}
+
+fn link_density(element: ElementRef) -> f32 {
+    let total = element_text(element).chars().count();
+    if total == 0 {
+        return 0.0;
+    }
+
+    let link_selector = Selector::parse("a").unwrap();
+    let linked: usize = element.select(&link_selector)
+        .map(|a| a.text().collect::<String>().trim().chars().count())
+        .sum();
+
+    (linked as f32 / total as f32).min(1.0)
+}
+
+fn body_text(document: &Html) -> String {
+    let selector = Selector::parse("body").unwrap();
+    document.select(&selector)
+        .next()
+        .map(element_text)
+        .unwrap_or_default()
+}
+
+/// Visible text of an element with boilerplate subtrees skipped and
+/// block elements separated by newlines
+fn element_text(element: ElementRef) -> String {
+    let mut raw = String::new();
+    collect_text(*element, &mut raw);
+
+    raw.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn collect_text(node: NodeRef<Node>, out: &mut String) {
+    for child in node.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(element) => {
+                let Some(child_ref) = ElementRef::wrap(child) else { continue };
+                if is_boilerplate(child_ref) {
+                    continue;
+                }
+                if element.name() == "br" {
+                    out.push('\n');
+                    continue;
+                }
+
+                let block = BLOCK_TAGS.contains(&element.name());
+                if block {
+                    out.push('\n');
+                }
+                collect_text(child, out);
+                if block {
+                    out.push('\n');
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn extract_links(document: &Html, base: Option<&Url>) -> Vec<OutboundLink> {
+    let selector = Selector::parse("a[href]").unwrap();
+    let mut seen = HashSet::new();
+    let mut links = Vec::new();
+
+    for anchor in document.select(&selector) {
+        let href = anchor.value().attr("href").unwrap_or_default().trim();
+        if href.is_empty() || href.starts_with('#') {
+            continue;
+        }
+
+        let resolved = match base {
+            Some(base) => base.join(href),
+            None => Url::parse(href),
+        };
+        let Ok(mut url) = resolved else { continue };
+        if !matches!(url.scheme(), "http" | "https") {
+            continue;
+        }
+        url.set_fragment(None);
+
+        if seen.insert(url.to_string()) {
+            let text = anchor.text().collect::<Vec<_>>().join(" ");
+            links.push(OutboundLink {
+                url: url.to_string(),
+                text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+                rel: anchor.value().attr("rel").map(str::to_string),
+            });
+        }
+    }
+
+    links
+}
+
+fn select_text(document: &Html, selector: &str) -> Option<String> {
+    let selector = Selector::parse(selector).unwrap();
+    document.select(&selector)
+        .next()
+        .map(|el| el.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|text| !text.is_empty())
+}
+
+fn select_attr(document: &Html, selector: &str, attr: &str) -> Option<String> {
+    let selector = Selector::parse(selector).unwrap();
+    document.select(&selector)
+        .next()
+        .and_then(|el| el.value().attr(attr))
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn json_ld_name(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(name) => Some(name.clone()),
+        serde_json::Value::Array(items) => items.iter().find_map(json_ld_name),
+        other => other.get("name").and_then(|n| n.as_str()).map(str::to_string),
+    }
+}
+
+fn push_tags<'a>(tags: &mut Vec<String>, values: impl Iterator<Item = &'a str>) {
+    for value in values {
+        let tag = value.trim().to_string();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+}
+
+/// `en-US` and `en_US` both become `en`
+fn normalize_language(lang: &str) -> String {
+    lang.split(['-', '_'])
+        .next()
+        .unwrap_or(lang)
+        .trim()
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en-GB">
+<head>
+  <title>Leave Policy | Intranet</title>
+  <meta name="description" content="How to request annual leave">
+  <meta name="keywords" content="hr, leave, policy">
+  <meta name="author" content="People Team">
+  <meta property="og:title" content="Leave Policy">
+  <meta property="og:type" content="article">
+  <link rel="canonical" href="https://intranet.example.com/hr/leave">
+  <script type="application/ld+json">{"@type": "Article", "datePublished": "2024-01-15", "keywords": ["holidays"]}</script>
+</head>
+<body>
+  <nav><a href="/">Home</a> <a href="/hr">HR</a> <a href="/it">IT</a> <a href="/finance">Finance</a></nav>
+  <div class="sidebar-menu"><a href="/a">Quick link one</a><a href="/b">Quick link two</a></div>
+  <article class="post-content">
+    <h1>Annual leave</h1>
+    <p>Employees accrue annual leave monthly, starting from their first day, at a rate set in their contract.</p>
+    <p>Requests should be submitted in the HR portal at least two weeks in advance, and approved by a manager.</p>
+    <p>See the <a href="forms#request">request forms</a> for details on carry-over, public holidays and unpaid leave.</p>
+  </article>
+  <footer>Copyright Example Corp. <a href="mailto:hr@example.com">Contact</a></footer>
+  <script>trackPageView();</script>
+</body>
+</html>"#;
+
+    #[test]
+    fn test_main_content_extraction() {
+        let extracted = HtmlExtractor::new().extract(PAGE, None).unwrap();
+
+        assert!(extracted.text.contains("Employees accrue annual leave monthly"));
+        assert!(extracted.text.contains("request forms"));
+        assert!(!extracted.text.contains("Quick link"));
+        assert!(!extracted.text.contains("Finance"));
+        assert!(!extracted.text.contains("Copyright"));
+        assert!(!extracted.text.contains("trackPageView"));
+    }
+
+    #[test]
+    fn test_metadata_harvesting() {
+        let extracted = HtmlExtractor::new().extract(PAGE, None).unwrap();
+        let metadata = &extracted.metadata;
+
+        assert_eq!(extracted.title.as_deref(), Some("Leave Policy | Intranet"));
+        assert_eq!(metadata.language.as_deref(), Some("en"));
+        assert_eq!(metadata.author.as_deref(), Some("People Team"));
+        assert_eq!(metadata.tags, vec!["hr", "leave", "policy", "holidays"]);
+        assert_eq!(metadata.custom_metadata.get("description").unwrap(), "How to request annual leave");
+        assert_eq!(metadata.custom_metadata.get("og:type").unwrap(), "article");
+        assert_eq!(metadata.custom_metadata.get("canonical_url").unwrap(), "https://intranet.example.com/hr/leave");
+        assert!(metadata.custom_metadata.get("json_ld").unwrap().contains("Article"));
+        assert_eq!(metadata.created_at.to_rfc3339(), "2024-01-15T00:00:00+00:00");
+    }
+
+    #[test]
+    fn test_link_extraction() {
+        let extracted = HtmlExtractor::new().extract(PAGE, None).unwrap();
+        let urls: Vec<&str> = extracted.metadata.links.iter().map(|l| l.url.as_str()).collect();
+
+        assert!(urls.contains(&"https://intranet.example.com/hr/forms"));
+        assert!(urls.contains(&"https://intranet.example.com/finance"));
+        assert!(!urls.iter().any(|u| u.starts_with("mailto:")));
+    }
+
+    #[test]
+    fn test_short_page_falls_back_to_body() {
+        let extracted = HtmlExtractor::new()
+            .extract("<html><body><nav>Menu</nav>Test content</body></html>", None)
+            .unwrap();
+
+        assert_eq!(extracted.text, "Test content");
+    }
+}
@@ -1,24 +1,56 @@
-use crate::document::{Document, DocumentMetadata, ProcessingStatus};
+use crate::document::{Document, DocumentMetadata, ExtractedContent, ProcessingStatus};
+use crate::document::directory::{
+    read_archive, walk, ArchiveKind, DirectoryIngestionReport, DirectoryOptions, FileOutcome,
+    IngestionProgress, PathFilter,
+};
+use crate::config::DedupConfig;
+use crate::document::dedup::{store_deduplicated, Deduplicated};
+use crate::document::extractor::{ContentExtractor, ExtractionContext, ExtractorRegistry};
+use crate::document::ocr::OcrEngine;
+use crate::document::store::SourceRecord;
+use crate::document::structured::{FieldMapping, RecordError, StructuredExtractor, StructuredFormat};
+use crate::search::analysis::detect_language;
+use crate::search::engine::SearchEngine;
+use crate::utils::helpers::{hash_bytes, is_valid_file_type};
 use crate::vector::store::VectorStore;
+use crate::webhooks::{WebhookDispatcher, WebhookEvent};
 use anyhow::Result;
+use futures::future::{BoxFuture, FutureExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::RwLock;
 use tokio::io::AsyncReadExt;
 use uuid::Uuid;
-use chrono::Utc;
+use tracing::warn;
 
+#[derive(Clone)]
 pub struct DocumentIngester {
     vector_store: Arc<RwLock<VectorStore>>,
     store: Arc<RwLock<crate::document::store::DocumentStore>>,
+    extractors: Arc<ExtractorRegistry>,
+    dedup: DedupConfig,
+    webhooks: Option<WebhookDispatcher>,
+    search_engine: Option<Arc<SearchEngine>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+pub struct RecordIngestionReport {
+    pub document_ids: Vec<String>,
+    pub errors: Vec<RecordError>,
+}
+
+#[derive(Debug, Clone)]
 pub struct IngestionOptions {
     pub batch_size: usize,
     pub parallel_processing: bool,
     pub validate_content: bool,
     pub generate_metadata: bool,
     pub extract_text: bool,
+    pub attachment_types: Vec<String>,
+    pub max_attachment_depth: usize,
 }
 
 impl Default for IngestionOptions {
@@ -29,6 +61,11 @@ impl Default for IngestionOptions {
             validate_content: true,
             generate_metadata: true,
             extract_text: true,
+            attachment_types: ["pdf", "html", "htm", "txt", "md", "markdown", "eml", "png", "jpg", "jpeg", "tif", "tiff"]
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            max_attachment_depth: 3,
         }
     }
 }
@@ -41,16 +78,308 @@ impl DocumentIngester {
         Self {
             vector_store,
             store,
+            extractors: Arc::new(ExtractorRegistry::default()),
+            dedup: DedupConfig::default(),
+            webhooks: None,
+            search_engine: None,
         }
     }
 
+    pub fn with_dedup(mut self, dedup: DedupConfig) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Send document lifecycle events to webhook subscribers
+    pub fn with_webhooks(mut self, webhooks: WebhookDispatcher) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// Keep the engine's full-text index in step with stored documents
+    pub fn with_search_engine(mut self, search_engine: Arc<SearchEngine>) -> Self {
+        self.search_engine = Some(search_engine);
+        self
+    }
+
+    /// Enable OCR for images and PDF pages without a text layer
+    pub fn with_ocr_engine(mut self, engine: Arc<dyn OcrEngine>) -> Self {
+        Arc::make_mut(&mut self.extractors).register_ocr(engine);
+        self
+    }
+
+    /// Handle an additional format, or replace a built-in one
+    pub fn with_extractor(mut self, extractor: Arc<dyn ContentExtractor>) -> Self {
+        Arc::make_mut(&mut self.extractors).register(extractor);
+        self
+    }
+
     pub async fn ingest_file(&self, path: &str, options: Option<IngestionOptions>) -> Result<String> {
         let options = options.unwrap_or_default();
         let mut file = tokio::fs::File::open(path).await?;
         let mut content = Vec::new();
         file.read_to_end(&mut content).await?;
 
-        self.ingest_content(content, path, options).await
+        self.ingest_with_parent(content, path.to_string(), &options, None, Some(Source::new(path.to_string())), 0).await
+    }
+
+    /// Ingest every file under `root`, descending into zip and tar archives.
+    /// Per-file failures are collected in the report rather than aborting the walk.
+    pub async fn ingest_directory(
+        &self,
+        root: impl AsRef<Path>,
+        options: DirectoryOptions,
+        progress: Option<UnboundedSender<IngestionProgress>>,
+    ) -> Result<DirectoryIngestionReport> {
+        use futures::stream::{self, StreamExt};
+
+        let root = root.as_ref().to_path_buf();
+        let filter = PathFilter::new(&root, &options.include, &options.exclude)?;
+        let files = {
+            let (root, options, filter) = (root.clone(), options.clone(), filter.clone());
+            tokio::task::spawn_blocking(move || walk(&root, &options, &filter, None)).await??
+        };
+
+        let total = files.len();
+        let concurrency = if options.ingestion.parallel_processing {
+            options.ingestion.batch_size.max(1)
+        } else {
+            1
+        };
+
+        let mut outcomes = stream::iter(files)
+            .map(|path| self.ingest_path(path, &options, &filter))
+            .buffer_unordered(concurrency);
+
+        let mut report = DirectoryIngestionReport::default();
+        let mut completed = 0;
+        while let Some(results) = outcomes.next().await {
+            completed += 1;
+            for (path, outcome) in results {
+                if let Some(progress) = &progress {
+                    let status = outcome.status(completed, total);
+                    // A dropped receiver only means nobody is watching
+                    let _ = progress.send(IngestionProgress { path: path.clone(), completed, total, status });
+                }
+                report.record(path, outcome);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Re-ingest a file whose content hash differs from what was stored for
+    /// it, replacing the previous documents. Archive entries are compared
+    /// one by one, and documents of entries gone from the archive are deleted.
+    pub(crate) async fn sync_file(
+        &self,
+        path: PathBuf,
+        options: &DirectoryOptions,
+        filter: &PathFilter,
+    ) -> Result<Vec<(String, FileOutcome)>> {
+        let display = path.display().to_string();
+
+        if let Some(kind) = ArchiveKind::detect(&path).filter(|_| options.extract_archives) {
+            return self.sync_archive(path, kind, options, filter).await;
+        }
+
+        let previous = self.store.read().await.find_by_source(&display).await?;
+        let content = tokio::fs::read(&path).await?;
+
+        if unchanged(&previous, &hash_bytes(&content)) {
+            return Ok(vec![(display, FileOutcome::Skipped("Unchanged".to_string()))]);
+        }
+        if content.len() as u64 > options.max_file_size {
+            let outcome = FileOutcome::Skipped(format!("Larger than {} bytes", options.max_file_size));
+            return Ok(vec![(display, outcome)]);
+        }
+
+        let source = Source {
+            path: display.clone(),
+            replaces: previous.iter().map(|r| r.id.clone()).collect(),
+        };
+        let outcome = self.ingest_source(content, display.clone(), source, options).await;
+
+        // Old documents only go once the new version is in
+        if let FileOutcome::Ingested(id) = &outcome {
+            self.retire(&superseded(previous, id), &[]).await?;
+        }
+
+        Ok(vec![(display, outcome)])
+    }
+
+    async fn sync_archive(
+        &self,
+        path: PathBuf,
+        kind: ArchiveKind,
+        options: &DirectoryOptions,
+        filter: &PathFilter,
+    ) -> Result<Vec<(String, FileOutcome)>> {
+        let display = path.display().to_string();
+        let previous = self.store.read().await.find_archive_entries(&display).await?;
+        let outcomes = self.ingest_archive(path, kind, options, filter, &previous).await;
+
+        // A failure of the archive itself means some entries were never seen
+        let complete = !outcomes.iter().any(|(source, _)| *source == display);
+
+        let (mut replaced, mut removed) = (Vec::new(), Vec::new());
+        for (source, records) in previous {
+            match outcomes.iter().find(|(s, _)| *s == source).map(|(_, outcome)| outcome) {
+                Some(FileOutcome::Ingested(id)) => replaced.extend(superseded(records, id)),
+                None if complete => removed.extend(records.into_iter().map(|r| r.id)),
+                _ => {}
+            }
+        }
+        self.retire(&replaced, &removed).await?;
+
+        Ok(outcomes)
+    }
+
+    /// Source paths of documents ingested from under `root`
+    pub async fn indexed_sources(&self, root: &Path) -> Result<Vec<String>> {
+        self.store.read().await.source_paths(&root.display().to_string()).await
+    }
+
+    /// Delete the documents ingested from a file, directory or archive
+    pub async fn remove_source(&self, path: &str) -> Result<Vec<String>> {
+        let deleted = self.store.write().await.delete_by_source(path).await?;
+        self.unindex(&deleted).await?;
+        self.emit_deleted(&deleted).await;
+        Ok(deleted)
+    }
+
+    /// Delete the documents of earlier source versions. Replaced ones were
+    /// announced in their successor's update event, so only their children
+    /// and the removed ones are reported as deleted.
+    async fn retire(&self, replaced: &[String], removed: &[String]) -> Result<()> {
+        let stale: Vec<String> = replaced.iter().chain(removed).cloned().collect();
+        let deleted = self.store.write().await.delete_with_children(&stale).await?;
+        self.unindex(&deleted).await?;
+        let unannounced: Vec<String> = deleted.into_iter().filter(|id| !replaced.contains(id)).collect();
+        self.emit_deleted(&unannounced).await;
+        Ok(())
+    }
+
+    async fn unindex(&self, ids: &[String]) -> Result<()> {
+        if let Some(engine) = &self.search_engine {
+            for id in ids {
+                engine.remove_document(id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn emit_deleted(&self, ids: &[String]) {
+        let Some(webhooks) = &self.webhooks else { return };
+        for id in ids {
+            webhooks.emit(WebhookEvent::DocumentDeleted, serde_json::json!({ "document_id": id })).await;
+        }
+    }
+
+    /// One walked file, or every entry of an archive
+    async fn ingest_path(
+        &self,
+        path: PathBuf,
+        options: &DirectoryOptions,
+        filter: &PathFilter,
+    ) -> Vec<(String, FileOutcome)> {
+        let display = path.display().to_string();
+
+        if let Some(kind) = ArchiveKind::detect(&path).filter(|_| options.extract_archives) {
+            return self.ingest_archive(path, kind, options, filter, &HashMap::new()).await;
+        }
+
+        let outcome = match tokio::fs::metadata(&path).await {
+            Ok(meta) if meta.len() > options.max_file_size => {
+                FileOutcome::Skipped(format!("Larger than {} bytes", options.max_file_size))
+            }
+            Ok(_) => match tokio::fs::read(&path).await {
+                Ok(content) => self.ingest_source(content, display.clone(), Source::new(display.clone()), options).await,
+                Err(e) => FileOutcome::Failed(e.to_string()),
+            },
+            Err(e) => FileOutcome::Failed(e.to_string()),
+        };
+
+        vec![(display, outcome)]
+    }
+
+    /// Entries whose bytes hash the same as their `previous` documents are skipped
+    async fn ingest_archive(
+        &self,
+        path: PathBuf,
+        kind: ArchiveKind,
+        options: &DirectoryOptions,
+        filter: &PathFilter,
+        previous: &HashMap<String, Vec<SourceRecord>>,
+    ) -> Vec<(String, FileOutcome)> {
+        let display = path.display().to_string();
+
+        // Entries are decompressed on a blocking thread and ingested as they arrive
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+        let reader = {
+            let (filter, max_size, max_total) = (filter.clone(), options.max_file_size, options.max_archive_size);
+            tokio::task::spawn_blocking(move || {
+                read_archive(&path, kind, max_size, max_total, |name| filter.allows(&path.join(name)), |entry| {
+                    sender.blocking_send(entry).map_err(|_| anyhow::anyhow!("Archive ingestion stopped"))
+                })
+            })
+        };
+
+        let mut outcomes = Vec::new();
+        while let Some(entry) = receiver.recv().await {
+            let source = format!("{}!/{}", display, entry.name);
+            let outcome = match entry.data {
+                None => FileOutcome::Skipped(format!("Larger than {} bytes", options.max_file_size)),
+                Some(_) if ArchiveKind::detect(Path::new(&entry.name)).is_some() => {
+                    FileOutcome::Skipped("Nested archives are not extracted".to_string())
+                }
+                Some(data) if previous.get(&source).map_or(false, |records| unchanged(records, &hash_bytes(&data))) => {
+                    FileOutcome::Skipped("Unchanged".to_string())
+                }
+                Some(data) => {
+                    let replaces = previous.get(&source).map_or_else(Vec::new, |records| {
+                        records.iter().map(|r| r.id.clone()).collect()
+                    });
+                    let from = Source { path: source.clone(), replaces };
+                    self.ingest_source(data, entry.name, from, options).await
+                }
+            };
+            outcomes.push((source, outcome));
+        }
+
+        match reader.await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => outcomes.push((display, FileOutcome::Failed(format!("Failed to read archive: {}", e)))),
+            Err(e) => outcomes.push((display, FileOutcome::Failed(e.to_string()))),
+        }
+
+        outcomes
+    }
+
+    async fn ingest_source(
+        &self,
+        content: Vec<u8>,
+        filename: String,
+        source: Source,
+        options: &DirectoryOptions,
+    ) -> FileOutcome {
+        if self.extractors.resolve(&content, &filename).is_none() {
+            return FileOutcome::Skipped("Unsupported format".to_string());
+        }
+
+        let source_path = source.path.clone();
+        match self.ingest_with_parent(content, filename, &options.ingestion, None, Some(source), 0).await {
+            Ok(id) => FileOutcome::Ingested(id),
+            Err(e) => {
+                if let Some(webhooks) = &self.webhooks {
+                    webhooks.emit(WebhookEvent::DocumentFailed, serde_json::json!({
+                        "source_path": source_path,
+                        "error": e.to_string(),
+                    })).await;
+                }
+                FileOutcome::Failed(e.to_string())
+            }
+        }
     }
 
     pub async fn ingest_content(
@@ -59,32 +388,198 @@ impl DocumentIngester {
         filename: &str,
         options: IngestionOptions,
     ) -> Result<String> {
-        // Extract text and metadata
-        let (text, metadata) = if options.extract_text {
-            self.extract_content(&content, filename).await?
-        } else {
-            (String::from_utf8_lossy(&content).to_string(), DocumentMetadata::default())
+        self.ingest_with_parent(content, filename.to_string(), &options, None, None, 0).await
+    }
+
+    fn ingest_with_parent<'a>(
+        &'a self,
+        content: Vec<u8>,
+        filename: String,
+        options: &'a IngestionOptions,
+        parent_id: Option<String>,
+        source: Option<Source>,
+        depth: usize,
+    ) -> BoxFuture<'a, Result<String>> {
+        async move {
+            // Hash the raw bytes so watchers can tell whether a source really changed
+            let source_hash = source.as_ref().map(|_| hash_bytes(&content));
+
+            // Extract text and metadata
+            let extracted = if options.extract_text {
+                let context = ExtractionContext { filename: &filename, ..Default::default() };
+                self.extractors.extract(&content, &context).await?
+            } else {
+                ExtractedContent {
+                    title: None,
+                    text: String::from_utf8_lossy(&content).to_string(),
+                    metadata: DocumentMetadata::default(),
+                    attachments: Vec::new(),
+                    ocr: None,
+                }
+            };
+
+            if extracted.text.trim().is_empty() {
+                anyhow::bail!("No text could be extracted from {}", filename);
+            }
+
+            let attachments = extracted.attachments;
+            let mut metadata = extracted.metadata;
+            metadata.parent_id = parent_id;
+            let mut replaces = Vec::new();
+            if let (Some(source), Some(hash)) = (source, source_hash) {
+                metadata.custom_metadata.insert("source_path".to_string(), source.path);
+                metadata.custom_metadata.insert("source_hash".to_string(), hash);
+                replaces = source.replaces;
+            }
+            if let Some(ocr) = &extracted.ocr {
+                metadata.custom_metadata.insert(
+                    "ocr_confidence".to_string(),
+                    format!("{:.1}", ocr.mean_confidence),
+                );
+            }
+
+            let document_id = self.store_extracted(
+                extracted.title.unwrap_or_else(|| filename.clone()),
+                extracted.text,
+                self.determine_content_type(&filename),
+                metadata,
+                &replaces,
+            ).await?;
+
+            // Ingest supported attachments as children of this document
+            if depth < options.max_attachment_depth {
+                for attachment in attachments {
+                    if !is_valid_file_type(&attachment.filename, &options.attachment_types)
+                        || self.extractors.resolve(&attachment.data, &attachment.filename).is_none()
+                    {
+                        continue;
+                    }
+
+                    let child = self.ingest_with_parent(
+                        attachment.data,
+                        attachment.filename.clone(),
+                        options,
+                        Some(document_id.clone()),
+                        None,
+                        depth + 1,
+                    ).await;
+
+                    if let Err(e) = child {
+                        warn!("Failed to ingest attachment {} of {}: {}", attachment.filename, filename, e);
+                    }
+                }
+            }
+
+            Ok(document_id)
+        }
+        .boxed()
+    }
+
+    /// Import a CSV, JSON-array or NDJSON file with one document per record.
+    /// Records that fail to map or index are reported rather than aborting the import.
+    pub async fn ingest_records(
+        &self,
+        content: Vec<u8>,
+        filename: &str,
+        mapping: FieldMapping,
+    ) -> Result<RecordIngestionReport> {
+        let extension = self.determine_content_type(filename);
+        let format = StructuredFormat::from_extension(&extension)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported record format: {}", extension))?;
+
+        let extraction = StructuredExtractor::new(mapping).extract(&content, format)?;
+        let mut report = RecordIngestionReport {
+            document_ids: Vec::with_capacity(extraction.records.len()),
+            errors: extraction.errors,
         };
 
+        for (record, extracted) in extraction.records {
+            let title = extracted.title
+                .unwrap_or_else(|| format!("{} #{}", filename, record));
+
+            match self.store_extracted(title, extracted.text, extension.clone(), extracted.metadata, &[]).await {
+                Ok(id) => report.document_ids.push(id),
+                Err(e) => report.errors.push(RecordError {
+                    record,
+                    message: e.to_string(),
+                }),
+            }
+        }
+
+        report.errors.sort_by_key(|e| e.record);
+        Ok(report)
+    }
+
+    /// Store a document, announcing it as an update of `replaces`, the
+    /// documents of an earlier version of its source, when there are any
+    async fn store_extracted(
+        &self,
+        title: String,
+        text: String,
+        content_type: String,
+        mut metadata: DocumentMetadata,
+        replaces: &[String],
+    ) -> Result<String> {
+        if metadata.language.is_none() {
+            metadata.language = detect_language(&text);
+        }
+
         // Generate vector embedding
-        let vector_store = self.vector_store.read().await;
-        let vector_embedding = vector_store.generate_embedding(&text).await?;
+        let vector_embedding = {
+            let vector_store = self.vector_store.read().await;
+            vector_store.generate_embedding(&text).await?
+        };
+
+        let source_path = metadata.custom_metadata.get("source_path").cloned();
+        let event = self.webhooks.as_ref().map(|_| serde_json::json!({
+            "title": title,
+            "content_type": content_type,
+            "source_path": source_path,
+            "parent_id": metadata.parent_id,
+        }));
 
         // Create document
         let document = Document {
             id: Uuid::new_v4().to_string(),
-            title: filename.to_string(),
+            title,
             content: text,
-            content_type: self.determine_content_type(filename),
+            content_type,
             metadata,
             vector_embedding: Some(vector_embedding),
         };
 
-        // Store document
-        let mut store = self.store.write().await;
-        store.store_document(document.clone()).await?;
+        // Store document, unless it duplicates a stored one
+        let indexed = self.search_engine.as_ref().map(|_| document.clone());
+        let stored = store_deduplicated(&self.store, document, &self.dedup).await?;
 
-        Ok(document.id)
+        if let (Some(engine), Some(mut document), Deduplicated::Stored(id)) = (&self.search_engine, indexed, &stored) {
+            document.id = id.clone();
+            engine.index_stored_document(&document).await?;
+        }
+
+        if let (Some(webhooks), Some(mut data)) = (&self.webhooks, event) {
+            let replaced: Vec<&String> = replaces.iter().filter(|id| *id != stored.id()).collect();
+            match &stored {
+                _ if !replaced.is_empty() => {
+                    data["document_id"] = serde_json::json!(stored.id());
+                    data["replaces"] = serde_json::json!(replaced);
+                    data["reason"] = serde_json::json!("source_changed");
+                    webhooks.emit(WebhookEvent::DocumentUpdated, data).await;
+                }
+                Deduplicated::Stored(id) => {
+                    data["document_id"] = serde_json::json!(id);
+                    webhooks.emit(WebhookEvent::DocumentProcessed, data).await;
+                }
+                Deduplicated::Merged(id) => {
+                    webhooks.emit(WebhookEvent::DocumentUpdated, serde_json::json!({
+                        "document_id": id,
+                        "reason": "merged_duplicate",
+                    })).await;
+                }
+                Deduplicated::Skipped(_) => {}
+            }
+        }
+        Ok(stored.id().to_string())
     }
 
     pub async fn ingest_batch(
@@ -123,37 +618,6 @@ impl DocumentIngester {
         Ok(document_ids)
     }
 
-    async fn extract_content(&self, content: &[u8], filename: &str) -> Result<(String, DocumentMetadata)> {
-        let content_type = self.determine_content_type(filename);
-        
-        let (text, metadata) = match content_type.as_str() {
-            "pdf" => {
-                let doc = lopdf::Document::load_mem(content)?;
-                let text = doc.extract_text(&[1])?;
-                let metadata = DocumentMetadata {
-                    source_type: "pdf".to_string(),
-                    author: doc.get_metadata().author,
-                    created_at: Utc::now(),
-                    last_modified: Utc::now(),
-                    language: None,
-                    tags: Vec::new(),
-                    custom_metadata: Default::default(),
-                };
-                (text, metadata)
-            },
-            "html" => {
-                let text = html2text::from_bytes(content, 80);
-                (text, DocumentMetadata::default())
-            },
-            _ => {
-                let text = String::from_utf8_lossy(content).to_string();
-                (text, DocumentMetadata::default())
-            }
-        };
-
-        Ok((text, metadata))
-    }
-
     fn determine_content_type(&self, filename: &str) -> String {
         match std::path::Path::new(filename)
             .extension()
@@ -166,6 +630,29 @@ impl DocumentIngester {
     }
 }
 
+/// A file or archive entry, and the documents ingested from its previous version
+struct Source {
+    path: String,
+    replaces: Vec<String>,
+}
+
+impl Source {
+    fn new(path: String) -> Self {
+        Self { path, replaces: Vec::new() }
+    }
+}
+
+/// Whether a source was already ingested from bytes hashing to `hash`
+fn unchanged(previous: &[SourceRecord], hash: &str) -> bool {
+    !previous.is_empty() && previous.iter().all(|r| r.source_hash.as_deref() == Some(hash))
+}
+
+/// Previous documents of a source re-ingested as `ingested`. Deduplication
+/// can hand back one of the previous ids, which must stay.
+fn superseded(previous: Vec<SourceRecord>, ingested: &str) -> Vec<String> {
+    previous.into_iter().map(|r| r.id).filter(|id| id != ingested).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -191,4 +678,16 @@ mod tests {
 
         assert!(result.is_ok());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_unchanged_and_superseded_sources() {
+        let record = |id: &str, hash: Option<&str>| SourceRecord { id: id.to_string(), source_hash: hash.map(str::to_string) };
+
+        assert!(!unchanged(&[], "abc"));
+        assert!(unchanged(&[record("1", Some("abc"))], "abc"));
+        assert!(!unchanged(&[record("1", Some("abc")), record("2", None)], "abc"));
+
+        let previous = vec![record("1", Some("abc")), record("2", Some("abc"))];
+        assert_eq!(superseded(previous, "2"), vec!["1"]);
+    }
+}
@@ -0,0 +1,373 @@
+use crate::document::extractor::{ContentExtractor, ExtractionContext};
+use crate::document::{CodeBlock, DocumentMetadata, DocumentSection, ExtractedContent};
+use crate::utils::helpers::parse_date;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag};
+use std::collections::HashMap;
+use tracing::debug;
+
+pub struct MarkdownExtractor {
+    options: Options,
+}
+
+impl MarkdownExtractor {
+    pub fn new() -> Self {
+        let mut options = Options::empty();
+        options.insert(Options::ENABLE_TABLES);
+        options.insert(Options::ENABLE_FOOTNOTES);
+        options.insert(Options::ENABLE_STRIKETHROUGH);
+        options.insert(Options::ENABLE_TASKLISTS);
+        options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+
+        Self { options }
+    }
+
+    pub fn extract(&self, content: &[u8]) -> Result<ExtractedContent> {
+        let source = String::from_utf8_lossy(content);
+        let (front_matter, body) = split_front_matter(&source);
+
+        let mut text = String::new();
+        // Characters of `text` up to byte `counted`, so offsets are counted once
+        let (mut counted, mut chars) = (0, 0);
+        let mut sections: Vec<DocumentSection> = Vec::new();
+        let mut code_blocks = Vec::new();
+        let mut anchors: HashMap<String, usize> = HashMap::new();
+
+        // Open headings, innermost last, used to resolve parents
+        let mut section_stack: Vec<usize> = Vec::new();
+        let mut heading: Option<(u8, Option<String>, String)> = None;
+        let mut code_block: Option<CodeBlock> = None;
+
+        for event in Parser::new_ext(body, self.options) {
+            match event {
+                Event::Start(Tag::Heading(level, id, _)) => {
+                    heading = Some((level as u8, id.map(str::to_string), String::new()));
+                }
+                Event::End(Tag::Heading(..)) => {
+                    if let Some((level, id, title)) = heading.take() {
+                        let title = title.trim().to_string();
+
+                        while let Some(&open) = section_stack.last() {
+                            if sections[open].level >= level {
+                                section_stack.pop();
+                            } else {
+                                break;
+                            }
+                        }
+
+                        let anchor = unique_anchor(
+                            id.unwrap_or_else(|| slugify(&title)),
+                            &mut anchors,
+                        );
+
+                        sections.push(DocumentSection {
+                            level,
+                            title: title.clone(),
+                            anchor,
+                            parent: section_stack.last().copied(),
+                            offset: {
+                                chars += text[counted..].chars().count();
+                                counted = text.len();
+                                chars
+                            },
+                        });
+                        section_stack.push(sections.len() - 1);
+
+                        text.push_str(&title);
+                        text.push('\n');
+                    }
+                }
+                Event::Start(Tag::CodeBlock(kind)) => {
+                    let language = match kind {
+                        CodeBlockKind::Fenced(info) => info
+                            .split_whitespace()
+                            .next()
+                            .map(str::to_string),
+                        CodeBlockKind::Indented => None,
+                    };
+                    code_block = Some(CodeBlock {
+                        language,
+                        code: String::new(),
+                        section: section_stack.last().map(|&i| sections[i].anchor.clone()),
+                    });
+                }
+                Event::End(Tag::CodeBlock(_)) => {
+                    if let Some(block) = code_block.take() {
+                        code_blocks.push(block);
+                    }
+                }
+                Event::Text(value) | Event::Code(value) => {
+                    if let Some(block) = code_block.as_mut() {
+                        block.code.push_str(&value);
+                    } else if let Some((_, _, title)) = heading.as_mut() {
+                        title.push_str(&value);
+                    } else {
+                        text.push_str(&value);
+                    }
+                }
+                Event::SoftBreak => push_separator(&mut text, &mut heading, ' '),
+                Event::HardBreak => push_separator(&mut text, &mut heading, '\n'),
+                Event::End(Tag::Paragraph)
+                | Event::End(Tag::Item)
+                | Event::End(Tag::BlockQuote)
+                | Event::End(Tag::TableRow)
+                | Event::End(Tag::TableHead) => {
+                    if !text.ends_with('\n') {
+                        text.push('\n');
+                    }
+                }
+                Event::End(Tag::TableCell) => text.push(' '),
+                // Raw HTML, rules and footnote markers carry no searchable text
+                _ => {}
+            }
+        }
+
+        let mut metadata = DocumentMetadata {
+            source_type: "markdown".to_string(),
+            sections,
+            code_blocks,
+            ..Default::default()
+        };
+
+        let mut title = None;
+        if let Some(front_matter) = front_matter {
+            title = front_matter.get("title").and_then(scalar_to_string);
+            apply_front_matter(&front_matter, &mut metadata);
+        }
+
+        let title = title.or_else(|| {
+            metadata.sections
+                .iter()
+                .find(|section| section.level == 1)
+                .map(|section| section.title.clone())
+        });
+
+        Ok(ExtractedContent {
+            title,
+            text: text.trim().to_string(),
+            metadata,
+            attachments: Vec::new(),
+            ocr: None,
+        })
+    }
+}
+
+impl Default for MarkdownExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ContentExtractor for MarkdownExtractor {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn mime_types(&self) -> Vec<&str> {
+        vec!["text/markdown", "text/x-markdown"]
+    }
+
+    fn extensions(&self) -> Vec<&str> {
+        vec!["md", "markdown"]
+    }
+
+    async fn extract(&self, content: &[u8], _context: &ExtractionContext<'_>) -> Result<ExtractedContent> {
+        MarkdownExtractor::extract(self, content)
+    }
+}
+
+fn push_separator(text: &mut String, heading: &mut Option<(u8, Option<String>, String)>, separator: char) {
+    match heading.as_mut() {
+        Some((_, _, title)) => title.push(' '),
+        None => text.push(separator),
+    }
+}
+
+/// Split a leading YAML (`---`) or TOML (`+++`) front-matter block from the body.
+/// A block that does not parse is taken to be Markdown, e.g. a leading
+/// thematic break, and left in the body.
+fn split_front_matter(source: &str) -> (Option<serde_json::Map<String, serde_json::Value>>, &str) {
+    let source = source.trim_start_matches('\u{feff}');
+
+    for (fence, is_toml) in [("---", false), ("+++", true)] {
+        let Some(rest) = source.strip_prefix(fence) else { continue };
+        let Some(rest) = rest.strip_prefix('\n').or_else(|| rest.strip_prefix("\r\n")) else { continue };
+
+        let closing = rest
+            .match_indices(fence)
+            .find(|(idx, _)| *idx == 0 || rest[..*idx].ends_with('\n'));
+
+        if let Some((end, _)) = closing {
+            let raw = &rest[..end];
+            let body = rest[end + fence.len()..].trim_start_matches(['\r', '\n']);
+
+            let value: Result<serde_json::Value> = if is_toml {
+                toml::from_str(raw).context("Failed to parse TOML front matter")
+            } else {
+                serde_yaml::from_str(raw).context("Failed to parse YAML front matter")
+            };
+
+            return match value {
+                Ok(value) => (value.as_object().cloned(), body),
+                Err(e) => {
+                    debug!("No front matter: {:#}", e);
+                    (None, source)
+                }
+            };
+        }
+    }
+
+    (None, source)
+}
+
+fn apply_front_matter(front_matter: &serde_json::Map<String, serde_json::Value>, metadata: &mut DocumentMetadata) {
+    for (key, value) in front_matter {
+        match key.as_str() {
+            "tags" | "keywords" | "categories" => {
+                let tags: Vec<String> = match value {
+                    serde_json::Value::Array(items) => items.iter().filter_map(scalar_to_string).collect(),
+                    other => scalar_to_string(other)
+                        .map(|s| s.split(',').map(|t| t.trim().to_string()).collect())
+                        .unwrap_or_default(),
+                };
+                for tag in tags {
+                    if !tag.is_empty() && !metadata.tags.contains(&tag) {
+                        metadata.tags.push(tag);
+                    }
+                }
+            }
+            "author" => metadata.author = scalar_to_string(value),
+            "lang" | "language" => metadata.language = scalar_to_string(value),
+            "date" => {
+                if let Some(date) = scalar_to_string(value).and_then(|d| parse_date(&d)) {
+                    metadata.created_at = date;
+                }
+            }
+            "updated" | "lastmod" => {
+                if let Some(date) = scalar_to_string(value).and_then(|d| parse_date(&d)) {
+                    metadata.last_modified = date;
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(value) = front_matter_value(value) {
+            metadata.custom_metadata.insert(key.clone(), value);
+        }
+    }
+}
+
+fn scalar_to_string(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn front_matter_value(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::Array(items) => Some(
+            items.iter()
+                .map(|item| scalar_to_string(item).unwrap_or_else(|| item.to_string()))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        serde_json::Value::Object(_) => Some(value.to_string()),
+        scalar => scalar_to_string(scalar),
+    }
+}
+
+/// GitHub-style heading anchor: lowercase, punctuation dropped, spaces to dashes
+fn slugify(title: &str) -> String {
+    title
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            ' ' => Some('-'),
+            _ => None,
+        })
+        .collect()
+}
+
+fn unique_anchor(anchor: String, seen: &mut HashMap<String, usize>) -> String {
+    let count = seen.entry(anchor.clone()).or_insert(0);
+    let unique = if *count == 0 {
+        anchor
+    } else {
+        format!("{}-{}", anchor, count)
+    };
+    *count += 1;
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yaml_front_matter() {
+        let source = "---\ntitle: Deploy Guide\nauthor: Ops\ntags: [k8s, deploy]\nowner: platform\n---\n# Intro\n\nSome *text*.\n";
+        let extracted = MarkdownExtractor::new().extract(source.as_bytes()).unwrap();
+
+        assert_eq!(extracted.title.as_deref(), Some("Deploy Guide"));
+        assert_eq!(extracted.metadata.author.as_deref(), Some("Ops"));
+        assert_eq!(extracted.metadata.tags, vec!["k8s", "deploy"]);
+        assert_eq!(extracted.metadata.custom_metadata.get("owner").unwrap(), "platform");
+        assert_eq!(extracted.text, "Intro\nSome text.");
+    }
+
+    #[test]
+    fn test_toml_front_matter() {
+        let source = "+++\ntitle = \"Runbook\"\ntags = [\"oncall\"]\n+++\nBody\n";
+        let extracted = MarkdownExtractor::new().extract(source.as_bytes()).unwrap();
+
+        assert_eq!(extracted.title.as_deref(), Some("Runbook"));
+        assert_eq!(extracted.metadata.tags, vec!["oncall"]);
+        assert_eq!(extracted.text, "Body");
+    }
+
+    #[test]
+    fn test_leading_thematic_break_is_not_front_matter() {
+        let source = "---\nSee: [the guide](guide.md): setup\n\n---\n# Notes\n";
+        let extracted = MarkdownExtractor::new().extract(source.as_bytes()).unwrap();
+
+        assert_eq!(extracted.title.as_deref(), Some("Notes"));
+        assert!(extracted.text.starts_with("See: the guide: setup"));
+        assert_eq!(extracted.metadata.sections[0].offset, extracted.text.find("Notes").unwrap());
+    }
+
+    #[test]
+    fn test_heading_hierarchy() {
+        let source = "# Guide\n## Setup\n### Install\n## Setup\n# Appendix\n";
+        let extracted = MarkdownExtractor::new().extract(source.as_bytes()).unwrap();
+        let sections = &extracted.metadata.sections;
+
+        assert_eq!(extracted.title.as_deref(), Some("Guide"));
+        assert_eq!(sections.len(), 5);
+        assert_eq!(sections[1].anchor, "setup");
+        assert_eq!(sections[2].parent, Some(1));
+        assert_eq!(sections[3].anchor, "setup-1");
+        assert_eq!(sections[3].parent, Some(0));
+        assert_eq!(sections[4].parent, None);
+    }
+
+    #[test]
+    fn test_code_blocks_are_separated() {
+        let source = "# Usage\n\nRun it:\n\n```bash\ncargo run\n```\n";
+        let extracted = MarkdownExtractor::new().extract(source.as_bytes()).unwrap();
+        let blocks = &extracted.metadata.code_blocks;
+
+        assert!(!extracted.text.contains("cargo run"));
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].language.as_deref(), Some("bash"));
+        assert_eq!(blocks[0].code, "cargo run\n");
+        assert_eq!(blocks[0].section.as_deref(), Some("usage"));
+    }
+}
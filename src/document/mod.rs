@@ -1,6 +1,16 @@
 pub mod processor;
 pub mod ingestion;
 pub mod store;
+pub mod markdown;
+pub mod email;
+pub mod structured;
+pub mod html;
+pub mod ocr;
+pub mod pdf;
+pub mod extractor;
+pub mod directory;
+pub mod watcher;
+pub mod dedup;
 
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
@@ -25,6 +35,79 @@ pub struct DocumentMetadata {
     pub language: Option<String>,
     pub tags: Vec<String>,
     pub custom_metadata: HashMap<String, String>,
+    #[serde(default)]
+    pub sections: Vec<DocumentSection>,
+    #[serde(default)]
+    pub code_blocks: Vec<CodeBlock>,
+    #[serde(default)]
+    pub parent_id: Option<String>,  // Set on attachments and other child documents
+    #[serde(default)]
+    pub links: Vec<OutboundLink>,
+    #[serde(default)]
+    pub duplicate_of: Option<String>,  // Earlier document this one nearly duplicates
+}
+
+impl Default for DocumentMetadata {
+    fn default() -> Self {
+        let now = Utc::now();
+        Self {
+            source_type: "text".to_string(),
+            author: None,
+            created_at: now,
+            last_modified: now,
+            language: None,
+            tags: Vec::new(),
+            custom_metadata: HashMap::new(),
+            sections: Vec::new(),
+            code_blocks: Vec::new(),
+            parent_id: None,
+            links: Vec::new(),
+            duplicate_of: None,
+        }
+    }
+}
+
+/// A heading in a structured document, addressable through its anchor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentSection {
+    pub level: u8,
+    pub title: String,
+    pub anchor: String,
+    pub parent: Option<usize>,  // Index of the enclosing section
+    pub offset: usize,          // Character offset into the extracted content
+}
+
+/// A fenced or indented code block, kept out of the prose content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeBlock {
+    pub language: Option<String>,
+    pub code: String,
+    pub section: Option<String>,  // Anchor of the enclosing section
+}
+
+/// A hyperlink found in the document, kept for crawling and link analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboundLink {
+    pub url: String,
+    pub text: String,
+    pub rel: Option<String>,
+}
+
+/// Result of running a format-specific extractor over raw bytes
+#[derive(Debug, Clone)]
+pub struct ExtractedContent {
+    pub title: Option<String>,
+    pub text: String,
+    pub metadata: DocumentMetadata,
+    pub attachments: Vec<Attachment>,  // Embedded files ingested as child documents
+    pub ocr: Option<ocr::OcrSummary>,   // Set when any text came from OCR
+}
+
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: Option<String>,
+    pub data: Vec<u8>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,4 +116,4 @@ pub enum ProcessingStatus {
     Processing(f32),  // Progress percentage
     Completed(String),  // Document ID
     Failed(String),   // Error message
-}
\ No newline at end of file
+}
@@ -0,0 +1,256 @@
+use crate::config::OcrConfig;
+use crate::document::extractor::{ContentExtractor, ExtractionContext};
+use crate::document::{DocumentMetadata, ExtractedContent};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Text recognised from a single image
+#[derive(Debug, Clone)]
+pub struct OcrResult {
+    pub text: String,
+    pub confidence: f32,  // Mean word confidence, 0-100
+}
+
+/// Per-page OCR outcome reported back to clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrPage {
+    pub page: u32,
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrSummary {
+    pub engine: String,
+    pub pages: Vec<OcrPage>,
+    pub mean_confidence: f32,
+}
+
+impl OcrSummary {
+    pub fn new(engine: &str, pages: Vec<OcrPage>) -> Self {
+        let mean_confidence = if pages.is_empty() {
+            0.0
+        } else {
+            pages.iter().map(|p| p.confidence).sum::<f32>() / pages.len() as f32
+        };
+
+        Self {
+            engine: engine.to_string(),
+            pages,
+            mean_confidence,
+        }
+    }
+}
+
+#[async_trait]
+pub trait OcrEngine: Send + Sync {
+    fn name(&self) -> &str;
+    async fn recognize(&self, image: &[u8]) -> Result<OcrResult>;
+}
+
+/// OCR through the `tesseract` command line tool, fed over stdin
+pub struct TesseractOcr {
+    binary: PathBuf,
+    languages: String,
+    timeout: Duration,
+}
+
+impl TesseractOcr {
+    pub fn new(config: &OcrConfig) -> Self {
+        Self {
+            binary: config.tesseract_path.clone(),
+            languages: config.languages.clone(),
+            timeout: Duration::from_secs(config.timeout_secs),
+        }
+    }
+}
+
+#[async_trait]
+impl OcrEngine for TesseractOcr {
+    fn name(&self) -> &str {
+        "tesseract"
+    }
+
+    async fn recognize(&self, image: &[u8]) -> Result<OcrResult> {
+        let mut child = Command::new(&self.binary)
+            .args(["stdin", "stdout", "-l", &self.languages, "tsv"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start {}", self.binary.display()))?;
+
+        let mut stdin = child.stdin.take().context("Tesseract stdin unavailable")?;
+        let input = image.to_vec();
+        let writer = tokio::spawn(async move {
+            stdin.write_all(&input).await?;
+            stdin.shutdown().await
+        });
+
+        let output = tokio::time::timeout(self.timeout, child.wait_with_output())
+            .await
+            .context("Tesseract timed out")??;
+        writer.await??;
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "Tesseract failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        Ok(parse_tsv(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+/// Rebuild text and mean confidence from Tesseract's TSV output. Word rows
+/// (level 5) carry the text; line breaks follow block/paragraph/line numbers.
+fn parse_tsv(tsv: &str) -> OcrResult {
+    let mut text = String::new();
+    let mut confidences = Vec::new();
+    let mut current_line = None;
+
+    for row in tsv.lines().skip(1) {
+        let columns: Vec<&str> = row.splitn(12, '\t').collect();
+        if columns.len() < 12 || columns[0] != "5" {
+            continue;
+        }
+
+        let word = columns[11].trim();
+        let confidence: f32 = columns[10].parse().unwrap_or(-1.0);
+        if word.is_empty() || confidence < 0.0 {
+            continue;
+        }
+
+        let line = (columns[2], columns[3], columns[4]);
+        match current_line {
+            Some(previous) if previous == line => text.push(' '),
+            Some(_) => text.push('\n'),
+            None => {}
+        }
+        current_line = Some(line);
+
+        text.push_str(word);
+        confidences.push(confidence);
+    }
+
+    let confidence = if confidences.is_empty() {
+        0.0
+    } else {
+        confidences.iter().sum::<f32>() / confidences.len() as f32
+    };
+
+    OcrResult { text, confidence }
+}
+
+/// Runs standalone images through an OCR engine
+pub struct ImageExtractor {
+    engine: Arc<dyn OcrEngine>,
+}
+
+impl ImageExtractor {
+    pub fn new(engine: Arc<dyn OcrEngine>) -> Self {
+        Self { engine }
+    }
+}
+
+#[async_trait]
+impl ContentExtractor for ImageExtractor {
+    fn name(&self) -> &str {
+        "image"
+    }
+
+    fn mime_types(&self) -> Vec<&str> {
+        vec!["image/png", "image/jpeg", "image/tiff"]
+    }
+
+    fn extensions(&self) -> Vec<&str> {
+        vec!["png", "jpg", "jpeg", "tif", "tiff"]
+    }
+
+    async fn extract(&self, content: &[u8], _context: &ExtractionContext<'_>) -> Result<ExtractedContent> {
+        let result = self.engine.recognize(content).await?;
+
+        Ok(ExtractedContent {
+            title: None,
+            text: result.text,
+            metadata: DocumentMetadata {
+                source_type: "image".to_string(),
+                ..Default::default()
+            },
+            attachments: Vec::new(),
+            ocr: Some(OcrSummary::new(self.engine.name(), vec![OcrPage {
+                page: 1,
+                confidence: result.confidence,
+            }])),
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Records the images it is given and answers with fixed text
+    pub(crate) struct StubOcr {
+        pub text: String,
+        pub confidence: f32,
+        pub seen: Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl StubOcr {
+        pub fn new(text: &str, confidence: f32) -> Self {
+            Self {
+                text: text.to_string(),
+                confidence,
+                seen: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl OcrEngine for StubOcr {
+        fn name(&self) -> &str {
+            "stub"
+        }
+
+        async fn recognize(&self, image: &[u8]) -> Result<OcrResult> {
+            self.seen.lock().unwrap().push(image.to_vec());
+            Ok(OcrResult {
+                text: self.text.clone(),
+                confidence: self.confidence,
+            })
+        }
+    }
+
+    #[test]
+    fn test_parse_tsv() {
+        let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n\
+1\t1\t0\t0\t0\t0\t0\t0\t100\t100\t-1\t\n\
+5\t1\t1\t1\t1\t1\t0\t0\t10\t10\t90\tThis\n\
+5\t1\t1\t1\t1\t2\t0\t0\t10\t10\t80\tcontract\n\
+5\t1\t1\t1\t2\t1\t0\t0\t10\t10\t70\tSigned\n\
+5\t1\t1\t1\t2\t2\t0\t0\t10\t10\t-1\t \n";
+
+        let result = parse_tsv(tsv);
+        assert_eq!(result.text, "This contract\nSigned");
+        assert!((result.confidence - 80.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_summary_mean() {
+        let summary = OcrSummary::new("stub", vec![
+            OcrPage { page: 1, confidence: 90.0 },
+            OcrPage { page: 3, confidence: 70.0 },
+        ]);
+        assert_eq!(summary.mean_confidence, 80.0);
+    }
+}
@@ -0,0 +1,270 @@
+use crate::document::extractor::{ContentExtractor, ExtractionContext, PageProgress};
+use crate::document::ocr::{OcrEngine, OcrPage, OcrSummary};
+use crate::document::{DocumentMetadata, ExtractedContent};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use lopdf::{Dictionary, Object, ObjectId, Stream};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+pub struct PdfExtractor {
+    ocr_engine: Option<Arc<dyn OcrEngine>>,
+}
+
+impl PdfExtractor {
+    pub fn new(ocr_engine: Option<Arc<dyn OcrEngine>>) -> Self {
+        Self { ocr_engine }
+    }
+
+    /// Extract text from every page, falling back to OCR of the page's
+    /// embedded images when a page has no text layer
+    pub async fn extract(&self, content: &[u8]) -> Result<ExtractedContent> {
+        self.extract_pages(content, None).await
+    }
+
+    async fn extract_pages(&self, content: &[u8], on_page: Option<PageProgress<'_>>) -> Result<ExtractedContent> {
+        let doc = lopdf::Document::load_mem(content)
+            .context("Failed to load PDF document")?;
+
+        let mut text = String::new();
+        let mut ocr_pages = Vec::new();
+
+        let pages = doc.get_pages();
+        let page_count = pages.len() as u32;
+
+        for (page_num, page_id) in pages {
+            if let Some(on_page) = on_page {
+                on_page.report(page_num, page_count);
+            }
+
+            let page_text = doc.extract_text(&[page_num]).unwrap_or_default();
+
+            if !page_text.trim().is_empty() {
+                text.push_str(&page_text);
+                text.push('\n');
+                continue;
+            }
+
+            let Some(engine) = &self.ocr_engine else { continue };
+
+            for image in page_images(&doc, page_id) {
+                match engine.recognize(&image).await {
+                    Ok(result) => {
+                        text.push_str(&result.text);
+                        text.push('\n');
+                        ocr_pages.push(OcrPage {
+                            page: page_num,
+                            confidence: result.confidence,
+                        });
+                    }
+                    Err(e) => warn!("OCR failed for page {}: {}", page_num, e),
+                }
+            }
+        }
+
+        let info = doc.get_metadata();
+        let metadata = DocumentMetadata {
+            source_type: "pdf".to_string(),
+            author: info.author,
+            ..Default::default()
+        };
+
+        let ocr = self.ocr_engine
+            .as_ref()
+            .filter(|_| !ocr_pages.is_empty())
+            .map(|engine| OcrSummary::new(engine.name(), ocr_pages));
+
+        Ok(ExtractedContent {
+            title: info.title,
+            text,
+            metadata,
+            attachments: Vec::new(),
+            ocr,
+        })
+    }
+}
+
+#[async_trait]
+impl ContentExtractor for PdfExtractor {
+    fn name(&self) -> &str {
+        "pdf"
+    }
+
+    fn mime_types(&self) -> Vec<&str> {
+        vec!["application/pdf"]
+    }
+
+    fn extensions(&self) -> Vec<&str> {
+        vec!["pdf"]
+    }
+
+    async fn extract(&self, content: &[u8], context: &ExtractionContext<'_>) -> Result<ExtractedContent> {
+        self.extract_pages(content, context.on_page).await
+    }
+}
+
+/// Encoded images on a page in a form OCR engines can read: JPEG and
+/// JPEG 2000 streams as-is, 8-bit gray/RGB and 1-bit Flate images as PNM
+fn page_images(doc: &lopdf::Document, page_id: ObjectId) -> Vec<Vec<u8>> {
+    let (resources, resource_ids) = doc.get_page_resources(page_id);
+
+    let mut dictionaries: Vec<&Dictionary> = resources.into_iter().collect();
+    dictionaries.extend(
+        resource_ids
+            .into_iter()
+            .filter_map(|id| doc.get_dictionary(id).ok()),
+    );
+
+    let mut images = Vec::new();
+    for resources in dictionaries {
+        let Ok(xobjects) = resources.get(b"XObject").and_then(|o| resolve(doc, o)?.as_dict()) else {
+            continue;
+        };
+
+        for (name, object) in xobjects.iter() {
+            let Ok(Object::Stream(stream)) = resolve(doc, object) else { continue };
+            if stream.dict.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Image".as_slice()) {
+                continue;
+            }
+
+            match encode_image(stream) {
+                Some(image) => images.push(image),
+                None => debug!("Skipping unsupported image {}", String::from_utf8_lossy(name)),
+            }
+        }
+    }
+
+    images
+}
+
+fn resolve<'a>(doc: &'a lopdf::Document, object: &'a Object) -> lopdf::Result<&'a Object> {
+    match object {
+        Object::Reference(id) => doc.get_object(*id),
+        other => Ok(other),
+    }
+}
+
+fn encode_image(stream: &Stream) -> Option<Vec<u8>> {
+    let filter = match stream.dict.get(b"Filter").ok()? {
+        Object::Name(name) => Some(name.clone()),
+        Object::Array(filters) => filters.last().and_then(|f| f.as_name().ok()).map(<[u8]>::to_vec),
+        _ => None,
+    };
+
+    match filter.as_deref() {
+        Some(b"DCTDecode") | Some(b"JPXDecode") => Some(stream.content.clone()),
+        Some(b"FlateDecode") | None => {
+            let width = stream.dict.get(b"Width").and_then(Object::as_i64).ok()?;
+            let height = stream.dict.get(b"Height").and_then(Object::as_i64).ok()?;
+            let bits = stream.dict.get(b"BitsPerComponent").and_then(Object::as_i64).unwrap_or(8);
+            let color_space = stream.dict.get(b"ColorSpace").and_then(Object::as_name).unwrap_or(b"DeviceGray");
+            let pixels = stream.decompressed_content().unwrap_or_else(|_| stream.content.clone());
+
+            let (magic, pixels) = match (color_space, bits) {
+                (b"DeviceGray", 8) => ("P5", pixels),
+                (b"DeviceRGB", 8) => ("P6", pixels),
+                // PDF stores 0 as black for 1-bit gray, PBM the opposite
+                (b"DeviceGray", 1) => ("P4", pixels.into_iter().map(|b| !b).collect()),
+                _ => return None,
+            };
+
+            let mut image = match magic {
+                "P4" => format!("{}\n{} {}\n", magic, width, height),
+                _ => format!("{}\n{} {}\n255\n", magic, width, height),
+            }
+            .into_bytes();
+            image.extend_from_slice(&pixels);
+            Some(image)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::ocr::tests::StubOcr;
+    use lopdf::content::{Content, Operation};
+    use lopdf::dictionary;
+    use std::sync::Mutex;
+
+    fn scanned_pdf() -> Vec<u8> {
+        let mut doc = lopdf::Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+
+        let image_id = doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 1,
+                "Height" => 1,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+                "Filter" => "DCTDecode",
+            },
+            b"jpeg-bytes".to_vec(),
+        ));
+        let resources_id = doc.add_object(dictionary! {
+            "XObject" => dictionary! { "Im1" => image_id },
+        });
+        let content = Content {
+            operations: vec![Operation::new("Do", vec![Object::Name(b"Im1".to_vec())])],
+        };
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), 100.into(), 100.into()],
+        });
+        doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }));
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[tokio::test]
+    async fn test_ocr_fallback_for_image_only_page() {
+        let stub = Arc::new(StubOcr::new("Master services agreement", 87.5));
+        let extractor = PdfExtractor::new(Some(stub.clone()));
+
+        let extracted = extractor.extract(&scanned_pdf()).await.unwrap();
+        let ocr = extracted.ocr.unwrap();
+
+        assert_eq!(extracted.text.trim(), "Master services agreement");
+        assert_eq!(stub.seen.lock().unwrap()[0], b"jpeg-bytes".to_vec());
+        assert_eq!(ocr.engine, "stub");
+        assert_eq!(ocr.pages[0].page, 1);
+        assert_eq!(ocr.mean_confidence, 87.5);
+    }
+
+    #[tokio::test]
+    async fn test_no_ocr_engine_leaves_page_empty() {
+        let extracted = PdfExtractor::new(None).extract(&scanned_pdf()).await.unwrap();
+
+        assert!(extracted.text.trim().is_empty());
+        assert!(extracted.ocr.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reports_page_progress() {
+        let seen = Mutex::new(Vec::new());
+        let on_page = |page, pages| seen.lock().unwrap().push((page, pages));
+        let context = ExtractionContext { on_page: Some(PageProgress(&on_page)), ..Default::default() };
+
+        ContentExtractor::extract(&PdfExtractor::new(None), &scanned_pdf(), &context).await.unwrap();
+
+        assert_eq!(*seen.lock().unwrap(), vec![(1, 1)]);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::vector::store::VectorStore;
 use crate::document::{Document, DocumentMetadata, ProcessingStatus};
+use crate::search::analysis::detect_language;
 use anyhow::Result;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -54,6 +55,11 @@ impl DocumentProcessor {
                 let vector_store = vector_store.read().await;
                 let vector_embedding = vector_store.generate_embedding(&upload.content).await?;
 
+                // Detect language, unless the upload declared it
+                let language = upload.metadata.as_ref()
+                    .and_then(|m| m.get("language").cloned())
+                    .or_else(|| detect_language(&upload.content));
+
                 // Create document
                 let document = Document {
                     id: Uuid::new_v4().to_string(),
@@ -66,9 +72,10 @@ impl DocumentProcessor {
                         author: upload.metadata.as_ref().and_then(|m| m.get("author").cloned()),
                         created_at: Utc::now(),
                         last_modified: Utc::now(),
-                        language: None, // TODO: Implement language detection
+                        language,
                         tags: Vec::new(),
                         custom_metadata: upload.metadata.unwrap_or_default(),
+                        ..Default::default()
                     },
                 };
 
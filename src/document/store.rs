@@ -1,10 +1,21 @@
 use crate::document::{Document, DocumentMetadata};
+use crate::document::dedup::{hamming_distance, simhash, simhash_bands};
+use crate::search::analysis::{postgres_config, Language};
+use crate::search::cjk::segment_cjk;
+use crate::utils::helpers::calculate_hash;
 use anyhow::Result;
 use sqlx::{PgPool, postgres::PgPoolOptions};
 use std::collections::HashMap;
 use uuid::Uuid;
 use chrono::Utc;
 
+/// A stored document and the hash of the file it was ingested from
+#[derive(Debug, Clone)]
+pub struct SourceRecord {
+    pub id: String,
+    pub source_hash: Option<String>,
+}
+
 pub struct DocumentStore {
     pool: PgPool,
     cache: HashMap<String, Document>,
@@ -20,10 +31,15 @@ impl DocumentStore {
             .connect(&database_url)
             .await?;
 
-        Ok(Self {
+        Ok(Self::from_pool(pool))
+    }
+
+    /// A store sharing an existing connection pool
+    pub fn from_pool(pool: PgPool) -> Self {
+        Self {
             pool,
             cache: HashMap::new(),
-        })
+        }
     }
 
     pub async fn store_document(&mut self, document: Document) -> Result<String> {
@@ -31,9 +47,10 @@ impl DocumentStore {
         let id = sqlx::query!(
             r#"
             INSERT INTO documents 
-                (id, title, content, content_type, vector_embedding, metadata)
+                (id, title, content, content_type, vector_embedding, metadata, content_hash, simhash,
+                 text_config, segmented_title, segmented_content)
             VALUES 
-                ($1, $2, $3, $4, $5, $6)
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9::text::regconfig, $10, $11)
             RETURNING id
             "#,
             document.id,
@@ -41,7 +58,12 @@ impl DocumentStore {
             document.content,
             document.content_type,
             document.vector_embedding.as_ref().map(|v| v.as_slice()),
-            serde_json::to_value(&document.metadata)?
+            serde_json::to_value(&document.metadata)?,
+            calculate_hash(&document.content),
+            simhash(&document.content) as i64,
+            postgres_config(document.metadata.language.as_deref()),
+            segment_cjk(&document.title),
+            segment_cjk(&document.content)
         )
         .fetch_one(&self.pool)
         .await?
@@ -93,7 +115,12 @@ impl DocumentStore {
                 content_type = $4,
                 vector_embedding = $5,
                 metadata = $6,
-                updated_at = $7
+                updated_at = $7,
+                content_hash = $8,
+                simhash = $9,
+                text_config = $10::text::regconfig,
+                segmented_title = $11,
+                segmented_content = $12
             WHERE id = $1
             "#,
             id,
@@ -103,6 +130,11 @@ impl DocumentStore {
             document.vector_embedding.as_ref().map(|v| v.as_slice()),
             serde_json::to_value(&document.metadata)?,
             Utc::now(),
+            calculate_hash(&document.content),
+            simhash(&document.content) as i64,
+            postgres_config(document.metadata.language.as_deref()),
+            segment_cjk(&document.title),
+            segment_cjk(&document.content),
         )
         .execute(&self.pool)
         .await?;
@@ -125,6 +157,181 @@ impl DocumentStore {
         Ok(())
     }
 
+    /// The oldest document with exactly this content
+    pub async fn find_by_content_hash(&self, content_hash: &str) -> Result<Option<Document>> {
+        let record = sqlx::query!(
+            r#"
+            SELECT id::text AS "id!"
+            FROM documents
+            WHERE content_hash = $1
+            ORDER BY created_at
+            LIMIT 1
+            "#,
+            content_hash
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match record {
+            Some(record) => self.get_document(&record.id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// The closest stored document whose SimHash is within `max_distance`
+    /// bits of `text`'s. Near duplicates resolve to the document they copy.
+    pub async fn find_near_duplicate(&self, text: &str, max_distance: u32) -> Result<Option<String>> {
+        let fingerprint = simhash(text);
+        let [b0, b1, b2, b3] = simhash_bands(fingerprint);
+
+        let candidates = sqlx::query!(
+            r#"
+            SELECT
+                id::text AS "id!",
+                simhash AS "simhash!",
+                metadata->>'duplicate_of' AS duplicate_of
+            FROM documents
+            WHERE simhash IS NOT NULL
+              AND ((simhash & 65535) = $1
+                OR ((simhash >> 16) & 65535) = $2
+                OR ((simhash >> 32) & 65535) = $3
+                OR ((simhash >> 48) & 65535) = $4)
+            "#,
+            b0,
+            b1,
+            b2,
+            b3
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(candidates
+            .into_iter()
+            .map(|c| (hamming_distance(fingerprint, c.simhash as u64), c.duplicate_of.unwrap_or(c.id)))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, id)| id))
+    }
+
+    /// Documents ingested from exactly `path`, with the hash of the source bytes
+    pub async fn find_by_source(&self, path: &str) -> Result<Vec<SourceRecord>> {
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                id::text AS "id!",
+                metadata->'custom_metadata'->>'source_hash' AS source_hash
+            FROM documents
+            WHERE metadata->'custom_metadata'->>'source_path' = $1
+            "#,
+            path
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records
+            .into_iter()
+            .map(|r| SourceRecord { id: r.id, source_hash: r.source_hash })
+            .collect())
+    }
+
+    /// Documents ingested from the entries of an archive, by entry source path
+    pub async fn find_archive_entries(&self, archive: &str) -> Result<HashMap<String, Vec<SourceRecord>>> {
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                id::text AS "id!",
+                metadata->'custom_metadata'->>'source_path' AS "path!",
+                metadata->'custom_metadata'->>'source_hash' AS source_hash
+            FROM documents
+            WHERE metadata->'custom_metadata'->>'source_path' LIKE $1 ESCAPE '\'
+            "#,
+            format!("{}!/%", escape_like(archive))
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut entries: HashMap<String, Vec<SourceRecord>> = HashMap::new();
+        for r in records {
+            entries.entry(r.path).or_default().push(SourceRecord { id: r.id, source_hash: r.source_hash });
+        }
+        Ok(entries)
+    }
+
+    /// Distinct source paths under a directory
+    pub async fn source_paths(&self, prefix: &str) -> Result<Vec<String>> {
+        let records = sqlx::query!(
+            r#"
+            SELECT DISTINCT metadata->'custom_metadata'->>'source_path' AS "path!"
+            FROM documents
+            WHERE metadata->'custom_metadata'->>'source_path' LIKE $1 ESCAPE '\'
+            "#,
+            format!("{}/%", escape_like(prefix.trim_end_matches('/')))
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records.into_iter().map(|r| r.path).collect())
+    }
+
+    /// Delete everything ingested from `path`: the file itself, files under it
+    /// when it was a directory, entries when it was an archive, and their
+    /// attachments. Returns the deleted ids.
+    pub async fn delete_by_source(&mut self, path: &str) -> Result<Vec<String>> {
+        let escaped = escape_like(path.trim_end_matches('/'));
+        let ids = sqlx::query!(
+            r#"
+            SELECT id::text AS "id!"
+            FROM documents
+            WHERE metadata->'custom_metadata'->>'source_path' = $1
+               OR metadata->'custom_metadata'->>'source_path' LIKE $2 ESCAPE '\'
+               OR metadata->'custom_metadata'->>'source_path' LIKE $3 ESCAPE '\'
+            "#,
+            path,
+            format!("{}/%", escaped),
+            format!("{}!/%", escaped)
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| r.id)
+        .collect::<Vec<_>>();
+
+        self.delete_with_children(&ids).await
+    }
+
+    /// Delete documents together with their attachments, recursively
+    pub async fn delete_with_children(&mut self, ids: &[String]) -> Result<Vec<String>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let deleted = sqlx::query!(
+            r#"
+            WITH RECURSIVE doomed AS (
+                SELECT id FROM documents WHERE id::text = ANY($1)
+                UNION
+                SELECT d.id FROM documents d
+                JOIN doomed ON d.metadata->>'parent_id' = doomed.id::text
+            )
+            DELETE FROM documents
+            WHERE id IN (SELECT id FROM doomed)
+            RETURNING id::text AS "id!"
+            "#,
+            ids
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| r.id)
+        .collect::<Vec<_>>();
+
+        for id in &deleted {
+            self.cache.remove(id);
+        }
+
+        Ok(deleted)
+    }
+
     pub async fn search_documents(
         &self,
         query: &str,
@@ -136,18 +343,15 @@ impl DocumentStore {
             SELECT 
                 id, title, content, content_type, vector_embedding, metadata
             FROM documents
-            WHERE 
-                to_tsvector('english', content) @@ plainto_tsquery('english', $1)
-                OR to_tsvector('english', title) @@ plainto_tsquery('english', $1)
-            ORDER BY 
-                ts_rank(to_tsvector('english', content), plainto_tsquery('english', $1)) +
-                ts_rank(to_tsvector('english', title), plainto_tsquery('english', $1)) DESC
+            WHERE search_vector @@ search_tsquery($1, $4::text::regconfig)
+            ORDER BY ts_rank_cd(search_vector, search_tsquery($1, $4::text::regconfig)) DESC
             LIMIT $2
             OFFSET $3
             "#,
-            query,
+            segment_cjk(query).as_deref().unwrap_or(query),
             limit.unwrap_or(10),
             offset.unwrap_or(0),
+            Language::detect_query(query).map(|language| language.postgres_config()),
         )
         .fetch_all(&self.pool)
         .await?;
@@ -166,6 +370,13 @@ impl DocumentStore {
     }
 }
 
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
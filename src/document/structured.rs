@@ -0,0 +1,313 @@
+use crate::document::{DocumentMetadata, ExtractedContent};
+use crate::utils::helpers::parse_date;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Which record fields feed which document fields
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FieldMapping {
+    pub title: Option<String>,
+    #[serde(default)]
+    pub content: Vec<String>,            // Joined in order; empty means all unmapped fields
+    pub author: Option<String>,
+    pub tags: Option<String>,
+    pub tag_separator: Option<String>,   // Defaults to ","
+    pub date: Option<String>,
+    pub metadata: Option<Vec<String>>,   // None copies every unmapped field
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StructuredFormat {
+    Csv { delimiter: u8 },
+    JsonArray,
+    Ndjson,
+}
+
+impl StructuredFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "csv" => Some(StructuredFormat::Csv { delimiter: b',' }),
+            "tsv" => Some(StructuredFormat::Csv { delimiter: b'\t' }),
+            "json" => Some(StructuredFormat::JsonArray),
+            "ndjson" | "jsonl" => Some(StructuredFormat::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+/// A record that could not be turned into a document
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordError {
+    pub record: usize,  // 1-based row, array index or line number
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct StructuredExtraction {
+    pub records: Vec<(usize, ExtractedContent)>,
+    pub errors: Vec<RecordError>,
+}
+
+pub struct StructuredExtractor {
+    mapping: FieldMapping,
+}
+
+impl StructuredExtractor {
+    pub fn new(mapping: FieldMapping) -> Self {
+        Self { mapping }
+    }
+
+    pub fn extract(&self, content: &[u8], format: StructuredFormat) -> Result<StructuredExtraction> {
+        let mut extraction = StructuredExtraction::default();
+
+        let rows: Vec<(usize, Result<Map<String, Value>>)> = match format {
+            StructuredFormat::Csv { delimiter } => read_csv(content, delimiter)?,
+            StructuredFormat::JsonArray => read_json_array(content)?,
+            StructuredFormat::Ndjson => read_ndjson(content),
+        };
+
+        for (record, row) in rows {
+            match row.and_then(|row| self.map_record(record, &row)) {
+                Ok(extracted) => extraction.records.push((record, extracted)),
+                Err(e) => extraction.errors.push(RecordError {
+                    record,
+                    message: e.to_string(),
+                }),
+            }
+        }
+
+        Ok(extraction)
+    }
+
+    fn map_record(&self, record: usize, row: &Map<String, Value>) -> Result<ExtractedContent> {
+        let mapping = &self.mapping;
+        let mapped: Vec<&str> = [&mapping.title, &mapping.author, &mapping.tags, &mapping.date]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .chain(mapping.content.iter().map(String::as_str))
+            .collect();
+
+        let field = |name: &Option<String>| -> Option<String> {
+            name.as_ref()
+                .and_then(|n| row.get(n))
+                .and_then(value_to_string)
+                .filter(|v| !v.trim().is_empty())
+        };
+
+        let text = if mapping.content.is_empty() {
+            row.iter()
+                .filter(|(key, _)| !mapped.contains(&key.as_str()))
+                .filter_map(|(key, value)| value_to_string(value).map(|v| format!("{}: {}", key, v)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            mapping.content
+                .iter()
+                .filter_map(|key| row.get(key).and_then(value_to_string))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        if text.trim().is_empty() {
+            anyhow::bail!("Record has no content");
+        }
+
+        let mut metadata = DocumentMetadata {
+            source_type: "record".to_string(),
+            author: field(&mapping.author),
+            ..Default::default()
+        };
+
+        if let Some(date) = field(&mapping.date) {
+            let parsed = parse_date(&date)
+                .with_context(|| format!("Invalid date '{}'", date))?;
+            metadata.created_at = parsed;
+            metadata.last_modified = parsed;
+        }
+
+        if let Some(tags) = mapping.tags.as_ref().and_then(|t| row.get(t)) {
+            let separator = mapping.tag_separator.as_deref().unwrap_or(",");
+            metadata.tags = match tags {
+                Value::Array(items) => items.iter().filter_map(value_to_string).collect(),
+                other => value_to_string(other)
+                    .map(|s| s.split(separator).map(|t| t.trim().to_string()).collect())
+                    .unwrap_or_default(),
+            };
+            metadata.tags.retain(|t| !t.is_empty());
+        }
+
+        for (key, value) in row {
+            let include = match &mapping.metadata {
+                Some(keys) => keys.contains(key),
+                None => !mapped.contains(&key.as_str()),
+            };
+            if include {
+                if let Some(value) = value_to_string(value) {
+                    metadata.custom_metadata.insert(key.clone(), value);
+                }
+            }
+        }
+        metadata.custom_metadata.insert("record".to_string(), record.to_string());
+
+        Ok(ExtractedContent {
+            title: field(&mapping.title),
+            text,
+            metadata,
+            attachments: Vec::new(),
+            ocr: None,
+        })
+    }
+}
+
+fn read_csv(content: &[u8], delimiter: u8) -> Result<Vec<(usize, Result<Map<String, Value>>)>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(content);
+
+    let headers = reader.headers().context("Failed to read CSV header row")?.clone();
+
+    Ok(reader
+        .records()
+        .enumerate()
+        .map(|(index, row)| {
+            let row = row.map_err(anyhow::Error::from).and_then(|row| {
+                if row.len() != headers.len() {
+                    anyhow::bail!("Expected {} columns, found {}", headers.len(), row.len());
+                }
+                Ok(headers
+                    .iter()
+                    .zip(row.iter())
+                    .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+                    .collect())
+            });
+            (index + 1, row)
+        })
+        .collect())
+}
+
+fn read_json_array(content: &[u8]) -> Result<Vec<(usize, Result<Map<String, Value>>)>> {
+    let items: Vec<Value> = serde_json::from_slice(content).context("Expected a JSON array of objects")?;
+
+    Ok(items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| (index + 1, into_object(item)))
+        .collect())
+}
+
+fn read_ndjson(content: &[u8]) -> Vec<(usize, Result<Map<String, Value>>)> {
+    String::from_utf8_lossy(content)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let row = serde_json::from_str::<Value>(line)
+                .map_err(anyhow::Error::from)
+                .and_then(into_object);
+            (index + 1, row)
+        })
+        .collect()
+}
+
+fn into_object(value: Value) -> Result<Map<String, Value>> {
+    match value {
+        Value::Object(map) => Ok(map),
+        other => anyhow::bail!("Expected an object, found {}", other),
+    }
+}
+
+fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        Value::Array(items) => Some(
+            items.iter()
+                .filter_map(value_to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        other => Some(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_mapping() {
+        let csv = "sku,name,description,brand,labels,added\nA1,Desk,Standing desk,Acme,office;furniture,2024-02-01\n";
+        let extractor = StructuredExtractor::new(FieldMapping {
+            title: Some("name".to_string()),
+            content: vec!["description".to_string()],
+            author: Some("brand".to_string()),
+            tags: Some("labels".to_string()),
+            tag_separator: Some(";".to_string()),
+            date: Some("added".to_string()),
+            metadata: Some(vec!["sku".to_string()]),
+        });
+
+        let extraction = extractor.extract(csv.as_bytes(), StructuredFormat::Csv { delimiter: b',' }).unwrap();
+        let (record, doc) = &extraction.records[0];
+
+        assert!(extraction.errors.is_empty());
+        assert_eq!(*record, 1);
+        assert_eq!(doc.title.as_deref(), Some("Desk"));
+        assert_eq!(doc.text, "Standing desk");
+        assert_eq!(doc.metadata.author.as_deref(), Some("Acme"));
+        assert_eq!(doc.metadata.tags, vec!["office", "furniture"]);
+        assert_eq!(doc.metadata.custom_metadata.get("sku").unwrap(), "A1");
+        assert!(!doc.metadata.custom_metadata.contains_key("brand"));
+    }
+
+    #[test]
+    fn test_ndjson_reports_bad_lines() {
+        let ndjson = "{\"title\":\"One\",\"body\":\"first\"}\nnot json\n\n[1,2]\n{\"title\":\"Four\",\"body\":\"\"}\n";
+        let extractor = StructuredExtractor::new(FieldMapping {
+            title: Some("title".to_string()),
+            content: vec!["body".to_string()],
+            ..Default::default()
+        });
+
+        let extraction = extractor.extract(ndjson.as_bytes(), StructuredFormat::Ndjson).unwrap();
+        let failed: Vec<usize> = extraction.errors.iter().map(|e| e.record).collect();
+
+        assert_eq!(extraction.records.len(), 1);
+        assert_eq!(failed, vec![2, 4, 5]);
+    }
+
+    #[test]
+    fn test_json_array_defaults() {
+        let json = r#"[{"id": 7, "subject": "Login fails", "tags": ["auth", "web"], "priority": "high"}]"#;
+        let extractor = StructuredExtractor::new(FieldMapping {
+            title: Some("subject".to_string()),
+            tags: Some("tags".to_string()),
+            ..Default::default()
+        });
+
+        let extraction = extractor.extract(json.as_bytes(), StructuredFormat::JsonArray).unwrap();
+        let (_, doc) = &extraction.records[0];
+
+        assert_eq!(doc.metadata.tags, vec!["auth", "web"]);
+        assert!(doc.text.contains("priority: high"));
+        assert_eq!(doc.metadata.custom_metadata.get("id").unwrap(), "7");
+    }
+
+    #[test]
+    fn test_invalid_date_is_a_record_error() {
+        let csv = "title,body,when\nA,text,yesterday\n";
+        let extractor = StructuredExtractor::new(FieldMapping {
+            title: Some("title".to_string()),
+            content: vec!["body".to_string()],
+            date: Some("when".to_string()),
+            ..Default::default()
+        });
+
+        let extraction = extractor.extract(csv.as_bytes(), StructuredFormat::Csv { delimiter: b',' }).unwrap();
+        assert_eq!(extraction.errors.len(), 1);
+        assert!(extraction.errors[0].message.contains("yesterday"));
+    }
+}
@@ -0,0 +1,207 @@
+use crate::document::directory::{walk, DirectoryOptions, FileOutcome, IngestionProgress, PathFilter};
+use crate::document::ingestion::DocumentIngester;
+use anyhow::{Context, Result};
+use notify::{Event, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    pub debounce: Duration,     // Quiet period before a burst of changes is applied
+    pub initial_sync: bool,     // Reconcile the index with the tree before watching
+    pub directory: DirectoryOptions,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_secs(2),
+            initial_sync: true,
+            directory: DirectoryOptions::default(),
+        }
+    }
+}
+
+/// Keeps the documents ingested from a directory in step with it. Changed
+/// files are re-ingested when their content hash differs from the stored
+/// one, and documents whose source disappears are deleted.
+pub struct DirectoryWatcher {
+    ingester: DocumentIngester,
+    root: PathBuf,
+    options: WatchOptions,
+    filter: PathFilter,
+}
+
+impl DirectoryWatcher {
+    pub fn new(ingester: DocumentIngester, root: impl AsRef<Path>, options: WatchOptions) -> Result<Self> {
+        // inotify reports absolute paths, and stored source paths must match them
+        let root = root.as_ref()
+            .canonicalize()
+            .with_context(|| format!("Cannot watch {}", root.as_ref().display()))?;
+        let filter = PathFilter::new(&root, &options.directory.include, &options.directory.exclude)?;
+
+        Ok(Self { ingester, root, options, filter })
+    }
+
+    /// Watch until the surrounding task is dropped
+    pub async fn run(&self, progress: Option<UnboundedSender<IngestionProgress>>) -> Result<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let _ = tx.send(event);
+        })?;
+        watcher.watch(&self.root, RecursiveMode::Recursive)?;
+        info!("Watching {}", self.root.display());
+
+        if self.options.initial_sync {
+            let mut paths: BTreeSet<PathBuf> = self.ingester
+                .indexed_sources(&self.root)
+                .await?
+                .iter()
+                .map(|source| PathBuf::from(source_file(source)))
+                .collect();
+            paths.insert(self.root.clone());
+            self.apply(paths, &progress).await;
+        }
+
+        let mut pending = BTreeSet::new();
+        loop {
+            let next = if pending.is_empty() {
+                rx.recv().await
+            } else {
+                match tokio::time::timeout(self.options.debounce, rx.recv()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        self.apply(std::mem::take(&mut pending), &progress).await;
+                        continue;
+                    }
+                }
+            };
+
+            match next {
+                Some(Ok(event)) if !event.kind.is_access() => pending.extend(event.paths),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => warn!("Watch error under {}: {}", self.root.display(), e),
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn apply(&self, paths: BTreeSet<PathBuf>, progress: &Option<UnboundedSender<IngestionProgress>>) {
+        let (existing, removed): (Vec<_>, Vec<_>) = paths
+            .into_iter()
+            .filter(|path| !self.is_hidden(path))
+            .partition(|path| path.exists());
+
+        for path in removed {
+            match self.ingester.remove_source(&path.display().to_string()).await {
+                Ok(ids) if !ids.is_empty() => info!("Removed {} documents for {}", ids.len(), path.display()),
+                Ok(_) => {}
+                Err(e) => warn!("Failed to remove documents for {}: {}", path.display(), e),
+            }
+        }
+
+        let files = {
+            let (options, filter) = (self.options.directory.clone(), self.filter.clone());
+            tokio::task::spawn_blocking(move || admitted(existing, &options, &filter))
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|files| files)
+        };
+        let files = match files {
+            Ok(files) => files,
+            Err(e) => {
+                warn!("Failed to scan changes under {}: {}", self.root.display(), e);
+                return;
+            }
+        };
+
+        let total = files.len();
+        for (index, path) in files.into_iter().enumerate() {
+            let display = path.display().to_string();
+            let results = self.ingester
+                .sync_file(path, &self.options.directory, &self.filter)
+                .await
+                .unwrap_or_else(|e| vec![(display, FileOutcome::Failed(e.to_string()))]);
+
+            for (source, outcome) in results {
+                if let FileOutcome::Failed(e) = &outcome {
+                    warn!("Failed to re-ingest {}: {}", source, e);
+                }
+                if let Some(progress) = progress {
+                    let status = outcome.status(index + 1, total);
+                    let _ = progress.send(IngestionProgress { path: source, completed: index + 1, total, status });
+                }
+            }
+        }
+    }
+
+    /// Dotfiles and dot-directories are skipped by the walk, and editors
+    /// churn through them constantly
+    fn is_hidden(&self, path: &Path) -> bool {
+        path.strip_prefix(&self.root)
+            .map(|relative| {
+                relative.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+            })
+            .unwrap_or(true)
+    }
+}
+
+/// Changed paths that a directory walk would pick up. Files are checked by
+/// walking their parent one level deep so ignore files and globs still apply.
+fn admitted(paths: Vec<PathBuf>, options: &DirectoryOptions, filter: &PathFilter) -> Result<Vec<PathBuf>> {
+    let mut files = BTreeSet::new();
+    let mut by_parent: BTreeMap<PathBuf, HashSet<PathBuf>> = BTreeMap::new();
+
+    for path in paths {
+        if path.is_dir() {
+            files.extend(walk(&path, options, filter, None)?);
+        } else if let Some(parent) = path.parent() {
+            by_parent.entry(parent.to_path_buf()).or_default().insert(path);
+        }
+    }
+
+    for (parent, changed) in by_parent {
+        files.extend(
+            walk(&parent, options, filter, Some(1))?
+                .into_iter()
+                .filter(|path| changed.contains(path)),
+        );
+    }
+
+    Ok(files.into_iter().collect())
+}
+
+/// The file on disk behind a stored source path; archive entries map to the archive
+fn source_file(source: &str) -> &str {
+    source.split_once("!/").map_or(source, |(archive, _)| archive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_file() {
+        assert_eq!(source_file("/docs/guide.md"), "/docs/guide.md");
+        assert_eq!(source_file("/docs/bundle.zip!/a/b.txt"), "/docs/bundle.zip");
+    }
+
+    #[test]
+    fn test_admitted_applies_ignore_files() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join(".gitignore"), "*.tmp\n").unwrap();
+        std::fs::write(root.path().join("notes.md"), "notes").unwrap();
+        std::fs::write(root.path().join("scratch.tmp"), "scratch").unwrap();
+
+        let options = DirectoryOptions::default();
+        let filter = PathFilter::new(root.path(), &[], &[]).unwrap();
+        let changed = vec![root.path().join("notes.md"), root.path().join("scratch.tmp")];
+
+        assert_eq!(admitted(changed, &options, &filter).unwrap(), vec![root.path().join("notes.md")]);
+    }
+}
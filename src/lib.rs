@@ -10,6 +10,11 @@ pub mod vector;
 pub mod config;
 pub mod telemetry;
 pub mod utils;
+pub mod webhooks;
+pub mod spell_checker;
+pub mod query_expander;
+pub mod trie;
+pub mod suggestions;
 
 use anyhow::Result;
 
@@ -1,59 +1,160 @@
 use modern_search_engine::{
-    api::{routes, error::handle_rejection},
+    api::{routes, auth::ApiKeys, error::handle_rejection, processor::DocumentProcessor, queue::JobQueue},
     config::Config,
-    search::engine::SearchEngine,
-    document::processor::DocumentProcessor,
+    document::{
+        directory::DirectoryOptions,
+        ingestion::DocumentIngester,
+        ocr::{OcrEngine, TesseractOcr},
+        store::DocumentStore,
+        watcher::{DirectoryWatcher, WatchOptions},
+    },
+    query_expander::QueryExpander,
+    search::{
+        engine::{SearchConfig, SearchEngine},
+        cross_encoder::Reranker,
+        ranking::RankingModel,
+        QueryAnalytics, SearchHistory, TextIndex,
+    },
     vector::store::VectorStore,
-    telemetry::{init_telemetry, MetricsCollector},
+    webhooks::WebhookDispatcher,
+    telemetry::init_telemetry,
 };
 
+use sqlx::postgres::PgPoolOptions;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use anyhow::Result;
 use tracing::{info, error};
+use warp::Filter;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize configuration
-    let config = Config::from_env()?;
+    let config = Config::new()?;
 
     // Initialize telemetry
-    init_telemetry(&config.service_name)?;
+    init_telemetry(&config)?;
     info!("Starting search engine v2...");
 
-    // Initialize metrics collector
-    let metrics = Arc::new(MetricsCollector::new());
+    let pool = PgPoolOptions::new()
+        .min_connections(config.database.min_connections)
+        .max_connections(config.database.max_connections)
+        .connect(&config.database.url)
+        .await?;
 
     // Initialize vector store
-    let vector_store = Arc::new(RwLock::new(VectorStore::new(&config).await?));
+    let vector_store = Arc::new(RwLock::new(VectorStore::new().await?));
     info!("Vector store initialized");
 
+    // Open the full-text index, committing changes in batches
+    let text_index = Arc::new(
+        TextIndex::open(&config.search.text_index.path)?
+            .with_commit_batch(config.search.text_index.commit_batch),
+    );
+    text_index.start_commits(Duration::from_millis(config.search.text_index.commit_interval_ms.max(1)));
+
+    // Synonyms are edited through the API even when queries are not expanded
+    let expander = Arc::new(QueryExpander::from_config(config.search.expansion.clone())?);
+
     // Initialize search engine
-    let search_engine = Arc::new(SearchEngine::new(
-        vector_store.clone(),
-        &config.search,
-    )?);
+    let mut search_engine = SearchEngine::new(vector_store.clone(), SearchConfig::from(&config.search))
+        .with_text_index(text_index);
+    if config.search.use_query_expansion {
+        search_engine = search_engine.with_query_expander(expander.clone());
+    }
+    if let Some(path) = &config.search.ranking.model_path {
+        search_engine = search_engine.with_ranking_model(Arc::new(RankingModel::load(path)?));
+        info!("Ranking model loaded from {}", path.display());
+    }
+    if config.search.cross_encoder.enabled {
+        let reranker = Reranker::from_config(config.search.cross_encoder.clone())?;
+        search_engine = search_engine.with_reranker(Arc::new(reranker));
+        info!("Cross-encoder loaded from {}", config.search.cross_encoder.model_path.display());
+    }
+    let search_engine = Arc::new(search_engine);
     info!("Search engine initialized");
 
+    if config.search.use_query_expansion && config.search.expansion.neighbour_expansion {
+        let engine = search_engine.clone();
+        tokio::spawn(async move {
+            match engine.load_neighbour_expansion().await {
+                Ok(count) => info!("Loaded expansion neighbours of {} words", count),
+                Err(e) => error!("Failed to load expansion neighbours: {}", e),
+            }
+        });
+    }
+
+    let documents = Arc::new(RwLock::new(DocumentStore::from_pool(pool.clone())));
+
+    // Start webhook delivery
+    let webhooks = WebhookDispatcher::new(pool.clone(), config.webhooks.clone())?;
+    webhooks.start();
+
+    let ocr_engine: Option<Arc<dyn OcrEngine>> = if config.processing.ocr.enabled {
+        info!("OCR enabled for languages {}", config.processing.ocr.languages);
+        Some(Arc::new(TesseractOcr::new(&config.processing.ocr)))
+    } else {
+        None
+    };
+
     // Initialize document processor
-    let document_processor = Arc::new(DocumentProcessor::new(
+    let mut document_processor = DocumentProcessor::new(
         vector_store.clone(),
         search_engine.clone(),
-        &config.processor,
-    )?);
+        JobQueue::new(pool.clone(), config.processing.queue.clone()),
+    )
+    .with_document_store(documents.clone(), config.processing.dedup.clone())
+    .with_webhooks(webhooks.clone());
+    if let Some(engine) = &ocr_engine {
+        document_processor = document_processor.with_ocr_engine(engine.clone());
+    }
+    let document_processor = Arc::new(document_processor);
+    document_processor.start_workers(config.processing.processing_threads);
     info!("Document processor initialized");
 
+    // Keep watched directories in sync with the index
+    let mut ingester = DocumentIngester::new(vector_store.clone(), documents)
+        .with_search_engine(search_engine.clone())
+        .with_dedup(config.processing.dedup.clone())
+        .with_webhooks(webhooks.clone());
+    if let Some(engine) = ocr_engine {
+        ingester = ingester.with_ocr_engine(engine);
+    }
+    for root in &config.processing.watch_directories {
+        let options = WatchOptions {
+            directory: DirectoryOptions::from_config(&config.processing),
+            ..Default::default()
+        };
+        let watcher = DirectoryWatcher::new(ingester.clone(), root, options)?;
+        info!("Watching {}", root.display());
+        tokio::spawn(async move {
+            if let Err(e) = watcher.run(None).await {
+                error!("Directory watcher stopped: {}", e);
+            }
+        });
+    }
+
+    let history = Arc::new(SearchHistory::new(pool.clone(), config.search.history.clone()));
+    history.start_refresh(search_engine.clone());
+    let analytics = Arc::new(QueryAnalytics::new(pool.clone()));
+
     // Setup API routes
     let routes = routes::create_routes(
         search_engine,
-        document_processor,
-        metrics.clone(),
-        &config,
+        document_processor.clone(),
+        history,
+        analytics,
+        Arc::new(webhooks),
+        expander,
+        ApiKeys::new(pool.clone()),
+        &config.processing,
     )
     .recover(handle_rejection);
 
     // Start cleanup task
-    let cleanup_interval = config.cleanup_interval;
+    let cleanup_interval = Duration::from_secs(config.processing.cleanup_interval);
     let processor_clone = document_processor.clone();
     tokio::spawn(async move {
         loop {
@@ -64,22 +165,13 @@ async fn main() -> Result<()> {
         }
     });
 
-    // Start metrics collection
-    let metrics_clone = metrics.clone();
-    tokio::spawn(async move {
-        loop {
-            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
-            metrics_clone.collect().await;
-        }
-    });
-
     // Start server
-    let addr = ([127, 0, 0, 1], config.port).into();
+    let addr: SocketAddr = format!("{}:{}", config.server.host, config.server.port).parse()?;
     info!("Server listening on http://{}", addr);
-    
+
     warp::serve(routes)
         .run(addr)
         .await;
 
     Ok(())
-}
\ No newline at end of file
+}
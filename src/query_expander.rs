@@ -1,12 +1,323 @@
-#[derive(Clone)]
-pub struct QueryExpander;
+use crate::config::ExpansionConfig;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use uuid::Uuid;
+
+/// A synonym rule. Without `expands_to` all terms are equivalent and
+/// each expands to the others; with it, the terms expand one way only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SynonymRule {
+    #[serde(default)]
+    pub id: String,  // Assigned when the rule is loaded or added
+    pub terms: Vec<String>,
+    #[serde(default)]
+    pub expands_to: Vec<String>,
+}
+
+/// Phrase (as lowercase words) to the terms it expands to
+type SynonymIndex = HashMap<Vec<String>, Vec<String>>;
+
+/// Vocabulary word to its nearest words by embedding, with similarity
+pub type Neighbours = HashMap<String, Vec<(String, f32)>>;
+
+/// Adds synonyms and embedding neighbours of the query's words to it,
+/// boosted below the words the user typed
+pub struct QueryExpander {
+    config: ExpansionConfig,
+    rules: RwLock<Vec<SynonymRule>>,
+    index: RwLock<SynonymIndex>,
+    neighbours: RwLock<Neighbours>,
+}
 
 impl QueryExpander {
     pub fn new() -> Self {
-        QueryExpander
+        Self::with_rules(ExpansionConfig::default(), Vec::new())
+    }
+
+    /// Loads the synonym file when one is configured and exists
+    pub fn from_config(config: ExpansionConfig) -> Result<Self> {
+        let rules = match &config.synonyms_path {
+            Some(path) if path.exists() => parse_rules(
+                &std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read synonyms from {}", path.display()))?,
+            )?,
+            _ => Vec::new(),
+        };
+        Ok(Self::with_rules(config, rules))
+    }
+
+    fn with_rules(config: ExpansionConfig, rules: Vec<SynonymRule>) -> Self {
+        let index = build_index(&rules);
+        Self {
+            config,
+            rules: RwLock::new(rules),
+            index: RwLock::new(index),
+            neighbours: RwLock::new(Neighbours::new()),
+        }
+    }
+
+    pub fn config(&self) -> &ExpansionConfig {
+        &self.config
+    }
+
+    pub fn rules(&self) -> Vec<SynonymRule> {
+        self.rules.read().unwrap().clone()
+    }
+
+    pub fn add_rule(&self, mut rule: SynonymRule) -> Result<SynonymRule> {
+        rule.terms = normalize_terms(&rule.terms);
+        rule.expands_to = normalize_terms(&rule.expands_to);
+        if rule.terms.is_empty() || (rule.expands_to.is_empty() && rule.terms.len() < 2) {
+            anyhow::bail!("A rule needs two equivalent terms, or terms and what they expand to");
+        }
+        rule.id = Uuid::new_v4().to_string();
+
+        let mut rules = self.rules.write().unwrap();
+        rules.push(rule.clone());
+        self.commit(&rules)?;
+        Ok(rule)
     }
 
+    pub fn remove_rule(&self, id: &str) -> Result<bool> {
+        let mut rules = self.rules.write().unwrap();
+        let before = rules.len();
+        rules.retain(|rule| rule.id != id);
+        if rules.len() == before {
+            return Ok(false);
+        }
+
+        self.commit(&rules)?;
+        Ok(true)
+    }
+
+    /// Re-read the synonym file, dropping rules added since that were not saved
+    pub fn reload(&self) -> Result<usize> {
+        let Some(path) = &self.config.synonyms_path else {
+            return Ok(self.rules.read().unwrap().len());
+        };
+        let loaded = parse_rules(&std::fs::read_to_string(path)?)?;
+
+        let mut rules = self.rules.write().unwrap();
+        *rules = loaded;
+        *self.index.write().unwrap() = build_index(&rules);
+        Ok(rules.len())
+    }
+
+    /// Rebuild the lookup index and save the rules to the synonym file
+    fn commit(&self, rules: &[SynonymRule]) -> Result<()> {
+        if let Some(path) = &self.config.synonyms_path {
+            let text: String = rules.iter().map(|rule| format_rule(rule) + "\n").collect();
+            let temp = path.with_extension("tmp");
+            std::fs::write(&temp, text)?;
+            std::fs::rename(&temp, path)?;
+        }
+
+        *self.index.write().unwrap() = build_index(rules);
+        Ok(())
+    }
+
+    pub fn set_neighbours(&self, neighbours: Neighbours) {
+        *self.neighbours.write().unwrap() = neighbours;
+    }
+
+    /// The query followed by its expansions, each boosted below 1 in the
+    /// text query syntax, e.g. `k8s "kubernetes"^0.50`
     pub fn expand(&self, query: &str) -> String {
-        query.to_string()
+        let words: Vec<String> = query
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+
+        let mut expansions: Vec<(String, f32)> = Vec::new();
+        let mut add = |term: &str, weight: f32| {
+            let term = term.to_lowercase();
+            if words.contains(&term) {
+                return;
+            }
+            match expansions.iter_mut().find(|(t, _)| *t == term) {
+                Some((_, w)) => *w = w.max(weight),
+                None => expansions.push((term, weight)),
+            }
+        };
+
+        let index = self.index.read().unwrap();
+        let longest = index.keys().map(Vec::len).max().unwrap_or(0);
+        for start in 0..words.len() {
+            for length in 1..=longest.min(words.len() - start) {
+                if let Some(terms) = index.get(&words[start..start + length]) {
+                    for term in terms {
+                        add(term, self.config.synonym_weight);
+                    }
+                }
+            }
+        }
+
+        let neighbours = self.neighbours.read().unwrap();
+        for word in &words {
+            for (neighbour, similarity) in neighbours.get(word).into_iter().flatten() {
+                add(neighbour, self.config.neighbour_weight * similarity);
+            }
+        }
+
+        if expansions.is_empty() {
+            return query.to_string();
+        }
+
+        let boosted: Vec<String> = expansions
+            .into_iter()
+            .map(|(term, weight)| format!("\"{}\"^{:.2}", term.replace('"', ""), weight))
+            .collect();
+        format!("{} {}", query, boosted.join(" "))
+    }
+}
+
+impl Default for QueryExpander {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn build_index(rules: &[SynonymRule]) -> SynonymIndex {
+    let mut index: SynonymIndex = HashMap::new();
+
+    for rule in rules {
+        for term in &rule.terms {
+            let targets = if rule.expands_to.is_empty() {
+                rule.terms.iter().filter(|t| *t != term).cloned().collect()
+            } else {
+                rule.expands_to.clone()
+            };
+
+            let key = term.split_whitespace().map(str::to_string).collect();
+            let entry = index.entry(key).or_default();
+            for target in targets {
+                if !entry.contains(&target) {
+                    entry.push(target);
+                }
+            }
+        }
+    }
+
+    index
+}
+
+fn normalize_terms(terms: &[String]) -> Vec<String> {
+    terms
+        .iter()
+        .map(|term| term.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase())
+        .filter(|term| !term.is_empty())
+        .collect()
+}
+
+/// Rules in the Solr synonym file format, one per line:
+/// `k8s, kubernetes` for equivalent terms and `js => javascript` for one
+/// way expansion. `#` starts a comment.
+pub fn parse_rules(text: &str) -> Result<Vec<SynonymRule>> {
+    let mut rules = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let split = |side: &str| normalize_terms(&side.split(',').map(str::to_string).collect::<Vec<_>>());
+        let (terms, expands_to) = match line.split_once("=>") {
+            Some((left, right)) => (split(left), split(right)),
+            None => (split(line), Vec::new()),
+        };
+
+        if terms.is_empty() || (expands_to.is_empty() && terms.len() < 2) {
+            anyhow::bail!("Invalid synonym rule on line {}: {}", number + 1, line);
+        }
+        rules.push(SynonymRule {
+            id: Uuid::new_v4().to_string(),
+            terms,
+            expands_to,
+        });
+    }
+
+    Ok(rules)
+}
+
+pub fn format_rule(rule: &SynonymRule) -> String {
+    if rule.expands_to.is_empty() {
+        rule.terms.join(", ")
+    } else {
+        format!("{} => {}", rule.terms.join(", "), rule.expands_to.join(", "))
+    }
+}
+
+/// Up to `k` nearest other words for each embedded word, by cosine
+/// similarity, keeping those at or above `min_similarity`
+pub fn nearest_neighbours(embedded: &[(String, Vec<f32>)], k: usize, min_similarity: f32) -> Neighbours {
+    let normalized: Vec<Vec<f32>> = embedded
+        .iter()
+        .map(|(_, vector)| {
+            let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+            vector.iter().map(|x| if norm > 0.0 { x / norm } else { 0.0 }).collect()
+        })
+        .collect();
+
+    let mut neighbours = Neighbours::new();
+    for (i, (word, _)) in embedded.iter().enumerate() {
+        let mut nearest: Vec<(String, f32)> = embedded
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(j, (other, _))| {
+                let similarity = normalized[i].iter().zip(&normalized[j]).map(|(a, b)| a * b).sum();
+                (other.clone(), similarity)
+            })
+            .filter(|(_, similarity)| *similarity >= min_similarity)
+            .collect();
+
+        nearest.sort_by(|a, b| b.1.total_cmp(&a.1));
+        nearest.truncate(k);
+        if !nearest.is_empty() {
+            neighbours.insert(word.clone(), nearest);
+        }
+    }
+
+    neighbours
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_with_synonyms() {
+        let rules = parse_rules("k8s, kubernetes\n# languages\njs => javascript\nml => machine learning\n").unwrap();
+        let expander = QueryExpander::with_rules(ExpansionConfig::default(), rules);
+
+        assert_eq!(expander.expand("deploy k8s"), "deploy k8s \"kubernetes\"^0.50");
+        assert_eq!(expander.expand("Kubernetes"), "Kubernetes \"k8s\"^0.50");
+        assert_eq!(expander.expand("ml js"), "ml js \"machine learning\"^0.50 \"javascript\"^0.50");
+        assert_eq!(expander.expand("javascript"), "javascript");
+        assert!(parse_rules("lonely").is_err());
+    }
+
+    #[test]
+    fn test_multi_word_terms_and_neighbours() {
+        let rules = parse_rules("machine learning => ml").unwrap();
+        let expander = QueryExpander::with_rules(ExpansionConfig::default(), rules);
+        expander.set_neighbours(nearest_neighbours(
+            &[
+                ("car".to_string(), vec![1.0, 0.1]),
+                ("automobile".to_string(), vec![0.9, 0.12]),
+                ("banana".to_string(), vec![0.0, 1.0]),
+            ],
+            2,
+            0.9,
+        ));
+
+        assert_eq!(expander.expand("machine learning basics"), "machine learning basics \"ml\"^0.50");
+        let expanded = expander.expand("car");
+        assert!(expanded.starts_with("car \"automobile\"^0.3"));
+        assert!(!expanded.contains("banana"));
     }
-}
\ No newline at end of file
+}
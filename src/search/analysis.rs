@@ -0,0 +1,300 @@
+use crate::search::cjk::{script_language, CjkTokenizer};
+use serde::{Deserialize, Serialize};
+use tantivy::schema::{
+    Field, IndexRecordOption, Schema, SchemaBuilder, TextFieldIndexing, TextOptions, STORED, STRING,
+};
+use tantivy::tokenizer::{
+    Language as StemmerLanguage, LowerCaser, RemoveLongFilter, Stemmer, StopWordFilter,
+    TextAnalyzer, TokenizerManager,
+};
+
+/// Analyzer for text in an unknown or unsupported language: no stemming
+/// and no stopwords. Like every analyzer it bigrams CJK runs.
+pub const DEFAULT_ANALYZER: &str = "lang_default";
+
+/// Postgres text search configuration for the same case
+pub const DEFAULT_POSTGRES_CONFIG: &str = "simple";
+
+/// Tokens longer than this are dropped, as tantivy's default analyzer does
+const MAX_TOKEN_LENGTH: usize = 40;
+
+/// Languages indexed in fields of their own. English, German and French
+/// are stemmed; Chinese, Japanese and Korean are indexed as bigrams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    English,
+    German,
+    French,
+    Chinese,
+    Japanese,
+    Korean,
+}
+
+impl Language {
+    pub const ALL: [Language; 6] = [
+        Language::English,
+        Language::German,
+        Language::French,
+        Language::Chinese,
+        Language::Japanese,
+        Language::Korean,
+    ];
+
+    /// ISO 639-1 code, also the suffix of the language's index fields
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::French => "fr",
+            Language::Chinese => "zh",
+            Language::Japanese => "ja",
+            Language::Korean => "ko",
+        }
+    }
+
+    /// Accepts ISO 639-1 and 639-3 codes as well as English names
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim().to_lowercase().as_str() {
+            "en" | "eng" | "english" => Some(Language::English),
+            "de" | "deu" | "ger" | "german" => Some(Language::German),
+            "fr" | "fra" | "fre" | "french" => Some(Language::French),
+            "zh" | "zho" | "chi" | "cmn" | "chinese" => Some(Language::Chinese),
+            "ja" | "jpn" | "japanese" => Some(Language::Japanese),
+            "ko" | "kor" | "korean" => Some(Language::Korean),
+            _ => None,
+        }
+    }
+
+    pub fn analyzer(&self) -> String {
+        format!("lang_{}", self.code())
+    }
+
+    /// Postgres has no CJK configurations; that text is stored pre-segmented
+    /// into bigrams and searched with `simple`
+    pub fn postgres_config(&self) -> &'static str {
+        match self {
+            Language::English => "english",
+            Language::German => "german",
+            Language::French => "french",
+            Language::Chinese | Language::Japanese | Language::Korean => DEFAULT_POSTGRES_CONFIG,
+        }
+    }
+
+    fn stemmer_language(&self) -> Option<StemmerLanguage> {
+        match self {
+            Language::English => Some(StemmerLanguage::English),
+            Language::German => Some(StemmerLanguage::German),
+            Language::French => Some(StemmerLanguage::French),
+            Language::Chinese | Language::Japanese | Language::Korean => None,
+        }
+    }
+
+    fn whatlang(&self) -> whatlang::Lang {
+        match self {
+            Language::English => whatlang::Lang::Eng,
+            Language::German => whatlang::Lang::Deu,
+            Language::French => whatlang::Lang::Fra,
+            Language::Chinese => whatlang::Lang::Cmn,
+            Language::Japanese => whatlang::Lang::Jpn,
+            Language::Korean => whatlang::Lang::Kor,
+        }
+    }
+
+    /// Language of a search query, only when detection is confident. Short
+    /// queries usually are not, and are then searched in every language.
+    pub fn detect_query(query: &str) -> Option<Self> {
+        if let Some(language) = script_language(query) {
+            return Some(language);
+        }
+
+        let allowed = Self::ALL.iter().map(Language::whatlang).collect();
+        let info = whatlang::Detector::with_allowlist(allowed).detect(query)?;
+
+        if !info.is_reliable() {
+            return None;
+        }
+        Self::ALL.into_iter().find(|language| language.whatlang() == info.lang())
+    }
+}
+
+/// ISO 639-3 code of the language `text` is written in, when detection is
+/// reliable. Text mostly in CJK script falls back to its script.
+pub fn detect_language(text: &str) -> Option<String> {
+    whatlang::detect(text)
+        .filter(|info| info.is_reliable())
+        .map(|info| info.lang())
+        .or_else(|| script_language(text).map(|language| language.whatlang()))
+        .map(|lang| lang.code().to_string())
+}
+
+/// Postgres text search configuration for a stored language code
+pub fn postgres_config(language: Option<&str>) -> &'static str {
+    language
+        .and_then(Language::from_code)
+        .map_or(DEFAULT_POSTGRES_CONFIG, |language| language.postgres_config())
+}
+
+pub fn register_analyzers(manager: &TokenizerManager) {
+    manager.register(DEFAULT_ANALYZER, base_analyzer());
+
+    for language in Language::ALL {
+        let mut analyzer = base_analyzer();
+        if let Some(stemmer_language) = language.stemmer_language() {
+            if let Some(stopwords) = StopWordFilter::new(stemmer_language) {
+                analyzer = analyzer.filter(stopwords);
+            }
+            analyzer = analyzer.filter(Stemmer::new(stemmer_language));
+        }
+        manager.register(&language.analyzer(), analyzer);
+    }
+}
+
+fn base_analyzer() -> TextAnalyzer {
+    TextAnalyzer::from(CjkTokenizer)
+        .filter(RemoveLongFilter::limit(MAX_TOKEN_LENGTH))
+        .filter(LowerCaser)
+}
+
+/// Title and content fields indexed with one analyzer
+#[derive(Debug, Clone, Copy)]
+pub struct AnalyzedFields {
+    pub title: Field,
+    pub content: Field,
+}
+
+/// The full-text schema. Each document's title and content are indexed
+/// only in the fields of its language; the originals are stored once.
+#[derive(Debug, Clone)]
+pub struct TextSchema {
+    pub schema: Schema,
+    pub id: Field,
+    pub language: Field,
+    pub title: Field,
+    pub content: Field,
+    /// Every document's words unstemmed, the vocabulary for spelling correction
+    pub words: Field,
+    /// Code blocks, kept apart from the prose and never stemmed
+    pub code: Field,
+    default: AnalyzedFields,
+    languages: Vec<(Language, AnalyzedFields)>,
+}
+
+impl TextSchema {
+    pub fn build() -> Self {
+        let mut builder = Schema::builder();
+        let id = builder.add_text_field("id", STRING | STORED);
+        let language = builder.add_text_field("language", STRING | STORED);
+        let title = builder.add_text_field("title", STORED);
+        let content = builder.add_text_field("content", STORED);
+        let words = builder.add_text_field(
+            "words",
+            TextOptions::default().set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer(DEFAULT_ANALYZER)
+                    .set_index_option(IndexRecordOption::Basic),
+            ),
+        );
+        let code = builder.add_text_field(
+            "code",
+            TextOptions::default().set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer(DEFAULT_ANALYZER)
+                    .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+            ),
+        );
+
+        let default = add_analyzed_fields(&mut builder, "default", DEFAULT_ANALYZER);
+        let languages = Language::ALL
+            .into_iter()
+            .map(|language| (language, add_analyzed_fields(&mut builder, language.code(), &language.analyzer())))
+            .collect();
+
+        Self {
+            schema: builder.build(),
+            id,
+            language,
+            title,
+            content,
+            words,
+            code,
+            default,
+            languages,
+        }
+    }
+
+    pub fn fields_for(&self, language: Option<Language>) -> AnalyzedFields {
+        language
+            .and_then(|language| self.languages.iter().find(|(l, _)| *l == language))
+            .map_or(self.default, |(_, fields)| *fields)
+    }
+
+    /// Fields a query in `language` runs against. Documents of unknown
+    /// language are always included; without a language, every field is.
+    /// CJK queries also run everywhere, since every analyzer bigrams CJK
+    /// text alike and other-language documents often quote it.
+    pub fn query_fields(&self, language: Option<Language>) -> Vec<AnalyzedFields> {
+        match language {
+            Some(language) if language.stemmer_language().is_some() => {
+                vec![self.fields_for(Some(language)), self.default]
+            }
+            _ => std::iter::once(self.default)
+                .chain(self.languages.iter().map(|(_, fields)| *fields))
+                .collect(),
+        }
+    }
+}
+
+fn add_analyzed_fields(builder: &mut SchemaBuilder, suffix: &str, analyzer: &str) -> AnalyzedFields {
+    let options = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(analyzer)
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+    );
+
+    AnalyzedFields {
+        title: builder.add_text_field(&format!("title_{}", suffix), options.clone()),
+        content: builder.add_text_field(&format!("content_{}", suffix), options),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(manager: &TokenizerManager, analyzer: &str, text: &str) -> Vec<String> {
+        let mut stream = manager.get(analyzer).unwrap().token_stream(text);
+        let mut tokens = Vec::new();
+        while stream.advance() {
+            tokens.push(stream.token().text.clone());
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_language_analyzers() {
+        let manager = TokenizerManager::default();
+        register_analyzers(&manager);
+
+        let de = Language::German.analyzer();
+        assert_eq!(tokens(&manager, &de, "Katzen"), tokens(&manager, &de, "Katze"));
+        assert!(tokens(&manager, &de, "die Katze und der Hund").len() == 2);
+
+        let fr = Language::French.analyzer();
+        assert_eq!(tokens(&manager, &fr, "documents"), tokens(&manager, &fr, "document"));
+
+        // The default analyzer leaves words alone
+        assert_eq!(tokens(&manager, DEFAULT_ANALYZER, "The Running"), vec!["the", "running"]);
+    }
+
+    #[test]
+    fn test_language_codes() {
+        assert_eq!(Language::from_code("deu"), Some(Language::German));
+        assert_eq!(Language::from_code("FR"), Some(Language::French));
+        assert_eq!(Language::from_code("spa"), None);
+        assert_eq!(postgres_config(Some("eng")), "english");
+        assert_eq!(postgres_config(None), "simple");
+        assert_eq!(postgres_config(Some("jpn")), "simple");
+        assert_eq!(Language::detect_query("東京の天気"), Some(Language::Japanese));
+    }
+}
@@ -0,0 +1,219 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+
+/// The longest window reports may cover
+const MAX_WINDOW_DAYS: u32 = 365;
+
+#[derive(Debug, Serialize)]
+pub struct QueryStats {
+    pub query: String,
+    pub searches: i64,
+    pub users: i64,
+    pub avg_results: f64,
+    pub avg_execution_time_ms: f64,
+    pub click_through_rate: f64,
+    pub last_searched: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ZeroResultQuery {
+    pub query: String,
+    pub searches: i64,
+    pub users: i64,
+    pub last_searched: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClickThrough {
+    pub query: String,
+    pub searches: i64,
+    pub clicked_searches: i64,
+    pub click_through_rate: f64,
+    pub mean_first_click_position: Option<f64>,  // Over searches with a click
+}
+
+#[derive(Debug, Serialize)]
+pub struct LatencyStats {
+    pub window_days: u32,
+    pub searches: i64,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub mean_ms: Option<f64>,
+    pub max_ms: Option<i32>,
+}
+
+/// Reports over `search_history` and the `search_feedback` on its results, for the
+/// last `days` days
+#[derive(Clone)]
+pub struct QueryAnalytics {
+    pool: PgPool,
+}
+
+impl QueryAnalytics {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// The most searched queries
+    pub async fn top_queries(&self, days: u32, limit: i64) -> Result<Vec<QueryStats>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                h.query,
+                COUNT(*) AS "searches!",
+                COUNT(DISTINCT h.user_id) AS "users!",
+                COALESCE(AVG(h.result_count), 0)::float8 AS "avg_results!",
+                COALESCE(AVG(h.execution_time_ms), 0)::float8 AS "avg_execution_time_ms!",
+                COUNT(*) FILTER (WHERE EXISTS (SELECT 1 FROM search_feedback f WHERE f.search_id = h.id AND f.clicked)) AS "clicked!",
+                MAX(h.timestamp) AS "last_searched!"
+            FROM search_history h
+            WHERE h.timestamp > NOW() - make_interval(days => $1)
+            GROUP BY h.query
+            ORDER BY 2 DESC, h.query
+            LIMIT $2
+            "#,
+            window_days(days) as i32,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| QueryStats {
+                click_through_rate: rate(row.clicked, row.searches),
+                query: row.query,
+                searches: row.searches,
+                users: row.users,
+                avg_results: row.avg_results,
+                avg_execution_time_ms: row.avg_execution_time_ms,
+                last_searched: row.last_searched,
+            })
+            .collect())
+    }
+
+    /// Queries that found nothing, most searched first: what the content
+    /// is missing
+    pub async fn zero_result_queries(&self, days: u32, limit: i64) -> Result<Vec<ZeroResultQuery>> {
+        let queries = sqlx::query_as!(
+            ZeroResultQuery,
+            r#"
+            SELECT
+                query,
+                COUNT(*) AS "searches!",
+                COUNT(DISTINCT user_id) AS "users!",
+                MAX(timestamp) AS "last_searched!"
+            FROM search_history
+            WHERE timestamp > NOW() - make_interval(days => $1) AND result_count = 0
+            GROUP BY query
+            ORDER BY 2 DESC, query
+            LIMIT $2
+            "#,
+            window_days(days) as i32,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(queries)
+    }
+
+    /// Share of searches with at least one click, for queries searched at
+    /// least `min_searches` times, lowest first
+    pub async fn click_through(&self, days: u32, min_searches: i64, limit: i64) -> Result<Vec<ClickThrough>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                h.query,
+                COUNT(*) AS "searches!",
+                COUNT(*) FILTER (WHERE first_click.clicked) AS "clicked!",
+                AVG(first_click.position)::float8 AS mean_first_click_position
+            FROM search_history h
+            LEFT JOIN LATERAL (
+                SELECT bool_or(f.clicked) AS clicked, MIN(f.position) FILTER (WHERE f.clicked) AS position
+                FROM search_feedback f
+                WHERE f.search_id = h.id
+            ) first_click ON TRUE
+            WHERE h.timestamp > NOW() - make_interval(days => $1) AND h.result_count > 0
+            GROUP BY h.query
+            HAVING COUNT(*) >= $2
+            ORDER BY (COUNT(*) FILTER (WHERE first_click.clicked))::float8 / COUNT(*), COUNT(*) DESC
+            LIMIT $3
+            "#,
+            window_days(days) as i32,
+            min_searches,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ClickThrough {
+                click_through_rate: rate(row.clicked, row.searches),
+                query: row.query,
+                searches: row.searches,
+                clicked_searches: row.clicked,
+                mean_first_click_position: row.mean_first_click_position,
+            })
+            .collect())
+    }
+
+    pub async fn latency(&self, days: u32) -> Result<LatencyStats> {
+        let days = window_days(days);
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                COUNT(execution_time_ms) AS "searches!",
+                percentile_cont(ARRAY[0.5, 0.95, 0.99]) WITHIN GROUP (ORDER BY execution_time_ms) AS percentiles,
+                AVG(execution_time_ms)::float8 AS mean,
+                MAX(execution_time_ms) AS max
+            FROM search_history
+            WHERE timestamp > NOW() - make_interval(days => $1)
+            "#,
+            days as i32
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let percentiles = row.percentiles.unwrap_or_default();
+        Ok(LatencyStats {
+            window_days: days,
+            searches: row.searches,
+            p50_ms: percentiles.first().copied(),
+            p95_ms: percentiles.get(1).copied(),
+            p99_ms: percentiles.get(2).copied(),
+            mean_ms: row.mean,
+            max_ms: row.max,
+        })
+    }
+}
+
+fn window_days(days: u32) -> u32 {
+    days.clamp(1, MAX_WINDOW_DAYS)
+}
+
+fn rate(count: i64, total: i64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_and_rate() {
+        assert_eq!(window_days(0), 1);
+        assert_eq!(window_days(30), 30);
+        assert_eq!(window_days(10_000), MAX_WINDOW_DAYS);
+        assert_eq!(rate(0, 0), 0.0);
+        assert_eq!(rate(1, 4), 0.25);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::search::analysis::Language;
+use tantivy::tokenizer::{BoxTokenStream, Token, TokenStream, Tokenizer};
+
+/// Splits words on non-alphanumeric characters like tantivy's
+/// `SimpleTokenizer`, except that runs of Chinese, Japanese or Korean
+/// characters, which are not separated by spaces, become overlapping
+/// bigrams. A lone CJK character stays a unigram.
+#[derive(Clone, Default)]
+pub struct CjkTokenizer;
+
+pub struct CjkTokenStream<'a> {
+    text: &'a str,
+    spans: std::vec::IntoIter<(usize, usize)>,
+    token: Token,
+}
+
+impl Tokenizer for CjkTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(CjkTokenStream {
+            text,
+            spans: token_spans(text).into_iter(),
+            token: Token::default(),
+        })
+    }
+}
+
+impl<'a> TokenStream for CjkTokenStream<'a> {
+    fn advance(&mut self) -> bool {
+        let Some((from, to)) = self.spans.next() else {
+            return false;
+        };
+
+        self.token.text.clear();
+        self.token.text.push_str(&self.text[from..to]);
+        self.token.offset_from = from;
+        self.token.offset_to = to;
+        // Consecutive bigrams take consecutive positions, so phrase queries
+        // over them match the original character sequence
+        self.token.position = self.token.position.wrapping_add(1);
+        true
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+pub fn is_cjk(c: char) -> bool {
+    is_han(c) || is_kana(c) || is_hangul(c)
+}
+
+fn is_han(c: char) -> bool {
+    matches!(c,
+        '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2A6DF}' | '\u{3005}')
+}
+
+fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3040}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}')
+}
+
+fn is_hangul(c: char) -> bool {
+    matches!(c, '\u{AC00}'..='\u{D7AF}' | '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}')
+}
+
+/// Byte ranges of the tokens in `text`
+fn token_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if is_cjk(c) {
+            let mut run = vec![start];
+            while let Some(&(i, _)) = chars.peek().filter(|(_, c)| is_cjk(*c)) {
+                run.push(i);
+                chars.next();
+            }
+            let end = chars.peek().map_or(text.len(), |(i, _)| *i);
+            run.push(end);
+
+            if run.len() == 2 {
+                spans.push((run[0], run[1]));
+            } else {
+                spans.extend(run.windows(3).map(|w| (w[0], w[2])));
+            }
+        } else if c.is_alphanumeric() {
+            while chars.peek().map_or(false, |(_, c)| c.is_alphanumeric() && !is_cjk(*c)) {
+                chars.next();
+            }
+            let end = chars.peek().map_or(text.len(), |(i, _)| *i);
+            spans.push((start, end));
+        }
+    }
+
+    spans
+}
+
+/// `text` with CJK runs rewritten as space-separated bigrams, for search
+/// backends that only split on whitespace. None when there is no CJK text.
+pub fn segment_cjk(text: &str) -> Option<String> {
+    if !text.chars().any(is_cjk) {
+        return None;
+    }
+
+    let tokens: Vec<&str> = token_spans(text)
+        .into_iter()
+        .map(|(from, to)| &text[from..to])
+        .collect();
+    Some(tokens.join(" "))
+}
+
+/// The CJK language `text` is written in, judged by script alone. Only
+/// answers when CJK characters make up at least half of the text, so a
+/// few names in another script do not decide it.
+pub fn script_language(text: &str) -> Option<Language> {
+    let (mut han, mut kana, mut hangul, mut other) = (0usize, 0usize, 0usize, 0usize);
+    for c in text.chars() {
+        if is_han(c) {
+            han += 1;
+        } else if is_kana(c) {
+            kana += 1;
+        } else if is_hangul(c) {
+            hangul += 1;
+        } else if c.is_alphanumeric() {
+            other += 1;
+        }
+    }
+
+    let cjk = han + kana + hangul;
+    if cjk == 0 || cjk < other {
+        return None;
+    }
+
+    // Japanese mixes kanji with kana; Korean text rarely uses hanja
+    if hangul >= han && hangul >= kana {
+        Some(Language::Korean)
+    } else if kana > 0 {
+        Some(Language::Japanese)
+    } else {
+        Some(Language::Chinese)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(text: &str) -> Vec<String> {
+        let mut stream = CjkTokenizer.token_stream(text);
+        let mut tokens = Vec::new();
+        while stream.advance() {
+            tokens.push(stream.token().text.clone());
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_bigrams_and_mixed_script() {
+        assert_eq!(tokens("東京大学"), vec!["東京", "京大", "大学"]);
+        assert_eq!(tokens("iPhone 15の価格"), vec!["iPhone", "15", "の価", "価格"]);
+        assert_eq!(tokens("서울 시"), vec!["서울", "시"]);
+        assert_eq!(tokens("plain text, only"), vec!["plain", "text", "only"]);
+        assert_eq!(segment_cjk("在北京工作").as_deref(), Some("在北 北京 京工 工作"));
+        assert_eq!(segment_cjk("no cjk here"), None);
+    }
+
+    #[test]
+    fn test_script_language() {
+        assert_eq!(script_language("北京的天气"), Some(Language::Chinese));
+        assert_eq!(script_language("東京の天気"), Some(Language::Japanese));
+        assert_eq!(script_language("서울 날씨"), Some(Language::Korean));
+        assert_eq!(script_language("Meeting notes from the 東京 office"), None);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::config::CrossEncoderConfig;
+use crate::search::SearchResult;
+use anyhow::{Context, Result};
+use rust_bert::bert::{BertConfig, BertForSequenceClassification};
+use rust_bert::Config;
+use rust_tokenizers::tokenizer::{BertTokenizer, Tokenizer, TruncationStrategy};
+use rust_tokenizers::vocab::{BertVocab, Vocab};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tch::{nn, Device, Kind, Tensor};
+
+/// Characters of content paired with the query; the model only reads the
+/// first few hundred tokens anyway
+const MAX_PASSAGE_CHARS: usize = 2000;
+
+/// Scores how well each passage answers the query, higher is better
+pub trait PairScorer: Send + Sync {
+    fn score(&self, query: &str, passages: &[String]) -> Result<Vec<f32>>;
+}
+
+/// A BERT cross-encoder (e.g. `cross-encoder/ms-marco-MiniLM-L-6-v2`)
+/// reading the query and passage together. The model directory holds
+/// `config.json`, `vocab.txt` and weights converted to `rust_model.ot`.
+pub struct CrossEncoder {
+    model: Mutex<BertForSequenceClassification>,
+    _var_store: nn::VarStore,
+    tokenizer: BertTokenizer,
+    device: Device,
+    max_length: usize,
+}
+
+impl CrossEncoder {
+    pub fn load(path: &Path, max_length: usize) -> Result<Self> {
+        let config_path = path.join("config.json");
+        if !config_path.exists() {
+            anyhow::bail!("No cross-encoder config at {}", config_path.display());
+        }
+        let config = BertConfig::from_file(&config_path);
+
+        let device = Device::cuda_if_available();
+        let mut var_store = nn::VarStore::new(device);
+        let model = BertForSequenceClassification::new(var_store.root(), &config)?;
+        var_store
+            .load(path.join("rust_model.ot"))
+            .with_context(|| format!("Failed to load cross-encoder weights from {}", path.display()))?;
+
+        let vocab = path.join("vocab.txt");
+        let tokenizer = BertTokenizer::from_file(vocab.to_str().context("Invalid vocabulary path")?, true, true)?;
+
+        Ok(Self {
+            model: Mutex::new(model),
+            _var_store: var_store,
+            tokenizer,
+            device,
+            max_length,
+        })
+    }
+}
+
+impl PairScorer for CrossEncoder {
+    fn score(&self, query: &str, passages: &[String]) -> Result<Vec<f32>> {
+        if passages.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let pairs: Vec<(&str, &str)> = passages.iter().map(|passage| (query, passage.as_str())).collect();
+        let inputs = self
+            .tokenizer
+            .encode_pair_list(&pairs, self.max_length, &TruncationStrategy::LongestFirst, 0);
+        let length = inputs.iter().map(|input| input.token_ids.len()).max().unwrap_or(0);
+        let pad = self.tokenizer.vocab().token_to_id(BertVocab::pad_value());
+
+        let padded = |values: Vec<i64>, fill: i64| {
+            let mut values = values;
+            values.resize(length, fill);
+            Tensor::of_slice(&values)
+        };
+        let (mut ids, mut segments, mut masks) = (Vec::new(), Vec::new(), Vec::new());
+        for input in &inputs {
+            ids.push(padded(input.token_ids.clone(), pad));
+            segments.push(padded(input.segment_ids.iter().map(|&s| s as i64).collect(), 0));
+            masks.push(padded(vec![1; input.token_ids.len()], 0));
+        }
+        let (ids, segments, masks) = (
+            Tensor::stack(&ids, 0).to(self.device),
+            Tensor::stack(&segments, 0).to(self.device),
+            Tensor::stack(&masks, 0).to(self.device),
+        );
+
+        let logits = tch::no_grad(|| {
+            let model = self.model.lock().unwrap();
+            model.forward_t(Some(&ids), Some(&masks), Some(&segments), None, None, false).logits
+        });
+
+        // One relevance logit, or the probability of the last of several labels
+        let scores = if logits.size()[1] == 1 {
+            logits.squeeze_dim(1).sigmoid()
+        } else {
+            logits.softmax(-1, Kind::Float).select(1, -1)
+        };
+        Ok(Vec::<f32>::from(&scores.to_kind(Kind::Float).to(Device::Cpu)))
+    }
+}
+
+/// Rescores the top hybrid candidates with a cross-encoder within a time
+/// budget. Batches are started until the budget runs out, and a batch still
+/// running then is not waited for; candidates left unscored keep their order
+/// below the scored ones.
+pub struct Reranker {
+    scorer: Arc<dyn PairScorer>,
+    config: CrossEncoderConfig,
+}
+
+impl Reranker {
+    pub fn new(scorer: Arc<dyn PairScorer>, config: CrossEncoderConfig) -> Self {
+        Self { scorer, config }
+    }
+
+    pub fn from_config(config: CrossEncoderConfig) -> Result<Self> {
+        let encoder = CrossEncoder::load(&config.model_path, config.max_length)?;
+        Ok(Self::new(Arc::new(encoder), config))
+    }
+
+    /// Set `rerank_score` on as many of the top results as the budget
+    /// allows and reorder them by it. Returns how many were scored.
+    pub async fn rerank(&self, query: &str, results: &mut [SearchResult]) -> Result<usize> {
+        let top = self.config.top_n.min(results.len());
+        let deadline = Instant::now() + Duration::from_millis(self.config.budget_ms);
+        let mut scored = 0;
+
+        for batch in results[..top].chunks_mut(self.config.batch_size.max(1)) {
+            if Instant::now() >= deadline {
+                break;
+            }
+
+            let passages: Vec<String> = batch.iter().map(passage).collect();
+            let scorer = self.scorer.clone();
+            let query = query.to_string();
+            let scoring = tokio::task::spawn_blocking(move || scorer.score(&query, &passages));
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Ok(scores) = tokio::time::timeout(remaining, scoring).await else {
+                break;
+            };
+            let scores = scores??;
+
+            for (result, score) in batch.iter_mut().zip(scores) {
+                result.scores.rerank_score = Some(score);
+            }
+            scored += batch.len();
+        }
+
+        results[..scored].sort_by(|a, b| {
+            b.scores.rerank_score.unwrap_or(0.0).total_cmp(&a.scores.rerank_score.unwrap_or(0.0))
+        });
+        Ok(scored)
+    }
+}
+
+fn passage(result: &SearchResult) -> String {
+    let content: String = result.content.chars().take(MAX_PASSAGE_CHARS).collect();
+    format!("{}. {}", result.title, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{SearchMetadata, SearchScores};
+
+    /// Scores passages by how often they mention the query
+    struct MentionScorer;
+
+    impl PairScorer for MentionScorer {
+        fn score(&self, query: &str, passages: &[String]) -> Result<Vec<f32>> {
+            Ok(passages.iter().map(|p| p.matches(query).count() as f32).collect())
+        }
+    }
+
+    fn results(contents: &[&str]) -> Vec<SearchResult> {
+        contents
+            .iter()
+            .enumerate()
+            .map(|(i, content)| SearchResult {
+                id: i.to_string(),
+                title: String::new(),
+                content: content.to_string(),
+                scores: SearchScores { text_score: 0.0, vector_score: 0.0, final_score: 0.0, rerank_score: None },
+                metadata: SearchMetadata {
+                    source_type: "txt".to_string(),
+                    author: None,
+                    created_at: chrono::Utc::now(),
+                    word_count: 0,
+                    duplicate_of: None,
+                    duplicates: Vec::new(),
+                },
+                features: None,
+            })
+            .collect()
+    }
+
+    fn ids(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|r| r.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_rerank_top_n() {
+        let config = CrossEncoderConfig { top_n: 3, batch_size: 2, ..CrossEncoderConfig::default() };
+        let reranker = Reranker::new(Arc::new(MentionScorer), config);
+
+        let mut results = results(&["vpn", "vpn vpn vpn", "nothing", "vpn vpn vpn vpn"]);
+        assert_eq!(reranker.rerank("vpn", &mut results).await.unwrap(), 3);
+
+        // The fourth was not a candidate, however good
+        assert_eq!(ids(&results), vec!["1", "0", "2", "3"]);
+        assert_eq!(results[0].scores.rerank_score, Some(3.0));
+        assert_eq!(results[3].scores.rerank_score, None);
+    }
+
+    /// Takes far longer than any search budget
+    struct SlowScorer;
+
+    impl PairScorer for SlowScorer {
+        fn score(&self, _query: &str, passages: &[String]) -> Result<Vec<f32>> {
+            std::thread::sleep(Duration::from_millis(500));
+            Ok(vec![1.0; passages.len()])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_batch_is_not_waited_for() {
+        let config = CrossEncoderConfig { budget_ms: 20, ..CrossEncoderConfig::default() };
+        let reranker = Reranker::new(Arc::new(SlowScorer), config);
+
+        let started = Instant::now();
+        let mut results = results(&["vpn", "vpn vpn"]);
+        assert_eq!(reranker.rerank("vpn", &mut results).await.unwrap(), 0);
+        assert!(started.elapsed() < Duration::from_millis(400));
+        assert_eq!(ids(&results), vec!["0", "1"]);
+        assert_eq!(results[0].scores.rerank_score, None);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_budget_keeps_order() {
+        let config = CrossEncoderConfig { budget_ms: 0, ..CrossEncoderConfig::default() };
+        let reranker = Reranker::new(Arc::new(MentionScorer), config);
+
+        let mut results = results(&["vpn", "vpn vpn"]);
+        assert_eq!(reranker.rerank("vpn", &mut results).await.unwrap(), 0);
+        assert_eq!(ids(&results), vec!["0", "1"]);
+    }
+}
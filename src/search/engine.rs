@@ -1,20 +1,52 @@
+use crate::api::processor::ProcessedDocument;
+use crate::document::{CodeBlock, Document};
+use crate::query_expander::{nearest_neighbours, QueryExpander};
 use crate::vector::store::VectorStore;
+use crate::search::analysis::Language;
+use crate::search::cross_encoder::Reranker;
+use crate::search::ranking::{title_match, ClickRates, Features, RankingModel};
+use crate::search::text_index::TextIndex;
 use crate::search::{SearchResult, SearchScores, SearchMetadata};
+use crate::spell_checker::SpellChecker;
+use crate::suggestions::Suggestions;
+use crate::trie::Completion;
 use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tracing::warn;
 
 pub struct SearchEngine {
     vector_store: Arc<RwLock<VectorStore>>,
+    text_index: Option<Arc<TextIndex>>,
+    spell_checker: RwLock<SpellChecker>,
+    suggestions: RwLock<Suggestions>,
+    query_expander: Option<Arc<QueryExpander>>,
+    ranking_model: Option<Arc<RankingModel>>,
+    reranker: Option<Arc<Reranker>>,
+    click_rates: RwLock<ClickRates>,
     config: SearchConfig,
 }
 
+/// Search results along with how the query was corrected and expanded
+#[derive(Debug, Serialize)]
+pub struct CorrectedSearch {
+    pub results: Vec<SearchResult>,
+    pub did_you_mean: Option<String>,
+    pub corrected: bool,  // The query matched nothing; results are for `did_you_mean`
+    pub expanded_query: String,  // The text query actually run
+}
+
 #[derive(Clone)]
 pub struct SearchConfig {
     pub max_results: usize,
     pub min_score: f32,
     pub vector_weight: f32,
     pub text_weight: f32,
+    pub collapse_duplicates: bool,
+    pub rerank_depth: usize,  // Top results scored by the ranking model
+    pub log_features: bool,   // Compute ranking features even without a model, for training
 }
 
 impl Default for SearchConfig {
@@ -24,6 +56,23 @@ impl Default for SearchConfig {
             min_score: 0.1,
             vector_weight: 0.6,
             text_weight: 0.4,
+            collapse_duplicates: true,
+            rerank_depth: 50,
+            log_features: true,
+        }
+    }
+}
+
+impl From<&crate::config::SearchConfig> for SearchConfig {
+    fn from(config: &crate::config::SearchConfig) -> Self {
+        Self {
+            max_results: config.max_results,
+            min_score: config.min_score,
+            vector_weight: config.vector_weight,
+            text_weight: config.text_weight,
+            collapse_duplicates: config.collapse_duplicates,
+            rerank_depth: config.ranking.rerank_depth,
+            log_features: config.ranking.log_features,
         }
     }
 }
@@ -32,15 +81,231 @@ impl SearchEngine {
     pub fn new(vector_store: Arc<RwLock<VectorStore>>, config: SearchConfig) -> Self {
         Self {
             vector_store,
+            text_index: None,
+            spell_checker: RwLock::new(SpellChecker::new()),
+            suggestions: RwLock::new(Suggestions::new()),
+            query_expander: None,
+            ranking_model: None,
+            reranker: None,
+            click_rates: RwLock::new(ClickRates::default()),
             config,
         }
     }
 
+    /// Rerank the top results with a learned model instead of the fixed
+    /// blend of vector and text scores
+    pub fn with_ranking_model(mut self, model: Arc<RankingModel>) -> Self {
+        self.ranking_model = Some(model);
+        self
+    }
+
+    /// Rescore the top results with a cross-encoder after duplicates are
+    /// collapsed
+    pub fn with_reranker(mut self, reranker: Arc<Reranker>) -> Self {
+        self.reranker = Some(reranker);
+        self
+    }
+
+    pub async fn set_click_rates(&self, click_rates: ClickRates) {
+        *self.click_rates.write().await = click_rates;
+    }
+
+    /// Add synonyms and neighbouring words to the full-text query
+    pub fn with_query_expander(mut self, query_expander: Arc<QueryExpander>) -> Self {
+        self.query_expander = Some(query_expander);
+        self
+    }
+
+    /// Embed the most frequent vocabulary words and give the expander
+    /// each one's nearest neighbours. Returns the number of words embedded,
+    /// none when neighbour expansion is off.
+    pub async fn load_neighbour_expansion(&self) -> Result<usize> {
+        let Some(expander) = &self.query_expander else {
+            return Ok(0);
+        };
+        let config = expander.config().clone();
+        if !config.neighbour_expansion {
+            return Ok(0);
+        }
+        let words = self.spell_checker.read().await.most_frequent(config.neighbour_vocabulary);
+
+        let mut embedded = Vec::with_capacity(words.len());
+        {
+            let vector_store = self.vector_store.read().await;
+            for word in words {
+                let embedding = vector_store.generate_embedding(&word).await?;
+                embedded.push((word, embedding));
+            }
+        }
+
+        let count = embedded.len();
+        let neighbours = tokio::task::spawn_blocking(move || {
+            nearest_neighbours(&embedded, config.max_neighbours, config.neighbour_min_similarity)
+        })
+        .await?;
+        expander.set_neighbours(neighbours);
+
+        Ok(count)
+    }
+
+    /// The full-text form of `query`, expanded when an expander is set.
+    /// A query whose expansion does not parse is searched as it was typed,
+    /// rather than as the plain terms of the expansion, which lose its boosts.
+    pub fn expand_query(&self, query: &str) -> String {
+        let Some(expander) = &self.query_expander else {
+            return query.to_string();
+        };
+
+        let expanded = expander.expand(query);
+        match &self.text_index {
+            Some(text_index) if !text_index.is_valid_query(&expanded) => query.to_string(),
+            _ => expanded,
+        }
+    }
+
+    /// Blend full-text matches into results; without one, search is vector
+    /// only. Spelling corrections and completions are drawn from the
+    /// index's vocabulary.
+    pub fn with_text_index(mut self, text_index: Arc<TextIndex>) -> Self {
+        match text_index.spell_checker() {
+            Ok(spell_checker) => {
+                let mut suggestions = Suggestions::new();
+                for (word, frequency) in spell_checker.frequencies() {
+                    suggestions.add_term(word, frequency);
+                }
+                self.suggestions = RwLock::new(suggestions);
+                self.spell_checker = RwLock::new(spell_checker);
+            }
+            Err(e) => warn!("Failed to load spelling vocabulary: {}", e),
+        }
+        self.text_index = Some(text_index);
+        self
+    }
+
+    /// Add a document to the full-text index, analyzed in its detected language
+    pub async fn index_document(&self, document: &ProcessedDocument) -> Result<()> {
+        let metadata = &document.metadata;
+        self.index_text(&document.id, &document.title, &document.content, metadata.language.as_deref(), &metadata.code_blocks)
+            .await
+    }
+
+    /// Add a document stored by the directory and record ingester
+    pub async fn index_stored_document(&self, document: &Document) -> Result<()> {
+        let metadata = &document.metadata;
+        self.index_text(&document.id, &document.title, &document.content, metadata.language.as_deref(), &metadata.code_blocks)
+            .await
+    }
+
+    async fn index_text(
+        &self,
+        id: &str,
+        title: &str,
+        content: &str,
+        language: Option<&str>,
+        code_blocks: &[CodeBlock],
+    ) -> Result<()> {
+        if let Some(text_index) = &self.text_index {
+            let language = language.and_then(Language::from_code);
+            let code: Vec<&str> = code_blocks.iter().map(|block| block.code.as_str()).collect();
+            text_index.add(id, title, content, &code.join("\n"), language)?;
+
+            let text = format!("{} {}", title, content);
+            self.spell_checker.write().await.add_text(&text);
+            self.suggestions.write().await.add_text(&text);
+        }
+        Ok(())
+    }
+
+    /// Type-ahead completions of a partly typed query
+    pub async fn suggest(&self, prefix: &str, limit: usize) -> Vec<Completion> {
+        self.suggestions.read().await.suggest(prefix, limit)
+    }
+
+    /// Complete to these past queries, with their recency weighted search
+    /// counts, instead of the ones loaded before
+    pub async fn set_popular_queries(&self, popular: &[(String, f64)]) {
+        self.suggestions.write().await.set_popular_queries(popular);
+    }
+
+    /// Drop a document from the full-text index and its words from the
+    /// spelling vocabulary
+    pub async fn remove_document(&self, id: &str) -> Result<()> {
+        if let Some(text_index) = &self.text_index {
+            if let Some((title, content)) = text_index.stored_text(id)? {
+                self.spell_checker.write().await.remove_text(&format!("{} {}", title, content));
+            }
+            text_index.delete(id)?;
+        }
+        Ok(())
+    }
+
+    /// Search, suggesting a correction for misspelled queries and running
+    /// the corrected query instead when the original has no text matches
+    pub async fn search_with_correction(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        offset: Option<usize>,
+    ) -> Result<CorrectedSearch> {
+        let results = self.search(query, limit, offset).await?;
+        let did_you_mean = self.suggest_correction(query).await;
+
+        if let Some(correction) = &did_you_mean {
+            if !has_text_matches(&results) {
+                let corrected_results = self.search(correction, limit, offset).await?;
+                if has_text_matches(&corrected_results) {
+                    self.suggestions.write().await.add_to_history(correction.clone());
+                    return Ok(CorrectedSearch {
+                        results: corrected_results,
+                        expanded_query: self.expand_query(correction),
+                        did_you_mean,
+                        corrected: true,
+                    });
+                }
+            }
+        }
+
+        // Only queries that found something are worth completing to
+        if has_text_matches(&results) {
+            self.suggestions.write().await.add_to_history(query.to_string());
+        }
+
+        Ok(CorrectedSearch {
+            results,
+            did_you_mean,
+            corrected: false,
+            expanded_query: self.expand_query(query),
+        })
+    }
+
+    /// The query with misspelled words replaced, when any were found
+    pub async fn suggest_correction(&self, query: &str) -> Option<String> {
+        let text_index = self.text_index.as_ref()?;
+        let corrected = self.spell_checker.read().await.correct_with(query, |first, second| {
+            text_index.pair_frequency(first, second).unwrap_or(0)
+        });
+
+        let original = query.split_whitespace().collect::<Vec<_>>().join(" ");
+        (corrected.to_lowercase() != original.to_lowercase()).then_some(corrected)
+    }
+
+    /// Search with the query's language detected from its text
     pub async fn search(
         &self,
         query: &str,
         limit: Option<usize>,
         offset: Option<usize>,
+    ) -> Result<Vec<SearchResult>> {
+        self.search_in_language(query, Language::detect_query(query), limit, offset).await
+    }
+
+    /// Search with the query analyzed as `language`, or in every language when None
+    pub async fn search_in_language(
+        &self,
+        query: &str,
+        language: Option<Language>,
+        limit: Option<usize>,
+        offset: Option<usize>,
     ) -> Result<Vec<SearchResult>> {
         let limit = limit.unwrap_or(self.config.max_results);
         let offset = offset.unwrap_or(0);
@@ -49,35 +314,130 @@ impl SearchEngine {
         let vector_store = self.vector_store.read().await;
         let query_embedding = vector_store.generate_embedding(query).await?;
 
+        // Over-fetch when collapsing so the page stays full
+        let fetch = if self.config.collapse_duplicates { limit * 2 } else { limit };
+
         // Perform vector search
         let vector_results = vector_store
-            .search(&query_embedding, limit, self.config.min_score)
+            .search(&query_embedding, fetch, self.config.min_score)
             .await?;
 
+        // Text scores are normalized to the best hit so both scales match
+        let text_hits = match &self.text_index {
+            Some(text_index) => text_index.search(&self.expand_query(query), language, fetch)?,
+            None => Vec::new(),
+        };
+        let best_text = text_hits.iter().map(|hit| hit.score).fold(0.0f32, f32::max);
+        let mut text_scores: HashMap<String, f32> = text_hits
+            .into_iter()
+            .map(|hit| (hit.id, if best_text > 0.0 { hit.score / best_text } else { 0.0 }))
+            .collect();
+
+        let (vector_weight, text_weight) = if self.text_index.is_some() {
+            (self.config.vector_weight, self.config.text_weight)
+        } else {
+            (1.0, 0.0)
+        };
+
         // Convert to search results
-        let results = vector_results
+        let mut results: Vec<SearchResult> = vector_results
             .into_iter()
             .map(|doc| SearchResult {
-                id: doc.id,
-                title: doc.title,
-                content: doc.content,
                 scores: SearchScores {
-                    text_score: 0.0, // TODO: Implement text scoring
+                    text_score: text_scores.remove(&doc.id).unwrap_or(0.0),
                     vector_score: doc.score,
-                    final_score: doc.score,
+                    final_score: 0.0,
+                    rerank_score: None,
                 },
+                id: doc.id,
+                title: doc.title,
+                content: doc.content,
                 metadata: SearchMetadata {
                     source_type: doc.metadata.source_type,
                     author: doc.metadata.author,
                     created_at: doc.metadata.created_at,
                     word_count: doc.content.split_whitespace().count(),
+                    duplicate_of: doc.metadata.duplicate_of,
+                    duplicates: Vec::new(),
                 },
+                features: None,
             })
             .collect();
+        drop(vector_store);
+
+        // Text matches the vector search missed
+        for (id, text_score) in text_scores {
+            if let Some(mut result) = self.get_document(&id).await? {
+                result.scores.text_score = text_score;
+                results.push(result);
+            }
+        }
+
+        for result in &mut results {
+            result.scores.final_score =
+                vector_weight * result.scores.vector_score + text_weight * result.scores.text_score;
+        }
+        results.sort_by(|a, b| b.scores.final_score.total_cmp(&a.scores.final_score));
+        self.rerank(query, language, fetch, &mut results).await?;
+
+        let mut results = if self.config.collapse_duplicates {
+            collapse_duplicates(results)
+        } else {
+            results
+        };
+        if let Some(reranker) = &self.reranker {
+            // Keep the hybrid order rather than fail the search
+            if let Err(e) = reranker.rerank(query, &mut results).await {
+                warn!("Cross-encoder reranking failed: {}", e);
+            }
+        }
+        results.truncate(limit);
 
         Ok(results)
     }
 
+    /// Compute ranking features for the top results and, when a model is
+    /// loaded, reorder them by its score. Results below keep their order.
+    async fn rerank(&self, query: &str, language: Option<Language>, fetch: usize, results: &mut [SearchResult]) -> Result<()> {
+        let depth = self.config.rerank_depth.min(results.len());
+        if depth == 0 || (self.ranking_model.is_none() && !self.config.log_features) {
+            return Ok(());
+        }
+
+        let field_scores = match &self.text_index {
+            Some(text_index) => text_index.field_scores(&self.expand_query(query), language, fetch)?,
+            None => HashMap::new(),
+        };
+        let click_rates = self.click_rates.read().await;
+        let now = chrono::Utc::now();
+
+        for result in &mut results[..depth] {
+            let (title_bm25, content_bm25) = field_scores.get(&result.id).copied().unwrap_or_default();
+            let features: Features = [
+                result.scores.text_score,
+                title_bm25,
+                content_bm25,
+                result.scores.vector_score,
+                (now - result.metadata.created_at).num_hours().max(0) as f32 / 24.0,
+                (1.0 + result.metadata.word_count as f32).ln(),
+                click_rates.get(&result.id),
+                title_match(query, &result.title),
+            ];
+            result.features = Some(features.to_vec());
+        }
+
+        if let Some(model) = &self.ranking_model {
+            for result in &mut results[..depth] {
+                if let Some(features) = &result.features {
+                    result.scores.final_score = model.score(features);
+                }
+            }
+            results[..depth].sort_by(|a, b| b.scores.final_score.total_cmp(&a.scores.final_score));
+        }
+
+        Ok(())
+    }
+
     pub async fn get_document(&self, id: &str) -> Result<Option<SearchResult>> {
         let vector_store = self.vector_store.read().await;
         let doc = vector_store.get_document(id).await?;
@@ -90,17 +450,48 @@ impl SearchEngine {
                 text_score: 0.0,
                 vector_score: 0.0,
                 final_score: 0.0,
+                rerank_score: None,
             },
             metadata: SearchMetadata {
                 source_type: doc.metadata.source_type,
                 author: doc.metadata.author,
                 created_at: doc.metadata.created_at,
                 word_count: doc.content.split_whitespace().count(),
+                duplicate_of: doc.metadata.duplicate_of,
+                duplicates: Vec::new(),
             },
+            features: None,
         }))
     }
 }
 
+/// Vector search always finds neighbours; a query only really matched
+/// when some result contains its words
+fn has_text_matches(results: &[SearchResult]) -> bool {
+    results.iter().any(|result| result.scores.text_score > 0.0)
+}
+
+/// Keep the best-scoring member of each near-duplicate group, listing the
+/// others on it. Results must already be sorted by score.
+fn collapse_duplicates(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut collapsed: Vec<SearchResult> = Vec::with_capacity(results.len());
+    let mut groups: HashMap<String, usize> = HashMap::new();
+
+    for result in results {
+        let group = result.metadata.duplicate_of.clone().unwrap_or_else(|| result.id.clone());
+
+        match groups.get(&group) {
+            Some(&index) => collapsed[index].metadata.duplicates.push(result.id),
+            None => {
+                groups.insert(group, collapsed.len());
+                collapsed.push(result);
+            }
+        }
+    }
+
+    collapsed
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -114,4 +505,34 @@ mod tests {
     async fn test_get_document() {
         // TODO: Add tests
     }
+
+    #[test]
+    fn test_collapse_duplicates() {
+        let result = |id: &str, duplicate_of: Option<&str>| SearchResult {
+            id: id.to_string(),
+            title: String::new(),
+            content: String::new(),
+            scores: SearchScores { text_score: 0.0, vector_score: 0.0, final_score: 0.0, rerank_score: None },
+            metadata: SearchMetadata {
+                source_type: "pdf".to_string(),
+                author: None,
+                created_at: chrono::Utc::now(),
+                word_count: 0,
+                duplicate_of: duplicate_of.map(str::to_string),
+                duplicates: Vec::new(),
+            },
+            features: None,
+        };
+
+        let collapsed = collapse_duplicates(vec![
+            result("copy-1", Some("policy")),
+            result("other", None),
+            result("policy", None),
+            result("copy-2", Some("policy")),
+        ]);
+
+        let ids: Vec<&str> = collapsed.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["copy-1", "other"]);
+        assert_eq!(collapsed[0].metadata.duplicates, vec!["policy", "copy-2"]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,355 @@
+use crate::search::lambdamart::{discount, gain, ideal_dcg};
+use crate::search::SearchEngine;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+/// Per-query changes in NDCG smaller than this count as ties
+const TIE_TOLERANCE: f32 = 1e-4;
+
+/// Queries listed as the largest wins and losses of a comparison
+const LISTED_CHANGES: usize = 10;
+
+#[derive(Debug, Deserialize)]
+struct JudgmentRow {
+    query: String,
+    document_id: String,
+    relevance: f32,
+}
+
+/// Graded relevance of documents for each query: 0 is irrelevant, higher
+/// is better. Unjudged documents count as irrelevant.
+#[derive(Debug, Clone, Default)]
+pub struct Judgments {
+    queries: BTreeMap<String, HashMap<String, f32>>,
+}
+
+impl Judgments {
+    /// A CSV file, or tab separated when the extension is `.tsv`, with a
+    /// `query,document_id,relevance` header
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read(path)
+            .with_context(|| format!("Failed to read judgments from {}", path.display()))?;
+        let delimiter = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("tsv") => b'\t',
+            _ => b',',
+        };
+        Self::from_reader(content.as_slice(), delimiter)
+    }
+
+    pub fn from_reader(reader: impl std::io::Read, delimiter: u8) -> Result<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .trim(csv::Trim::All)
+            .from_reader(reader);
+
+        let mut judgments = Self::default();
+        for (index, row) in reader.deserialize::<JudgmentRow>().enumerate() {
+            let row = row.with_context(|| format!("Invalid judgment on row {}", index + 1))?;
+            judgments.add(&row.query, &row.document_id, row.relevance);
+        }
+        Ok(judgments)
+    }
+
+    /// Judge a document for a query, replacing an earlier grade
+    pub fn add(&mut self, query: &str, document_id: &str, relevance: f32) {
+        self.queries
+            .entry(query.to_string())
+            .or_default()
+            .insert(document_id.to_string(), relevance);
+    }
+
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &HashMap<String, f32>)> {
+        self.queries.iter().map(|(query, grades)| (query.as_str(), grades))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryMetrics {
+    pub query: String,
+    pub ndcg: f32,             // At k
+    pub reciprocal_rank: f32,  // Of the first relevant result retrieved
+    pub recall: f32,           // At k
+    pub average_precision: f32,
+    pub unjudged: usize,       // Results in the top k nobody graded
+}
+
+impl QueryMetrics {
+    /// Metrics of one ranking of document ids against its grades. NDCG and
+    /// recall look at the top `k`; reciprocal rank and average precision at
+    /// everything retrieved.
+    pub fn from_ranking(query: &str, ranked: &[String], grades: &HashMap<String, f32>, k: usize) -> Self {
+        let grade = |id: &String| grades.get(id).copied().unwrap_or(0.0);
+        let relevant = grades.values().filter(|grade| **grade > 0.0).count();
+
+        let dcg: f32 = ranked
+            .iter()
+            .take(k)
+            .enumerate()
+            .map(|(rank, id)| gain(grade(id)) * discount(rank, k))
+            .sum();
+        let ideal = ideal_dcg(&grades.values().copied().collect::<Vec<_>>(), k);
+
+        let mut reciprocal_rank = 0.0;
+        let mut hits = 0;
+        let mut precision_sum = 0.0;
+        for (rank, id) in ranked.iter().enumerate() {
+            if grade(id) > 0.0 {
+                hits += 1;
+                precision_sum += hits as f32 / (rank + 1) as f32;
+                if reciprocal_rank == 0.0 {
+                    reciprocal_rank = 1.0 / (rank + 1) as f32;
+                }
+            }
+        }
+        let retrieved_at_k = ranked.iter().take(k).filter(|id| grade(id) > 0.0).count();
+
+        let share = |count: f32| if relevant > 0 { count / relevant as f32 } else { 0.0 };
+        Self {
+            query: query.to_string(),
+            ndcg: if ideal > 0.0 { dcg / ideal } else { 0.0 },
+            reciprocal_rank,
+            recall: share(retrieved_at_k as f32),
+            average_precision: share(precision_sum),
+            unjudged: ranked.iter().take(k).filter(|id| !grades.contains_key(*id)).count(),
+        }
+    }
+}
+
+/// Metrics of every judged query, and their means
+#[derive(Debug, Clone, Serialize)]
+pub struct EvaluationReport {
+    pub name: String,
+    pub k: usize,
+    pub queries: Vec<QueryMetrics>,
+    pub skipped: Vec<String>,  // Queries without a single relevant document
+}
+
+impl EvaluationReport {
+    pub fn ndcg(&self) -> f32 {
+        self.mean(|metrics| metrics.ndcg)
+    }
+
+    pub fn mrr(&self) -> f32 {
+        self.mean(|metrics| metrics.reciprocal_rank)
+    }
+
+    pub fn recall(&self) -> f32 {
+        self.mean(|metrics| metrics.recall)
+    }
+
+    pub fn map(&self) -> f32 {
+        self.mean(|metrics| metrics.average_precision)
+    }
+
+    /// Share of the top-k results nobody judged; when high, the metrics
+    /// understate a ranking that surfaces new documents
+    pub fn unjudged_rate(&self) -> f32 {
+        let unjudged: usize = self.queries.iter().map(|metrics| metrics.unjudged).sum();
+        let shown = self.queries.len() * self.k;
+        if shown == 0 {
+            0.0
+        } else {
+            unjudged as f32 / shown as f32
+        }
+    }
+
+    fn mean(&self, metric: impl Fn(&QueryMetrics) -> f32) -> f32 {
+        if self.queries.is_empty() {
+            return 0.0;
+        }
+        self.queries.iter().map(metric).sum::<f32>() / self.queries.len() as f32
+    }
+
+    fn summary(&self) -> [(String, f32); 5] {
+        [
+            (format!("NDCG@{}", self.k), self.ndcg()),
+            ("MRR".to_string(), self.mrr()),
+            (format!("Recall@{}", self.k), self.recall()),
+            ("MAP".to_string(), self.map()),
+            (format!("Unjudged@{}", self.k), self.unjudged_rate()),
+        ]
+    }
+}
+
+impl fmt::Display for EvaluationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}: {} queries, {} skipped", self.name, self.queries.len(), self.skipped.len())?;
+        for (metric, value) in self.summary() {
+            writeln!(f, "  {:<12} {:.4}", metric, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run every judged query through `engine`, retrieving `depth` results
+/// (at least `k`), and score the rankings
+pub async fn evaluate(
+    name: &str,
+    engine: &SearchEngine,
+    judgments: &Judgments,
+    k: usize,
+    depth: usize,
+) -> Result<EvaluationReport> {
+    let mut report = EvaluationReport {
+        name: name.to_string(),
+        k,
+        queries: Vec::with_capacity(judgments.len()),
+        skipped: Vec::new(),
+    };
+
+    for (query, grades) in judgments.iter() {
+        if !grades.values().any(|grade| *grade > 0.0) {
+            report.skipped.push(query.to_string());
+            continue;
+        }
+
+        let results = engine
+            .search(query, Some(depth.max(k)), None)
+            .await
+            .with_context(|| format!("Search for {:?} failed", query))?;
+        let ranked: Vec<String> = results.into_iter().map(|result| result.id).collect();
+        report.queries.push(QueryMetrics::from_ranking(query, &ranked, grades, k));
+    }
+
+    Ok(report)
+}
+
+/// Two reports over the same judgments side by side, with the queries
+/// whose NDCG changed the most
+pub struct Comparison<'a> {
+    pub baseline: &'a EvaluationReport,
+    pub candidate: &'a EvaluationReport,
+}
+
+impl Comparison<'_> {
+    /// Per query present in both, the candidate's NDCG minus the baseline's
+    pub fn ndcg_changes(&self) -> Vec<(&str, f32)> {
+        let baseline: HashMap<&str, f32> = self
+            .baseline
+            .queries
+            .iter()
+            .map(|metrics| (metrics.query.as_str(), metrics.ndcg))
+            .collect();
+        let mut changes: Vec<(&str, f32)> = self
+            .candidate
+            .queries
+            .iter()
+            .filter_map(|metrics| {
+                let before = baseline.get(metrics.query.as_str())?;
+                Some((metrics.query.as_str(), metrics.ndcg - before))
+            })
+            .collect();
+        changes.sort_by(|a, b| b.1.total_cmp(&a.1));
+        changes
+    }
+}
+
+impl fmt::Display for Comparison<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<12} {:>12} {:>12} {:>9}", "", self.baseline.name, self.candidate.name, "change")?;
+        for ((metric, before), (_, after)) in self.baseline.summary().iter().zip(self.candidate.summary()) {
+            writeln!(f, "{:<12} {:>12.4} {:>12.4} {:>+9.4}", metric, before, after, after - before)?;
+        }
+
+        let changes = self.ndcg_changes();
+        let wins = changes.iter().filter(|(_, change)| *change > TIE_TOLERANCE).count();
+        let losses = changes.iter().filter(|(_, change)| *change < -TIE_TOLERANCE).count();
+        writeln!(
+            f,
+            "\nNDCG@{} per query: {} better, {} worse, {} unchanged",
+            self.baseline.k,
+            wins,
+            losses,
+            changes.len() - wins - losses
+        )?;
+
+        let listed = |f: &mut fmt::Formatter<'_>, title: &str, changes: &mut dyn Iterator<Item = &(&str, f32)>| {
+            writeln!(f, "\n{}:", title)?;
+            for (query, change) in changes.take(LISTED_CHANGES) {
+                writeln!(f, "  {:>+7.4}  {}", change, query)?;
+            }
+            Ok(())
+        };
+        if wins > 0 {
+            listed(f, "Largest improvements", &mut changes.iter().filter(|(_, change)| *change > TIE_TOLERANCE))?;
+        }
+        if losses > 0 {
+            listed(f, "Largest regressions", &mut changes.iter().rev().filter(|(_, change)| *change < -TIE_TOLERANCE))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranking(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_metrics() {
+        let judgments = Judgments::from_reader(
+            "query,document_id,relevance\nvpn setup,a,2\nvpn setup,b,1\nvpn setup,c,0\nvpn setup,d,1\n".as_bytes(),
+            b',',
+        )
+        .unwrap();
+        let (query, grades) = judgments.iter().next().unwrap();
+
+        let perfect = QueryMetrics::from_ranking(query, &ranking(&["a", "b", "d", "c"]), grades, 3);
+        assert!((perfect.ndcg - 1.0).abs() < 1e-6);
+        assert_eq!(perfect.reciprocal_rank, 1.0);
+        assert_eq!(perfect.recall, 1.0);
+        assert!((perfect.average_precision - 1.0).abs() < 1e-6);
+
+        // Relevant at ranks 2 and 4 (past k), unjudged "x" first
+        let metrics = QueryMetrics::from_ranking(query, &ranking(&["x", "b", "c", "a"]), grades, 3);
+        assert_eq!(metrics.reciprocal_rank, 0.5);
+        assert!((metrics.recall - 1.0 / 3.0).abs() < 1e-6);
+        assert!((metrics.average_precision - (0.5 + 0.5) / 3.0).abs() < 1e-6);
+        assert_eq!(metrics.unjudged, 1);
+
+        let ideal = 3.0 + 1.0 / 3f32.log2() + 0.5;
+        assert!((metrics.ndcg - (1.0 / 3f32.log2()) / ideal).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_comparison() {
+        let report = |name: &str, ndcgs: &[(&str, f32)]| EvaluationReport {
+            name: name.to_string(),
+            k: 10,
+            queries: ndcgs
+                .iter()
+                .map(|(query, ndcg)| QueryMetrics {
+                    query: query.to_string(),
+                    ndcg: *ndcg,
+                    reciprocal_rank: 0.0,
+                    recall: 0.0,
+                    average_precision: 0.0,
+                    unjudged: 0,
+                })
+                .collect(),
+            skipped: Vec::new(),
+        };
+        let baseline = report("baseline", &[("a", 0.5), ("b", 0.8), ("c", 0.3)]);
+        let candidate = report("candidate", &[("a", 0.9), ("b", 0.6), ("c", 0.3)]);
+        let comparison = Comparison { baseline: &baseline, candidate: &candidate };
+
+        let changes = comparison.ndcg_changes();
+        assert_eq!(changes[0].0, "a");
+        assert_eq!(changes[2].0, "b");
+        assert!(comparison.to_string().contains("1 better, 1 worse, 1 unchanged"));
+    }
+}
@@ -0,0 +1,486 @@
+use crate::config::HistoryConfig;
+use crate::search::engine::SearchEngine;
+use crate::search::lambdamart::TrainingQuery;
+use crate::search::ranking::ClickRates;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::error;
+use uuid::Uuid;
+
+/// One executed search, as stored in `search_history`
+#[derive(Debug, Clone)]
+pub struct SearchRecord {
+    pub id: Uuid,  // Returned to the client as `query_id`
+    pub query: String,
+    pub user_id: Option<Uuid>,
+    pub result_count: usize,
+    pub execution_time_ms: u64,
+    pub result_ids: Vec<String>,  // In the order shown
+    pub result_features: Vec<Option<Vec<f32>>>,  // Ranking features of each result, when computed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+/// A click on or rating of one result of a search. Dwell time is usually
+/// sent in a second event, once the user comes back from the document, and
+/// is merged into the feedback already stored for that result.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Feedback {
+    pub query_id: String,
+    pub document_id: String,
+    pub position: Option<u32>,  // 1-based; looked up in the results shown when omitted
+    #[serde(default)]
+    pub clicked: bool,
+    pub dwell_time_ms: Option<u64>,
+    pub rating: Option<Rating>,
+}
+
+impl Feedback {
+    pub fn validate(&self) -> Result<()> {
+        if Uuid::parse_str(&self.query_id).is_err() {
+            anyhow::bail!("Unknown query id {}", self.query_id);
+        }
+        if self.position == Some(0) {
+            anyhow::bail!("Positions start at 1");
+        }
+        if !self.clicked && self.dwell_time_ms.is_none() && self.rating.is_none() {
+            anyhow::bail!("Feedback needs a click, a dwell time or a rating");
+        }
+        Ok(())
+    }
+}
+
+/// Dwell time after which a click counts as the result being useful
+const SATISFIED_DWELL_MS: i64 = 30_000;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeedbackEvent {
+    pub document_id: String,
+    pub clicked: bool,
+    pub dwell_time_ms: Option<i64>,
+    pub rating: Option<i16>,
+}
+
+/// A search with ranking features logged, and the feedback it received
+#[derive(Debug, Clone)]
+pub struct LoggedSearch {
+    pub result_ids: Vec<String>,
+    pub result_features: Vec<Option<Vec<f32>>>,
+    pub feedback: Vec<FeedbackEvent>,
+}
+
+impl LoggedSearch {
+    /// Graded relevance of each shown result: 3 for a thumbs up, 2 for a
+    /// click viewed long enough, 1 for any other click and 0 for a thumbs
+    /// down or a result skipped above a click. Results below the last click
+    /// were probably never looked at and get no label.
+    pub fn labels(&self) -> Vec<Option<f32>> {
+        let mut labels: Vec<Option<f32>> = vec![None; self.result_ids.len()];
+
+        for (position, id) in self.result_ids.iter().enumerate() {
+            let events: Vec<&FeedbackEvent> = self.feedback.iter().filter(|e| &e.document_id == id).collect();
+            let rating = events.iter().rev().find_map(|e| e.rating);
+            let dwell = events.iter().filter_map(|e| e.dwell_time_ms).max();
+            let clicked = events.iter().any(|e| e.clicked || e.dwell_time_ms.is_some());
+
+            labels[position] = match (rating, clicked) {
+                (Some(rating), _) if rating > 0 => Some(3.0),
+                (Some(_), _) => Some(0.0),
+                (None, true) if dwell.is_some_and(|dwell| dwell >= SATISFIED_DWELL_MS) => Some(2.0),
+                (None, true) => Some(1.0),
+                (None, false) => None,
+            };
+        }
+
+        if let Some(last_click) = labels.iter().rposition(|label| label.is_some_and(|label| label > 0.0)) {
+            for label in &mut labels[..last_click] {
+                label.get_or_insert(0.0);
+            }
+        }
+        labels
+    }
+
+    /// Features and labels of the results that have both, if any was relevant
+    pub fn training_query(&self) -> Option<TrainingQuery> {
+        let (features, labels): (Vec<Vec<f32>>, Vec<f32>) = self
+            .labels()
+            .into_iter()
+            .zip(&self.result_features)
+            .filter_map(|(label, features)| Some((features.clone()?, label?)))
+            .unzip();
+
+        labels.iter().any(|label| *label > 0.0).then_some(TrainingQuery { features, labels })
+    }
+}
+
+/// A past search as shown to the user who ran it
+#[derive(Debug, Serialize)]
+pub struct HistoryEntry {
+    pub id: String,
+    pub query: String,
+    pub timestamp: DateTime<Utc>,
+    pub result_count: Option<i32>,
+}
+
+/// Every search run, kept in Postgres so popular and personal query
+/// suggestions survive restarts. Popularity decays with a half-life of a
+/// week and is precomputed in the `popular_searches` view.
+#[derive(Clone)]
+pub struct SearchHistory {
+    pool: PgPool,
+    config: HistoryConfig,
+    refresh: Arc<Notify>,  // Wakes the refresh task early after deletions
+}
+
+impl SearchHistory {
+    pub fn new(pool: PgPool, config: HistoryConfig) -> Self {
+        Self {
+            pool,
+            config,
+            refresh: Arc::new(Notify::new()),
+        }
+    }
+
+    pub async fn record(&self, record: SearchRecord) -> Result<()> {
+        let query = normalize_query(&record.query);
+        if query.is_empty() {
+            return Ok(());
+        }
+        let result_features = if record.result_features.iter().any(Option::is_some) {
+            Some(serde_json::to_value(&record.result_features)?)
+        } else {
+            None
+        };
+
+        sqlx::query!(
+            r#"
+            INSERT INTO search_history (id, query, user_id, result_count, execution_time_ms, result_ids, result_features)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            record.id,
+            query,
+            record.user_id,
+            record.result_count as i32,
+            record.execution_time_ms.min(i32::MAX as u64) as i32,
+            &record.result_ids,
+            result_features
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Store feedback on a result of a recorded search, merged with earlier
+    /// feedback on the same result: clicks and ratings stick, the latest
+    /// rating wins and the longest dwell time is kept. Only the user who ran
+    /// the search may give feedback on it, as it trains the ranking model.
+    /// Returns the feedback's id, or `None` when `user_id` has no such search.
+    pub async fn record_feedback(&self, feedback: &Feedback, user_id: Uuid) -> Result<Option<String>> {
+        feedback.validate()?;
+        let rating = feedback.rating.map(|rating| match rating {
+            Rating::Up => 1i16,
+            Rating::Down => -1,
+        });
+
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO search_feedback (search_id, document_id, position, clicked, dwell_time_ms, rating)
+            SELECT h.id, $2, COALESCE($3, array_position(h.result_ids, $2)), $4 OR $5::integer IS NOT NULL, $5, $6
+            FROM search_history h
+            WHERE h.id = $1::uuid AND h.user_id = $7
+            ON CONFLICT (search_id, document_id) DO UPDATE SET
+                position = COALESCE(search_feedback.position, EXCLUDED.position),
+                clicked = search_feedback.clicked OR EXCLUDED.clicked,
+                dwell_time_ms = GREATEST(search_feedback.dwell_time_ms, EXCLUDED.dwell_time_ms),
+                rating = COALESCE(EXCLUDED.rating, search_feedback.rating)
+            RETURNING id::text AS "id!"
+            "#,
+            feedback.query_id,
+            feedback.document_id,
+            feedback.position.map(|position| position as i32),
+            feedback.clicked,
+            feedback.dwell_time_ms.map(|ms| ms.min(i32::MAX as u64) as i32),
+            rating,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// The most popular queries that found something, with their decayed
+    /// search counts
+    pub async fn popular(&self, limit: usize) -> Result<Vec<(String, f64)>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT query AS "query!", popularity AS "popularity!"
+            FROM popular_searches
+            ORDER BY popularity DESC
+            LIMIT $1
+            "#,
+            limit as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| (row.query, row.popularity)).collect())
+    }
+
+    /// A user's own past queries starting with `prefix`, weighted the same
+    /// way as popular ones
+    pub async fn user_suggestions(&self, user_id: Uuid, prefix: &str, limit: usize) -> Result<Vec<(String, f64)>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT query, SUM(POWER(0.5, EXTRACT(EPOCH FROM NOW() - timestamp)::float8 / 604800)) AS "popularity!"
+            FROM search_history
+            WHERE user_id = $1 AND query LIKE $2 AND result_count > 0
+            GROUP BY query
+            ORDER BY 2 DESC
+            LIMIT $3
+            "#,
+            user_id,
+            like_prefix(&normalize_query(prefix)),
+            limit as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| (row.query, row.popularity)).collect())
+    }
+
+    /// A user's searches, newest first
+    pub async fn user_history(&self, user_id: Uuid, limit: i64, offset: i64) -> Result<Vec<HistoryEntry>> {
+        let entries = sqlx::query_as!(
+            HistoryEntry,
+            r#"
+            SELECT id::text AS "id!", query, timestamp AS "timestamp!", result_count
+            FROM search_history
+            WHERE user_id = $1
+            ORDER BY timestamp DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            user_id,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// Forget all of a user's searches, and drop them from popular queries
+    /// with an early refresh
+    pub async fn delete_user_history(&self, user_id: Uuid) -> Result<u64> {
+        let deleted = sqlx::query!("DELETE FROM search_history WHERE user_id = $1", user_id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        if deleted > 0 {
+            self.refresh.notify_one();
+        }
+        Ok(deleted)
+    }
+
+    /// Forget one search, if it belongs to the user
+    pub async fn delete_entry(&self, user_id: Uuid, id: &str) -> Result<bool> {
+        let deleted = sqlx::query!(
+            "DELETE FROM search_history WHERE id = $1::uuid AND user_id = $2",
+            id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        if deleted > 0 {
+            self.refresh.notify_one();
+        }
+        Ok(deleted > 0)
+    }
+
+    /// Clicks and impressions of every document shown in the last `days` days
+    pub async fn click_rates(&self, days: u32) -> Result<ClickRates> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                shown.document_id AS "document_id!",
+                COUNT(*) FILTER (WHERE EXISTS (
+                    SELECT 1 FROM search_feedback f
+                    WHERE f.search_id = h.id AND f.document_id = shown.document_id AND f.clicked
+                )) AS "clicks!",
+                COUNT(*) AS "impressions!"
+            FROM search_history h
+            CROSS JOIN LATERAL unnest(h.result_ids) AS shown(document_id)
+            WHERE h.timestamp > NOW() - make_interval(days => $1)
+            GROUP BY shown.document_id
+            "#,
+            days as i32
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let counts: Vec<(String, i64, i64)> = rows
+            .into_iter()
+            .map(|row| (row.document_id, row.clicks, row.impressions))
+            .collect();
+        Ok(ClickRates::from_counts(&counts))
+    }
+
+    /// Searches of the last `days` days that logged ranking features and
+    /// received feedback
+    pub async fn logged_searches(&self, days: u32) -> Result<Vec<LoggedSearch>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                h.result_ids,
+                h.result_features AS "result_features!",
+                json_agg(json_build_object(
+                    'document_id', f.document_id,
+                    'clicked', f.clicked,
+                    'dwell_time_ms', f.dwell_time_ms,
+                    'rating', f.rating
+                ) ORDER BY f.created_at) AS "feedback!"
+            FROM search_history h
+            JOIN search_feedback f ON f.search_id = h.id
+            WHERE h.timestamp > NOW() - make_interval(days => $1) AND h.result_features IS NOT NULL
+            GROUP BY h.id
+            "#,
+            days as i32
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(LoggedSearch {
+                    result_ids: row.result_ids,
+                    result_features: serde_json::from_value(row.result_features)?,
+                    feedback: serde_json::from_value(row.feedback)?,
+                })
+            })
+            .collect()
+    }
+
+    pub async fn refresh_popular(&self) -> Result<()> {
+        sqlx::query!("REFRESH MATERIALIZED VIEW CONCURRENTLY popular_searches")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Refresh the popular queries and click-through rates and load them
+    /// into the engine now, on every interval and after history is deleted,
+    /// until the task is dropped
+    pub fn start_refresh(&self, engine: Arc<SearchEngine>) -> JoinHandle<()> {
+        let history = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(history.config.refresh_interval_secs.max(1)));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = history.refresh.notified() => {}
+                }
+                if let Err(e) = history.load_popular(&engine).await {
+                    error!("Failed to refresh popular searches: {}", e);
+                }
+                match history.click_rates(history.config.click_window_days).await {
+                    Ok(click_rates) => engine.set_click_rates(click_rates).await,
+                    Err(e) => error!("Failed to load click-through rates: {}", e),
+                }
+            }
+        })
+    }
+
+    async fn load_popular(&self, engine: &SearchEngine) -> Result<()> {
+        self.refresh_popular().await?;
+        let popular = self.popular(self.config.popular_limit).await?;
+        engine.set_popular_queries(&popular).await;
+        Ok(())
+    }
+}
+
+/// Lowercase with single spaces, so the same search groups together
+pub fn normalize_query(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// A LIKE pattern matching strings that start with `prefix` literally
+fn like_prefix(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_normalization_and_prefix_pattern() {
+        assert_eq!(normalize_query("  Rust   Async\tIO "), "rust async io");
+        assert_eq!(like_prefix("rust"), "rust%");
+        assert_eq!(like_prefix("100%_c\\d"), "100\\%\\_c\\\\d%");
+    }
+
+    #[test]
+    fn test_feedback_validation() {
+        let feedback: Feedback = serde_json::from_str(
+            r#"{"query_id": "6f1c9e4e-2b1a-4d2e-9a55-0c3b5f8d7e21", "document_id": "d", "position": 2, "dwell_time_ms": 30000, "rating": "up"}"#,
+        )
+        .unwrap();
+        assert!(feedback.validate().is_ok());
+
+        let empty = Feedback { dwell_time_ms: None, rating: None, ..feedback.clone() };
+        assert!(empty.validate().is_err());
+        assert!(Feedback { position: Some(0), ..feedback.clone() }.validate().is_err());
+        assert!(Feedback { query_id: "q".to_string(), ..feedback }.validate().is_err());
+    }
+
+    #[test]
+    fn test_labels_from_feedback() {
+        let event = |id: &str, clicked: bool, dwell_time_ms: Option<i64>, rating: Option<i16>| FeedbackEvent {
+            document_id: id.to_string(),
+            clicked,
+            dwell_time_ms,
+            rating,
+        };
+        let search = LoggedSearch {
+            result_ids: ["a", "b", "c", "d", "e"].map(str::to_string).to_vec(),
+            result_features: vec![Some(vec![0.1]), Some(vec![0.2]), None, Some(vec![0.4]), Some(vec![0.5])],
+            feedback: vec![
+                event("b", true, None, None),
+                event("b", false, Some(45_000), None),
+                event("c", false, None, Some(-1)),
+                event("d", true, Some(2_000), None),
+            ],
+        };
+
+        assert_eq!(search.labels(), vec![Some(0.0), Some(2.0), Some(0.0), Some(1.0), None]);
+
+        // Results without logged features are left out
+        let query = search.training_query().unwrap();
+        assert_eq!(query.labels, vec![0.0, 2.0, 1.0]);
+        assert_eq!(query.features, vec![vec![0.1], vec![0.2], vec![0.4]]);
+    }
+}
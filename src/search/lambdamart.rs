@@ -0,0 +1,264 @@
+use crate::search::ranking::{Node, RankingModel, FEATURE_NAMES};
+
+/// Results shown for one search, with graded relevance labels (0 is
+/// irrelevant, higher is better)
+#[derive(Debug, Clone)]
+pub struct TrainingQuery {
+    pub features: Vec<Vec<f32>>,
+    pub labels: Vec<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TrainingParams {
+    pub trees: usize,
+    pub max_depth: usize,
+    pub learning_rate: f32,
+    pub min_samples_leaf: usize,
+    pub ndcg_at: usize,  // Rank cutoff of the NDCG being optimized
+}
+
+impl Default for TrainingParams {
+    fn default() -> Self {
+        Self {
+            trees: 100,
+            max_depth: 4,
+            learning_rate: 0.1,
+            min_samples_leaf: 5,
+            ndcg_at: 10,
+        }
+    }
+}
+
+/// Train gradient boosted trees with LambdaMART: each tree fits the
+/// gradients of NDCG@k for every mis-ordered pair of results of a search
+pub fn train(queries: &[TrainingQuery], params: &TrainingParams) -> RankingModel {
+    let samples: Vec<&[f32]> = queries
+        .iter()
+        .flat_map(|query| query.features.iter().map(Vec::as_slice))
+        .collect();
+    let mut scores = vec![0.0f32; samples.len()];
+    let mut trees = Vec::with_capacity(params.trees);
+
+    for _ in 0..params.trees {
+        let (lambdas, weights) = lambda_gradients(queries, &scores, params.ndcg_at);
+        if lambdas.iter().all(|lambda| *lambda == 0.0) {
+            break;
+        }
+
+        let indices: Vec<usize> = (0..samples.len()).collect();
+        let tree = fit_tree(&samples, &lambdas, &weights, indices, params, 0);
+        for (score, sample) in scores.iter_mut().zip(&samples) {
+            *score += evaluate(&tree, sample);
+        }
+        trees.push(tree);
+    }
+
+    RankingModel {
+        feature_names: FEATURE_NAMES.iter().map(|name| name.to_string()).collect(),
+        base_score: 0.0,
+        trees,
+    }
+}
+
+/// Per result, the summed pull towards the top (lambda) and the second
+/// derivative weighting its Newton step
+fn lambda_gradients(queries: &[TrainingQuery], scores: &[f32], ndcg_at: usize) -> (Vec<f32>, Vec<f32>) {
+    let mut lambdas = vec![0.0f32; scores.len()];
+    let mut weights = vec![0.0f32; scores.len()];
+
+    let mut offset = 0;
+    for query in queries {
+        let n = query.labels.len();
+        let range = offset..offset + n;
+        offset += n;
+
+        let ideal = ideal_dcg(&query.labels, ndcg_at);
+        if ideal == 0.0 {
+            continue;
+        }
+
+        // Rank of each result under the current scores
+        let query_scores = &scores[range.clone()];
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|&a, &b| query_scores[b].total_cmp(&query_scores[a]));
+        let mut rank = vec![0; n];
+        for (position, &index) in order.iter().enumerate() {
+            rank[index] = position;
+        }
+
+        for i in 0..n {
+            for j in 0..n {
+                if query.labels[i] <= query.labels[j] {
+                    continue;
+                }
+                // Change in NDCG from swapping the two results
+                let gain = gain(query.labels[i]) - gain(query.labels[j]);
+                let discount = discount(rank[i], ndcg_at) - discount(rank[j], ndcg_at);
+                let delta = (gain * discount).abs() / ideal;
+
+                let rho = 1.0 / (1.0 + (query_scores[i] - query_scores[j]).exp());
+                let lambda = delta * rho;
+                let weight = delta * rho * (1.0 - rho);
+                lambdas[range.start + i] += lambda;
+                lambdas[range.start + j] -= lambda;
+                weights[range.start + i] += weight;
+                weights[range.start + j] += weight;
+            }
+        }
+    }
+
+    (lambdas, weights)
+}
+
+/// Gain of a graded relevance label in DCG
+pub(crate) fn gain(label: f32) -> f32 {
+    2f32.powf(label) - 1.0
+}
+
+/// Discount at a 0-based rank, nothing past the cutoff
+pub(crate) fn discount(rank: usize, cutoff: usize) -> f32 {
+    if rank < cutoff {
+        1.0 / (rank as f32 + 2.0).log2()
+    } else {
+        0.0
+    }
+}
+
+pub(crate) fn ideal_dcg(labels: &[f32], cutoff: usize) -> f32 {
+    let mut sorted = labels.to_vec();
+    sorted.sort_by(|a, b| b.total_cmp(a));
+    sorted.iter().enumerate().map(|(rank, label)| gain(*label) * discount(rank, cutoff)).sum()
+}
+
+/// A regression tree on the lambdas, split to most reduce their squared
+/// error, with Newton step leaves scaled by the learning rate
+fn fit_tree(
+    samples: &[&[f32]],
+    lambdas: &[f32],
+    weights: &[f32],
+    indices: Vec<usize>,
+    params: &TrainingParams,
+    depth: usize,
+) -> Node {
+    let leaf = || {
+        let lambda: f32 = indices.iter().map(|&i| lambdas[i]).sum();
+        let weight: f32 = indices.iter().map(|&i| weights[i]).sum();
+        let step = if weight > f32::EPSILON { lambda / weight } else { 0.0 };
+        Node::Leaf(params.learning_rate * step)
+    };
+
+    if depth >= params.max_depth || indices.len() < 2 * params.min_samples_leaf.max(1) {
+        return leaf();
+    }
+
+    let Some((feature, threshold)) = best_split(samples, lambdas, &indices, params.min_samples_leaf.max(1)) else {
+        return leaf();
+    };
+    let (left, right): (Vec<usize>, Vec<usize>) = indices.iter().partition(|&&i| samples[i][feature] < threshold);
+
+    Node::Split {
+        feature,
+        threshold,
+        left: Box::new(fit_tree(samples, lambdas, weights, left, params, depth + 1)),
+        right: Box::new(fit_tree(samples, lambdas, weights, right, params, depth + 1)),
+        missing_left: true,
+    }
+}
+
+/// The feature and threshold whose split leaves the smallest squared error,
+/// if any split improves on none
+fn best_split(samples: &[&[f32]], lambdas: &[f32], indices: &[usize], min_leaf: usize) -> Option<(usize, f32)> {
+    let total: f32 = indices.iter().map(|&i| lambdas[i]).sum();
+    // Minimizing squared error is maximizing sum²/n over both sides
+    let unsplit = total * total / indices.len() as f32 + 1e-6;
+
+    let dimensions = samples.iter().map(|sample| sample.len()).min().unwrap_or(0);
+    (0..dimensions)
+        .filter_map(|feature| {
+            let (gain, threshold) = best_threshold(samples, lambdas, indices, min_leaf, feature, total)?;
+            (gain > unsplit).then_some((gain, feature, threshold))
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, feature, threshold)| (feature, threshold))
+}
+
+fn best_threshold(
+    samples: &[&[f32]],
+    lambdas: &[f32],
+    indices: &[usize],
+    min_leaf: usize,
+    feature: usize,
+    total: f32,
+) -> Option<(f32, f32)> {
+    let mut sorted = indices.to_vec();
+    sorted.sort_by(|&a, &b| samples[a][feature].total_cmp(&samples[b][feature]));
+
+    let mut best: Option<(f32, f32)> = None;
+    let mut left_sum = 0.0;
+    for (position, pair) in sorted.windows(2).enumerate() {
+        left_sum += lambdas[pair[0]];
+        let (left_count, right_count) = (position + 1, indices.len() - position - 1);
+        let (value, next) = (samples[pair[0]][feature], samples[pair[1]][feature]);
+        if value == next || left_count < min_leaf || right_count < min_leaf {
+            continue;
+        }
+
+        let right_sum = total - left_sum;
+        let gain = left_sum * left_sum / left_count as f32 + right_sum * right_sum / right_count as f32;
+        if best.is_none_or(|(best_gain, _)| gain > best_gain) {
+            best = Some((gain, (value + next) / 2.0));
+        }
+    }
+
+    best
+}
+
+fn evaluate(node: &Node, sample: &[f32]) -> f32 {
+    match node {
+        Node::Leaf(value) => *value,
+        Node::Split { feature, threshold, left, right, .. } => {
+            if sample[*feature] < *threshold {
+                evaluate(left, sample)
+            } else {
+                evaluate(right, sample)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_learns_relevant_feature() {
+        // Relevance follows the title match, while the vector score is noise
+        let queries: Vec<TrainingQuery> = (0..20)
+            .map(|q| {
+                let features = (0..6)
+                    .map(|d| {
+                        let mut features = vec![0.0; FEATURE_NAMES.len()];
+                        features[3] = ((q * 7 + d * 13) % 10) as f32 / 10.0;
+                        features[7] = d as f32 / 5.0;
+                        features
+                    })
+                    .collect();
+                let labels = (0..6).map(|d| (d / 2) as f32).collect();
+                TrainingQuery { features, labels }
+            })
+            .collect();
+
+        let params = TrainingParams { trees: 20, min_samples_leaf: 2, ..TrainingParams::default() };
+        let model = train(&queries, &params);
+        assert!(!model.trees.is_empty());
+
+        let scored = |title_match: f32, vector: f32| {
+            let mut features = vec![0.0; FEATURE_NAMES.len()];
+            features[3] = vector;
+            features[7] = title_match;
+            model.score(&features)
+        };
+        assert!(scored(1.0, 0.0) > scored(0.4, 0.9));
+        assert!(scored(0.4, 0.0) > scored(0.0, 0.9));
+    }
+}
@@ -1,12 +1,24 @@
+pub mod analysis;
+pub mod analytics;
+pub mod cjk;
+pub mod cross_encoder;
 pub mod engine;
+pub mod evaluation;
 pub mod query_parser;
+pub mod ranking;
 pub mod executor;
+pub mod history;
+pub mod lambdamart;
 pub mod scoring;
+pub mod text_index;
 
+pub use self::analytics::QueryAnalytics;
 pub use self::engine::SearchEngine;
 pub use self::query_parser::QueryParser;
 pub use self::executor::SearchExecutor;
+pub use self::history::SearchHistory;
 pub use self::scoring::ScoreCalculator;
+pub use self::text_index::TextIndex;
 
 use serde::{Deserialize, Serialize};
 
@@ -17,6 +29,8 @@ pub struct SearchResult {
     pub content: String,
     pub scores: SearchScores,
     pub metadata: SearchMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<f32>>,  // Ranking features, logged with the search for training
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,6 +38,8 @@ pub struct SearchScores {
     pub text_score: f32,
     pub vector_score: f32,
     pub final_score: f32,
+    #[serde(default)]
+    pub rerank_score: Option<f32>,  // Cross-encoder relevance, when reranked
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -32,4 +48,8 @@ pub struct SearchMetadata {
     pub author: Option<String>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub word_count: usize,
+    #[serde(default)]
+    pub duplicate_of: Option<String>,
+    #[serde(default)]
+    pub duplicates: Vec<String>,  // Near duplicates collapsed into this result
 }
\ No newline at end of file
@@ -0,0 +1,339 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Impressions a document's click-through rate is smoothed over, so a
+/// single click on a rarely shown document does not put it first
+const CLICK_PRIOR_IMPRESSIONS: f32 = 20.0;
+
+/// Features of a (query, document) pair, in the order models index them
+pub const FEATURE_NAMES: [&str; 8] = [
+    "text_score",          // Blended full-text score, normalized to the best hit
+    "title_bm25",
+    "content_bm25",
+    "vector_score",
+    "age_days",
+    "log_word_count",
+    "click_through_rate",  // Of the document, smoothed towards the average
+    "title_match",         // Share of the query's words in the title
+];
+
+pub type Features = [f32; FEATURE_NAMES.len()];
+
+/// Share of the query's distinct words that occur in the title
+pub fn title_match(query: &str, title: &str) -> f32 {
+    let words = |text: &str| -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let mut query_words = words(query);
+    query_words.sort();
+    query_words.dedup();
+    if query_words.is_empty() {
+        return 0.0;
+    }
+
+    let title_words = words(title);
+    let matched = query_words.iter().filter(|word| title_words.contains(word)).count();
+    matched as f32 / query_words.len() as f32
+}
+
+/// Click-through rates of documents shown in searches
+#[derive(Debug, Clone, Default)]
+pub struct ClickRates {
+    rates: HashMap<String, f32>,
+    average: f32,
+}
+
+impl ClickRates {
+    /// From (document id, clicks, impressions), each rate pulled towards
+    /// the average over all documents
+    pub fn from_counts(counts: &[(String, i64, i64)]) -> Self {
+        let clicks: i64 = counts.iter().map(|(_, clicks, _)| clicks).sum();
+        let impressions: i64 = counts.iter().map(|(_, _, impressions)| impressions).sum();
+        let average = if impressions > 0 { clicks as f32 / impressions as f32 } else { 0.0 };
+
+        let rates = counts
+            .iter()
+            .map(|(id, clicks, impressions)| {
+                let rate = (*clicks as f32 + CLICK_PRIOR_IMPRESSIONS * average)
+                    / (*impressions as f32 + CLICK_PRIOR_IMPRESSIONS);
+                (id.clone(), rate)
+            })
+            .collect();
+
+        Self { rates, average }
+    }
+
+    /// The average for documents never shown
+    pub fn get(&self, id: &str) -> f32 {
+        self.rates.get(id).copied().unwrap_or(self.average)
+    }
+}
+
+/// A regression tree node. Splits send a value below the threshold, or a
+/// missing one when `missing_left` is set, to `left`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Leaf(f32),
+    Split {
+        feature: usize,
+        threshold: f32,
+        left: Box<Node>,
+        right: Box<Node>,
+        missing_left: bool,
+    },
+}
+
+impl Node {
+    fn evaluate(&self, features: &[f32]) -> f32 {
+        let mut node = self;
+        loop {
+            match node {
+                Node::Leaf(value) => return *value,
+                Node::Split { feature, threshold, left, right, missing_left } => {
+                    let value = features.get(*feature).copied().unwrap_or(f32::NAN);
+                    let go_left = if value.is_nan() { *missing_left } else { value < *threshold };
+                    node = if go_left { left } else { right };
+                }
+            }
+        }
+    }
+}
+
+/// Gradient boosted trees scoring a feature vector: the sum of every
+/// tree's leaf plus a base score
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankingModel {
+    pub feature_names: Vec<String>,
+    pub base_score: f32,
+    pub trees: Vec<Node>,
+}
+
+impl RankingModel {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read ranking model from {}", path.display()))?;
+        Self::from_json(&text)
+    }
+
+    /// Either `{"feature_names", "base_score", "trees"}` as written by
+    /// `to_json`, or a bare XGBoost JSON dump (`dump_model(..., dump_format="json")`)
+    /// whose splits name features as in `FEATURE_NAMES` or as `f0`, `f1`, ...
+    /// for the model's own `feature_names`. Splits are remapped to positions
+    /// in `FEATURE_NAMES`, whatever order the model listed its features in.
+    pub fn from_json(text: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(text)?;
+        let (feature_names, base_score, trees) = match &value {
+            Value::Array(trees) => (default_feature_names(), 0.0, trees.clone()),
+            Value::Object(model) => {
+                let feature_names = match model.get("feature_names") {
+                    Some(names) => serde_json::from_value(names.clone())?,
+                    None => default_feature_names(),
+                };
+                let base_score = model.get("base_score").and_then(Value::as_f64).unwrap_or(0.0) as f32;
+                let trees = model
+                    .get("trees")
+                    .and_then(Value::as_array)
+                    .context("Ranking model has no trees")?
+                    .clone();
+                (feature_names, base_score, trees)
+            }
+            _ => anyhow::bail!("Ranking model must be a JSON object or array"),
+        };
+
+        let trees = trees
+            .iter()
+            .map(|tree| parse_node(tree, &feature_names))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            feature_names: default_feature_names(),
+            base_score,
+            trees,
+        })
+    }
+
+    /// The model in XGBoost's JSON dump layout, with feature names
+    pub fn to_json(&self) -> Value {
+        let trees: Vec<Value> = self
+            .trees
+            .iter()
+            .map(|tree| dump_node(tree, &self.feature_names, &mut 0, 0))
+            .collect();
+        json!({
+            "feature_names": self.feature_names,
+            "base_score": self.base_score,
+            "trees": trees,
+        })
+    }
+
+    pub fn score(&self, features: &[f32]) -> f32 {
+        self.base_score + self.trees.iter().map(|tree| tree.evaluate(features)).sum::<f32>()
+    }
+}
+
+fn default_feature_names() -> Vec<String> {
+    FEATURE_NAMES.iter().map(|name| name.to_string()).collect()
+}
+
+fn parse_node(value: &Value, feature_names: &[String]) -> Result<Node> {
+    if let Some(leaf) = value.get("leaf") {
+        return Ok(Node::Leaf(leaf.as_f64().context("Leaf value must be a number")? as f32));
+    }
+
+    let split = value.get("split").context("Tree node has neither a leaf nor a split")?;
+    let feature = match split {
+        Value::Number(index) => {
+            let column = index.as_u64().context("Invalid split feature")?;
+            feature_index(&format!("f{}", column), feature_names)
+                .with_context(|| format!("Unknown feature {} in ranking model", column))?
+        }
+        Value::String(name) => feature_index(name, feature_names)
+            .with_context(|| format!("Unknown feature {} in ranking model", name))?,
+        _ => anyhow::bail!("Invalid split feature"),
+    };
+    let threshold = value
+        .get("split_condition")
+        .and_then(Value::as_f64)
+        .context("Split has no split_condition")? as f32;
+
+    let id = |key: &str| value.get(key).and_then(Value::as_u64);
+    let (yes, no) = (id("yes").context("Split has no yes")?, id("no").context("Split has no no")?);
+    let children = value
+        .get("children")
+        .and_then(Value::as_array)
+        .context("Split has no children")?;
+    let child = |node_id: u64| -> Result<Node> {
+        let child = children
+            .iter()
+            .find(|child| child.get("nodeid").and_then(Value::as_u64) == Some(node_id))
+            .with_context(|| format!("Missing tree node {}", node_id))?;
+        parse_node(child, feature_names)
+    };
+
+    Ok(Node::Split {
+        feature,
+        threshold,
+        left: Box::new(child(yes)?),
+        right: Box::new(child(no)?),
+        missing_left: id("missing").is_none_or(|missing| missing == yes),
+    })
+}
+
+/// Position in `FEATURE_NAMES` of a split's feature, named directly or as
+/// `fN` for the N-th of the model's `feature_names`
+fn feature_index(name: &str, feature_names: &[String]) -> Option<usize> {
+    let name = match name.strip_prefix('f').and_then(|column| column.parse::<usize>().ok()) {
+        Some(column) => feature_names.get(column)?.as_str(),
+        None => name,
+    };
+    FEATURE_NAMES.iter().position(|feature| *feature == name)
+}
+
+fn dump_node(node: &Node, feature_names: &[String], next_id: &mut u64, depth: u64) -> Value {
+    let node_id = *next_id;
+    *next_id += 1;
+
+    match node {
+        Node::Leaf(value) => json!({ "nodeid": node_id, "leaf": value }),
+        Node::Split { feature, threshold, left, right, missing_left } => {
+            let left_id = *next_id;
+            let left = dump_node(left, feature_names, next_id, depth + 1);
+            let right_id = *next_id;
+            let right = dump_node(right, feature_names, next_id, depth + 1);
+            json!({
+                "nodeid": node_id,
+                "depth": depth,
+                "split": feature_names.get(*feature).cloned().unwrap_or_else(|| format!("f{}", feature)),
+                "split_condition": threshold,
+                "yes": left_id,
+                "no": right_id,
+                "missing": if *missing_left { left_id } else { right_id },
+                "children": [left, right],
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xgboost_dump() {
+        let dump = r#"[
+            { "nodeid": 0, "depth": 0, "split": "title_match", "split_condition": 0.5, "yes": 1, "no": 2, "missing": 1,
+              "children": [
+                { "nodeid": 1, "leaf": -0.2 },
+                { "nodeid": 2, "depth": 1, "split": "f3", "split_condition": 0.8, "yes": 3, "no": 4, "missing": 4,
+                  "children": [ { "nodeid": 3, "leaf": 0.1 }, { "nodeid": 4, "leaf": 0.4 } ] }
+              ] },
+            { "nodeid": 0, "leaf": 0.05 }
+        ]"#;
+        let model = RankingModel::from_json(dump).unwrap();
+
+        let mut features = [0.0; FEATURE_NAMES.len()];
+        assert!((model.score(&features) - -0.15).abs() < 1e-6);
+        features[7] = 1.0;
+        features[3] = 0.9;
+        assert!((model.score(&features) - 0.45).abs() < 1e-6);
+        features[3] = f32::NAN;
+        assert!((model.score(&features) - 0.45).abs() < 1e-6);
+
+        // Written models read back the same
+        let reloaded = RankingModel::from_json(&model.to_json().to_string()).unwrap();
+        assert_eq!(reloaded, model);
+        assert!(RankingModel::from_json(r#"[{ "nodeid": 0, "split": "nope", "split_condition": 1 }]"#).is_err());
+    }
+
+    #[test]
+    fn test_model_feature_order_is_remapped() {
+        let model = r#"{
+            "feature_names": ["title_match", "vector_score"],
+            "trees": [
+                { "nodeid": 0, "split": "f0", "split_condition": 0.5, "yes": 1, "no": 2,
+                  "children": [
+                    { "nodeid": 1, "split": 1, "split_condition": 0.5, "yes": 3, "no": 4,
+                      "children": [ { "nodeid": 3, "leaf": 0.0 }, { "nodeid": 4, "leaf": 0.5 } ] },
+                    { "nodeid": 2, "leaf": 1.0 }
+                  ] }
+            ]
+        }"#;
+        let model = RankingModel::from_json(model).unwrap();
+
+        let mut features = [0.0; FEATURE_NAMES.len()];
+        features[0] = 0.9;
+        assert_eq!(model.score(&features), 0.0);
+        features[3] = 0.9;
+        assert_eq!(model.score(&features), 0.5);
+        features[7] = 0.9;
+        assert_eq!(model.score(&features), 1.0);
+
+        let unknown = r#"{ "feature_names": ["bm25"], "trees": [{ "nodeid": 0, "split": "f0", "split_condition": 1 }] }"#;
+        assert!(RankingModel::from_json(unknown).is_err());
+    }
+
+    #[test]
+    fn test_click_rates_are_smoothed() {
+        let rates = ClickRates::from_counts(&[
+            ("popular".to_string(), 80, 100),
+            ("lucky".to_string(), 1, 1),
+            ("ignored".to_string(), 0, 99),
+        ]);
+
+        assert!((rates.get("unseen") - 0.405).abs() < 1e-3);
+        assert!(rates.get("popular") > rates.get("lucky"));
+        assert!(rates.get("lucky") > rates.get("ignored"));
+    }
+
+    #[test]
+    fn test_title_match() {
+        assert_eq!(title_match("Expense policy", "Travel and expense policy 2024"), 1.0);
+        assert_eq!(title_match("expense report form", "Expense policy"), 1.0 / 3.0);
+        assert_eq!(title_match("", "Expense policy"), 0.0);
+    }
+}
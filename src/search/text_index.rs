@@ -0,0 +1,343 @@
+use crate::search::analysis::{register_analyzers, Language, TextSchema};
+use crate::spell_checker::SpellChecker;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tantivy::collector::{Count, TopDocs};
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{BooleanQuery, Query, QueryParser, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy, Term};
+use tokio::task::JoinHandle;
+use tracing::error;
+
+const WRITER_MEMORY: usize = 50_000_000;
+const TITLE_BOOST: f32 = 2.0;
+const DEFAULT_COMMIT_BATCH: usize = 1000;
+
+/// Full-text index over document titles and content, analyzed in each
+/// document's own language, and over their code blocks. Changes are
+/// committed in batches and only searchable once committed.
+pub struct TextIndex {
+    index: Index,
+    fields: TextSchema,
+    reader: IndexReader,
+    writer: Mutex<BatchedWriter>,
+    commit_batch: usize,
+}
+
+struct BatchedWriter {
+    writer: IndexWriter,
+    uncommitted: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct TextHit {
+    pub id: String,
+    pub score: f32,
+}
+
+impl TextIndex {
+    pub fn open(path: &Path) -> Result<Self> {
+        std::fs::create_dir_all(path)?;
+        let fields = TextSchema::build();
+        let index = Index::open_or_create(MmapDirectory::open(path)?, fields.schema.clone())?;
+        Self::from_index(index, fields)
+    }
+
+    pub fn in_memory() -> Result<Self> {
+        let fields = TextSchema::build();
+        let index = Index::create_in_ram(fields.schema.clone());
+        Self::from_index(index, fields)
+    }
+
+    fn from_index(index: Index, fields: TextSchema) -> Result<Self> {
+        register_analyzers(index.tokenizers());
+        let writer = index.writer(WRITER_MEMORY)?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+
+        Ok(Self {
+            index,
+            fields,
+            reader,
+            writer: Mutex::new(BatchedWriter { writer, uncommitted: 0 }),
+            commit_batch: DEFAULT_COMMIT_BATCH,
+        })
+    }
+
+    /// Commit once this many changes have built up, besides any timed commits
+    pub fn with_commit_batch(mut self, commit_batch: usize) -> Self {
+        self.commit_batch = commit_batch.max(1);
+        self
+    }
+
+    /// Index a document, replacing any earlier version with the same id
+    pub fn add(&self, id: &str, title: &str, content: &str, code: &str, language: Option<Language>) -> Result<()> {
+        let analyzed = self.fields.fields_for(language);
+        let mut batch = self.writer.lock().unwrap();
+
+        batch.writer.delete_term(Term::from_field_text(self.fields.id, id));
+        batch.writer.add_document(doc!(
+            self.fields.id => id,
+            self.fields.language => language.map_or("", |l| l.code()),
+            self.fields.title => title,
+            self.fields.content => content,
+            analyzed.title => title,
+            analyzed.content => content,
+            self.fields.code => code,
+            self.fields.words => title,
+            self.fields.words => content,
+        ))?;
+        self.changed(&mut batch)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<()> {
+        let mut batch = self.writer.lock().unwrap();
+        batch.writer.delete_term(Term::from_field_text(self.fields.id, id));
+        self.changed(&mut batch)
+    }
+
+    /// Make every change so far searchable
+    pub fn commit(&self) -> Result<()> {
+        let mut batch = self.writer.lock().unwrap();
+        self.commit_locked(&mut batch)
+    }
+
+    /// Commit every `interval` while there are changes, until the task is dropped
+    pub fn start_commits(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let index = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                let index = index.clone();
+                match tokio::task::spawn_blocking(move || index.commit()).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => error!("Failed to commit the text index: {}", e),
+                    Err(e) => error!("Text index commit panicked: {}", e),
+                }
+            }
+        })
+    }
+
+    fn changed(&self, batch: &mut BatchedWriter) -> Result<()> {
+        batch.uncommitted += 1;
+        if batch.uncommitted >= self.commit_batch {
+            self.commit_locked(batch)?;
+        }
+        Ok(())
+    }
+
+    fn commit_locked(&self, batch: &mut BatchedWriter) -> Result<()> {
+        if batch.uncommitted == 0 {
+            return Ok(());
+        }
+        batch.writer.commit()?;
+        batch.uncommitted = 0;
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    /// Each field analyzes the query with its own analyzer, so the query
+    /// is stemmed the same way as the documents it is matched against
+    pub fn search(&self, query: &str, language: Option<Language>, limit: usize) -> Result<Vec<TextHit>> {
+        let analyzed = self.fields.query_fields(language);
+        let mut fields: Vec<_> = analyzed.iter().flat_map(|f| [f.title, f.content]).collect();
+        fields.push(self.fields.code);
+
+        let mut parser = QueryParser::for_index(&self.index, fields);
+        for f in &analyzed {
+            parser.set_field_boost(f.title, TITLE_BOOST);
+        }
+
+        // Fall back to plain terms when the query is not valid syntax
+        let parsed = match parser.parse_query(query) {
+            Ok(parsed) => parsed,
+            Err(_) => parser.parse_query(&plain_terms(query))?,
+        };
+        self.collect_hits(&parsed, limit)
+    }
+
+    /// Whether `query` is valid query syntax. Invalid queries are still
+    /// searched, as their plain terms.
+    pub fn is_valid_query(&self, query: &str) -> bool {
+        let default = self.fields.fields_for(None);
+        let parser = QueryParser::for_index(&self.index, vec![default.title, default.content, self.fields.code]);
+        parser.parse_query(query).is_ok()
+    }
+
+    fn collect_hits(&self, query: &dyn Query, limit: usize) -> Result<Vec<TextHit>> {
+        let searcher = self.reader.searcher();
+        let top = searcher.search(query, &TopDocs::with_limit(limit))?;
+
+        let mut hits = Vec::with_capacity(top.len());
+        for (score, address) in top {
+            let stored = searcher.doc(address)?;
+            if let Some(id) = stored.get_first(self.fields.id).and_then(|v| v.as_text()) {
+                hits.push(TextHit { id: id.to_string(), score });
+            }
+        }
+
+        Ok(hits)
+    }
+
+    /// BM25 of the query against titles and against content separately, by
+    /// document id, for documents matching either
+    pub fn field_scores(&self, query: &str, language: Option<Language>, limit: usize) -> Result<HashMap<String, (f32, f32)>> {
+        let analyzed = self.fields.query_fields(language);
+        let mut scores: HashMap<String, (f32, f32)> = HashMap::new();
+
+        let titles: Vec<_> = analyzed.iter().map(|f| f.title).collect();
+        for hit in self.search_fields(query, titles, limit)? {
+            scores.entry(hit.id).or_default().0 = hit.score;
+        }
+        let contents: Vec<_> = analyzed.iter().map(|f| f.content).collect();
+        for hit in self.search_fields(query, contents, limit)? {
+            scores.entry(hit.id).or_default().1 = hit.score;
+        }
+
+        Ok(scores)
+    }
+
+    fn search_fields(&self, query: &str, fields: Vec<Field>, limit: usize) -> Result<Vec<TextHit>> {
+        let parser = QueryParser::for_index(&self.index, fields);
+        let parsed = match parser.parse_query(query) {
+            Ok(parsed) => parsed,
+            Err(_) => parser.parse_query(&plain_terms(query))?,
+        };
+        self.collect_hits(&parsed, limit)
+    }
+
+    /// Stored title and content of a committed document
+    pub fn stored_text(&self, id: &str) -> Result<Option<(String, String)>> {
+        let searcher = self.reader.searcher();
+        let query = TermQuery::new(Term::from_field_text(self.fields.id, id), IndexRecordOption::Basic);
+        let Some((_, address)) = searcher.search(&query, &TopDocs::with_limit(1))?.into_iter().next() else {
+            return Ok(None);
+        };
+
+        let stored = searcher.doc(address)?;
+        let text = |field| stored.get_first(field).and_then(|v| v.as_text()).unwrap_or_default().to_string();
+        Ok(Some((text(self.fields.title), text(self.fields.content))))
+    }
+
+    /// A spell checker over the indexed vocabulary
+    pub fn spell_checker(&self) -> Result<SpellChecker> {
+        SpellChecker::from_index(&self.reader.searcher(), self.fields.words)
+    }
+
+    /// Number of documents containing both words
+    pub fn pair_frequency(&self, first: &str, second: &str) -> Result<u64> {
+        let term_query = |word: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(self.fields.words, word),
+                IndexRecordOption::Basic,
+            ))
+        };
+        let both = BooleanQuery::intersection(vec![term_query(first), term_query(second)]);
+
+        Ok(self.reader.searcher().search(&both, &Count)? as u64)
+    }
+}
+
+fn plain_terms(query: &str) -> String {
+    query
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_matches_inflected_forms() {
+        let index = TextIndex::in_memory().unwrap();
+        index.add("de", "Mietvertrag", "Die Häuser am See werden vermietet", "", Some(Language::German)).unwrap();
+        index.add("en", "Shoes", "Running shoes for trail runners", "", Some(Language::English)).unwrap();
+        index.add("fr", "Rapport", "Les documents annuels", "", Some(Language::French)).unwrap();
+        index.commit().unwrap();
+
+        let ids = |hits: Vec<TextHit>| hits.into_iter().map(|h| h.id).collect::<Vec<_>>();
+        assert_eq!(index.stored_text("en").unwrap().unwrap().0, "Shoes");
+
+        assert_eq!(ids(index.search("Haus", Some(Language::German), 10).unwrap()), vec!["de"]);
+        assert_eq!(ids(index.search("runs", None, 10).unwrap()), vec!["en"]);
+        assert_eq!(ids(index.search("document", None, 10).unwrap()), vec!["fr"]);
+
+        index.delete("fr").unwrap();
+        index.commit().unwrap();
+        assert!(index.search("document", None, 10).unwrap().is_empty());
+
+        // The vocabulary keeps words as written, not their stems
+        let checker = index.spell_checker().unwrap();
+        assert_eq!(checker.correct("runing shoes"), "running shoes");
+        assert_eq!(index.pair_frequency("running", "shoes").unwrap(), 1);
+
+        assert!(index.is_valid_query("\"trail shoes\"^0.50 runs"));
+        assert!(!index.is_valid_query("\"trail shoes"));
+
+        let scores = index.field_scores("shoes", None, 10).unwrap();
+        let (title, content) = scores["en"];
+        assert!(title > 0.0 && content > 0.0);
+        assert_eq!(index.field_scores("trail", None, 10).unwrap()["en"].0, 0.0);
+    }
+
+    #[test]
+    fn test_search_cjk_text() {
+        let index = TextIndex::in_memory().unwrap();
+        index.add("ja", "会議", "東京大学で会議があります", "", Some(Language::Japanese)).unwrap();
+        index.add("en", "Travel", "Flights from 京都 to Osaka", "", Some(Language::English)).unwrap();
+        index.commit().unwrap();
+
+        let ids = |query: &str| {
+            let mut ids: Vec<_> = index
+                .search(query, Language::detect_query(query), 10)
+                .unwrap()
+                .into_iter()
+                .map(|h| h.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(ids("大学"), vec!["ja"]);
+        assert_eq!(ids("京都"), vec!["en"]);
+        assert!(ids("京大阪").is_empty());
+    }
+
+    #[test]
+    fn test_search_code_blocks() {
+        let index = TextIndex::in_memory().unwrap();
+        index.add("md", "Building", "Compile the project first", "cargo build --release", Some(Language::English)).unwrap();
+        index.commit().unwrap();
+
+        let hits = index.search("release", None, 10).unwrap();
+        assert_eq!(hits.into_iter().map(|h| h.id).collect::<Vec<_>>(), vec!["md"]);
+
+        // Code stays out of the spelling vocabulary
+        assert_eq!(index.pair_frequency("cargo", "build").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_changes_commit_in_batches() {
+        let index = TextIndex::in_memory().unwrap().with_commit_batch(2);
+
+        index.add("a", "First", "batched words", "", None).unwrap();
+        assert!(index.search("batched", None, 10).unwrap().is_empty());
+
+        index.add("b", "Second", "batched words", "", None).unwrap();
+        assert_eq!(index.search("batched", None, 10).unwrap().len(), 2);
+
+        index.delete("a").unwrap();
+        assert_eq!(index.search("batched", None, 10).unwrap().len(), 2);
+        index.commit().unwrap();
+        assert_eq!(index.search("batched", None, 10).unwrap().len(), 1);
+    }
+}
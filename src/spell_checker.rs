@@ -1,12 +1,393 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use tantivy::schema::Field;
+use tantivy::Searcher;
+
+const DEFAULT_MAX_DISTANCE: usize = 2;
+
+/// Deletes are generated from this many leading characters only, which
+/// keeps the dictionary small at little cost in recall (as in SymSpell)
+const PREFIX_LENGTH: usize = 7;
+
+/// Candidates kept per word when ranking multi-word corrections
+const MAX_CANDIDATES: usize = 5;
+
+/// Score lost per edit; a closer word needs far fewer occurrences to win
+const DISTANCE_PENALTY: f64 = 3.0;
+
+/// Weight of how often neighbouring corrections appear together
+const CONTEXT_WEIGHT: f64 = 1.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub term: String,
+    pub distance: usize,
+    pub frequency: u64,
+}
+
+/// Spelling corrector over a corpus vocabulary, using a symmetric delete
+/// dictionary: every word is stored under the strings obtained by deleting
+/// up to `max_distance` characters, so lookups never enumerate inserts or
+/// substitutions.
 #[derive(Clone)]
-pub struct SpellChecker;
+pub struct SpellChecker {
+    words: HashMap<String, u64>,
+    deletes: HashMap<String, Vec<String>>,
+    max_distance: usize,
+}
 
 impl SpellChecker {
     pub fn new() -> Self {
-        SpellChecker
+        SpellChecker {
+            words: HashMap::new(),
+            deletes: HashMap::new(),
+            max_distance: DEFAULT_MAX_DISTANCE,
+        }
+    }
+
+    /// Only takes effect for words added afterwards
+    pub fn with_max_distance(mut self, max_distance: usize) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Vocabulary from an unstemmed index field, weighted by document frequency
+    pub fn from_index(searcher: &Searcher, field: Field) -> Result<Self> {
+        let mut frequencies: HashMap<String, u64> = HashMap::new();
+
+        for segment in searcher.segment_readers() {
+            let inverted_index = segment.inverted_index(field)?;
+            let mut terms = inverted_index.terms().stream()?;
+            while terms.advance() {
+                if let Ok(word) = std::str::from_utf8(terms.key()) {
+                    *frequencies.entry(word.to_string()).or_default() += u64::from(terms.value().doc_freq);
+                }
+            }
+        }
+
+        let mut checker = Self::new();
+        for (word, frequency) in frequencies {
+            checker.add_word(&word, frequency);
+        }
+        Ok(checker)
+    }
+
+    pub fn add_word(&mut self, word: &str, frequency: u64) {
+        let word = word.to_lowercase();
+        if !is_correctable(&word) {
+            return;
+        }
+
+        match self.words.get_mut(&word) {
+            Some(count) => *count += frequency,
+            None => {
+                for delete in deletes(&word, self.max_distance) {
+                    self.deletes.entry(delete).or_default().push(word.clone());
+                }
+                self.words.insert(word, frequency);
+            }
+        }
+    }
+
+    /// Count every word of `text` once, as one more document would
+    pub fn add_text(&mut self, text: &str) {
+        for word in distinct_words(text) {
+            self.add_word(&word, 1);
+        }
+    }
+
+    /// Take back `frequency` occurrences of a word, forgetting it once none are left
+    pub fn remove_word(&mut self, word: &str, frequency: u64) {
+        let word = word.to_lowercase();
+        let Some(count) = self.words.get_mut(&word) else {
+            return;
+        };
+        if *count > frequency {
+            *count -= frequency;
+            return;
+        }
+
+        self.words.remove(&word);
+        for delete in deletes(&word, self.max_distance) {
+            if let Some(terms) = self.deletes.get_mut(&delete) {
+                terms.retain(|term| *term != word);
+                if terms.is_empty() {
+                    self.deletes.remove(&delete);
+                }
+            }
+        }
+    }
+
+    /// Undo `add_text` for a document that was deleted
+    pub fn remove_text(&mut self, text: &str) {
+        for word in distinct_words(text) {
+            self.remove_word(&word, 1);
+        }
+    }
+
+    /// Every known word with the number of documents it occurs in
+    pub fn frequencies(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.words.iter().map(|(word, frequency)| (word.as_str(), *frequency))
+    }
+
+    /// Up to `limit` words, most frequent first
+    pub fn most_frequent(&self, limit: usize) -> Vec<String> {
+        let mut words: Vec<(&String, &u64)> = self.words.iter().collect();
+        words.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        words.into_iter().take(limit).map(|(word, _)| word.clone()).collect()
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains_key(&word.to_lowercase())
+    }
+
+    /// Known words within `max_distance` edits, closest and then most
+    /// frequent first. A known word is its own only suggestion.
+    pub fn lookup(&self, word: &str) -> Vec<Suggestion> {
+        let word = word.to_lowercase();
+        if let Some(&frequency) = self.words.get(&word) {
+            return vec![Suggestion { term: word, distance: 0, frequency }];
+        }
+
+        let mut seen = HashSet::new();
+        let mut suggestions = Vec::new();
+
+        for delete in deletes(&word, self.max_distance) {
+            let direct = self.words.contains_key(&delete).then_some(&delete);
+            let terms = self.deletes.get(&delete).into_iter().flatten();
+
+            for term in direct.into_iter().chain(terms) {
+                if !seen.insert(term.clone()) {
+                    continue;
+                }
+                let distance = edit_distance(&word, term);
+                if distance <= self.max_distance {
+                    suggestions.push(Suggestion {
+                        term: term.clone(),
+                        distance,
+                        frequency: self.words[term],
+                    });
+                }
+            }
+        }
+
+        suggestions.sort_by(|a, b| {
+            a.distance
+                .cmp(&b.distance)
+                .then(b.frequency.cmp(&a.frequency))
+                .then(a.term.cmp(&b.term))
+        });
+        suggestions
     }
 
     pub fn correct(&self, text: &str) -> String {
-        text.to_string()
+        self.correct_with(text, |_, _| 0)
+    }
+
+    /// Correct each unknown word of `text`, choosing the combination of
+    /// candidates that best fits together. `pair_frequency` says how often
+    /// two words occur in the same document.
+    pub fn correct_with(&self, text: &str, pair_frequency: impl Fn(&str, &str) -> u64) -> String {
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        if tokens.is_empty() {
+            return text.to_string();
+        }
+
+        // Words that are known, or that are not plain words at all, stay as they are
+        let candidates: Vec<Vec<(String, f64)>> = tokens
+            .iter()
+            .map(|token| {
+                let word = token.to_lowercase();
+                if !is_correctable(&word) || self.words.contains_key(&word) {
+                    return vec![(token.to_string(), 0.0)];
+                }
+
+                let suggestions = self.lookup(&word);
+                if suggestions.is_empty() {
+                    return vec![(token.to_string(), 0.0)];
+                }
+                suggestions
+                    .into_iter()
+                    .take(MAX_CANDIDATES)
+                    .map(|s| (s.term, ((s.frequency + 1) as f64).ln() - DISTANCE_PENALTY * s.distance as f64))
+                    .collect()
+            })
+            .collect();
+
+        // Best path through the candidates, scoring adjacent pairs
+        let mut scores: Vec<f64> = candidates[0].iter().map(|(_, score)| *score).collect();
+        let mut back: Vec<Vec<usize>> = vec![vec![0; candidates[0].len()]];
+
+        for position in 1..candidates.len() {
+            let previous = &candidates[position - 1];
+            let mut next_scores = Vec::with_capacity(candidates[position].len());
+            let mut next_back = Vec::with_capacity(candidates[position].len());
+
+            for (word, score) in &candidates[position] {
+                let (best, best_score) = previous
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (prev, _))| {
+                        let frequency = pair_frequency(&prev.to_lowercase(), &word.to_lowercase());
+                        let context = ((frequency + 1) as f64).ln();
+                        (i, scores[i] + CONTEXT_WEIGHT * context)
+                    })
+                    .fold((0, f64::NEG_INFINITY), |best, item| if item.1 > best.1 { item } else { best });
+
+                next_scores.push(best_score + score);
+                next_back.push(best);
+            }
+
+            scores = next_scores;
+            back.push(next_back);
+        }
+
+        let mut index = scores
+            .iter()
+            .enumerate()
+            .fold((0, f64::NEG_INFINITY), |best, (i, s)| if *s > best.1 { (i, *s) } else { best })
+            .0;
+
+        let mut words = vec![String::new(); candidates.len()];
+        for position in (0..candidates.len()).rev() {
+            words[position] = candidates[position][index].0.clone();
+            index = back[position][index];
+        }
+
+        words.join(" ")
+    }
+}
+
+impl Default for SpellChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The lowercase words of `text`, each once
+pub fn distinct_words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Only alphabetic words are corrected; numbers, codes, query syntax and
+/// CJK text, which has no spaces to find words by, are left alone
+fn is_correctable(word: &str) -> bool {
+    word.chars().count() > 1
+        && word.chars().all(|c| c.is_alphabetic() && !crate::search::cjk::is_cjk(c))
+}
+
+/// Every string reachable by deleting up to `max_distance` characters from
+/// the word's prefix, the prefix itself included
+fn deletes(word: &str, max_distance: usize) -> HashSet<String> {
+    let prefix: String = word.chars().take(PREFIX_LENGTH).collect();
+    let mut all = HashSet::from([prefix.clone()]);
+    let mut frontier = vec![prefix];
+
+    for _ in 0..max_distance {
+        let mut next = Vec::new();
+        for current in &frontier {
+            let chars: Vec<char> = current.chars().collect();
+            for skip in 0..chars.len() {
+                let delete: String = chars
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != skip)
+                    .map(|(_, c)| c)
+                    .collect();
+                if all.insert(delete.clone()) {
+                    next.push(delete);
+                }
+            }
+        }
+        frontier = next;
+    }
+
+    all
+}
+
+/// Damerau-Levenshtein distance, counting adjacent transpositions as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for j in 0..=b.len() {
+        rows[0][j] = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checker() -> SpellChecker {
+        let mut checker = SpellChecker::new();
+        checker.add_word("search", 120);
+        checker.add_word("engine", 80);
+        checker.add_word("engines", 5);
+        checker.add_word("machine", 60);
+        checker.add_word("learning", 60);
+        checker.add_word("leaning", 90);
+        checker
+    }
+
+    #[test]
+    fn test_lookup() {
+        let checker = checker();
+
+        assert_eq!(checker.lookup("serach")[0].term, "search");
+        assert_eq!(checker.lookup("engin")[0].term, "engine");
+        assert_eq!(checker.lookup("search")[0].distance, 0);
+        assert!(checker.lookup("xyzzy").is_empty());
+        assert_eq!(edit_distance("serach", "search"), 1);
+    }
+
+    #[test]
+    fn test_correct_uses_context() {
+        let checker = checker();
+
+        assert_eq!(checker.correct("serach engin"), "search engine");
+        // Both are one edit away; alone the more frequent word wins
+        assert_eq!(checker.correct("lerning"), "leaning");
+
+        let together = |a: &str, b: &str| if (a, b) == ("machine", "learning") { 50 } else { 0 };
+        assert_eq!(checker.correct_with("machine lerning", together), "machine learning");
+        assert_eq!(checker.correct_with("v2 api", together), "v2 api");
+    }
+
+    #[test]
+    fn test_removed_words_are_not_suggested() {
+        let mut checker = SpellChecker::new();
+        checker.add_text("Quarterly report");
+        checker.add_text("Annual report");
+
+        checker.remove_text("Annual report");
+        assert!(checker.contains("report"));
+        assert!(!checker.contains("annual"));
+        assert!(checker.lookup("anual").is_empty());
+
+        checker.remove_text("Quarterly report");
+        assert_eq!(checker.correct("reprot"), "reprot");
     }
-}
\ No newline at end of file
+}
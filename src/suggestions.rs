@@ -1,28 +1,119 @@
-use std::collections::HashSet;
+use crate::spell_checker::distinct_words;
+use crate::trie::{Completion, Trie};
 
+const DEFAULT_LIMIT: usize = 5;
+
+/// A successful search counts like this many documents containing the
+/// query, so popular queries outrank rare vocabulary
+const QUERY_WEIGHT: u64 = 10;
+
+/// Type-ahead completions from document words and past queries
 pub struct Suggestions {
-    history: HashSet<String>,
+    terms: Trie,
+    queries: Trie,  // Replaced whenever popular searches are reloaded
 }
 
 impl Suggestions {
     pub fn new() -> Self {
         Self {
-            history: HashSet::new(),
+            terms: Trie::new(),
+            queries: Trie::new(),
+        }
+    }
+
+    /// A word and the number of documents it occurs in
+    pub fn add_term(&mut self, term: &str, frequency: u64) {
+        self.terms.insert_weighted(&normalize(term), frequency);
+    }
+
+    /// Count every word of a newly indexed document
+    pub fn add_text(&mut self, text: &str) {
+        for word in distinct_words(text) {
+            self.terms.insert_weighted(&word, 1);
         }
     }
 
     pub fn add_to_history(&mut self, query: String) {
-        self.history.insert(query);
+        let query = normalize(&query);
+        if !query.is_empty() {
+            self.queries.insert_weighted(&query, QUERY_WEIGHT);
+        }
+    }
+
+    /// Replace the past queries with these, weighted by their recency
+    /// weighted search counts
+    pub fn set_popular_queries(&mut self, popular: &[(String, f64)]) {
+        let mut queries = Trie::new();
+        for (query, popularity) in popular {
+            let query = normalize(query);
+            if !query.is_empty() {
+                queries.insert_weighted(&query, query_weight(*popularity));
+            }
+        }
+        self.queries = queries;
     }
 
     pub fn get_suggestions(&self, prefix: &str) -> Vec<String> {
-        self.history
-            .iter()
-            .filter(|q| q.starts_with(prefix))
-            .take(5)
-            .cloned()
+        self.suggest(prefix, DEFAULT_LIMIT)
+            .into_iter()
+            .map(|completion| completion.term)
             .collect()
     }
+
+    /// Completions of `prefix`, heaviest first. When nothing starts with
+    /// the prefix, it is taken to hold one typo.
+    pub fn suggest(&self, prefix: &str, limit: usize) -> Vec<Completion> {
+        let prefix = normalize(prefix);
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+
+        let completions = merge(self.queries.top_k(&prefix, limit), self.terms.top_k(&prefix, limit), limit);
+        if completions.is_empty() {
+            return merge(
+                self.queries.fuzzy_top_k(&prefix, limit),
+                self.terms.fuzzy_top_k(&prefix, limit),
+                limit,
+            );
+        }
+        completions
+    }
+}
+
+/// Trie weight of a query searched `popularity` times, counting older
+/// searches for less
+pub fn query_weight(popularity: f64) -> u64 {
+    ((popularity * QUERY_WEIGHT as f64).round() as u64).max(1)
+}
+
+/// Heaviest first; a query that is also a word keeps its larger weight
+fn merge(queries: Vec<Completion>, terms: Vec<Completion>, limit: usize) -> Vec<Completion> {
+    let mut merged: Vec<Completion> = Vec::with_capacity(queries.len() + terms.len());
+    for completion in queries.into_iter().chain(terms) {
+        match merged.iter_mut().find(|c| c.term == completion.term) {
+            Some(existing) => existing.weight = existing.weight.max(completion.weight),
+            None => merged.push(completion),
+        }
+    }
+    merged.sort_by(|a, b| b.weight.cmp(&a.weight).then(a.term.cmp(&b.term)));
+    merged.truncate(limit);
+    merged
+}
+
+impl Default for Suggestions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lowercase with single spaces, keeping a trailing space so "new " only
+/// completes to phrases
+fn normalize(text: &str) -> String {
+    let mut normalized = text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    if text.ends_with(char::is_whitespace) && !normalized.is_empty() {
+        normalized.push(' ');
+    }
+    normalized
 }
 
 #[cfg(test)]
@@ -41,4 +132,24 @@ mod tests {
         assert!(results.contains(&"test query".to_string()));
         assert!(results.contains(&"test another".to_string()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_popular_queries_and_typos() {
+        let mut suggestions = Suggestions::new();
+        suggestions.add_term("kubernetes", 12);
+        suggestions.add_term("kubelet", 3);
+        suggestions.add_to_history("kubelet logs".to_string());
+        suggestions.add_to_history("kubelet logs".to_string());
+
+        let terms = |prefix: &str| -> Vec<String> {
+            suggestions.suggest(prefix, 3).into_iter().map(|c| c.term).collect()
+        };
+        assert_eq!(terms("kube"), vec!["kubelet logs", "kubernetes", "kubelet"]);
+        assert_eq!(terms("Kubelet "), vec!["kubelet logs"]);
+        assert_eq!(terms("kuver")[0], "kubernetes");
+
+        // Reloaded popular searches replace the queries, not the words
+        suggestions.set_popular_queries(&[("kubectl apply".to_string(), 0.4)]);
+        assert_eq!(terms("kube"), vec!["kubernetes", "kubectl apply", "kubelet"]);
+    }
+}
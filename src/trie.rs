@@ -1,9 +1,20 @@
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{BinaryHeap, HashMap};
 
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Completion {
+    pub term: String,
+    pub weight: u64,
+}
+
+/// Prefix tree of weighted terms. Every node keeps the highest weight in
+/// its subtree, so the top completions are found without visiting the rest.
 #[derive(Clone)]
 pub struct Trie {
     children: HashMap<char, Trie>,
     is_end: bool,
+    weight: u64,
+    best: u64,
 }
 
 impl Trie {
@@ -11,18 +22,196 @@ impl Trie {
         Self {
             children: HashMap::new(),
             is_end: false,
+            weight: 0,
+            best: 0,
         }
     }
 
     pub fn insert(&mut self, word: &str) {
+        self.insert_weighted(word, 1);
+    }
+
+    /// Add `weight` to a term, inserting it if needed
+    pub fn insert_weighted(&mut self, word: &str, weight: u64) {
+        let chars: Vec<char> = word.chars().collect();
+        self.insert_chars(&chars, weight);
+    }
+
+    fn insert_chars(&mut self, chars: &[char], weight: u64) -> u64 {
+        let total = match chars.split_first() {
+            None => {
+                self.is_end = true;
+                self.weight += weight;
+                self.weight
+            }
+            Some((c, rest)) => self.children.entry(*c).or_insert_with(Trie::new).insert_chars(rest, weight),
+        };
+        self.best = self.best.max(total);
+        total
+    }
+
+    /// Every term starting with `query`, heaviest first
+    pub fn search(&self, query: &str) -> Vec<String> {
+        self.top_k(query, usize::MAX)
+            .into_iter()
+            .map(|completion| completion.term)
+            .collect()
+    }
+
+    pub fn top_k(&self, prefix: &str, k: usize) -> Vec<Completion> {
+        match self.node(prefix) {
+            Some(node) => node.best_completions(prefix.to_string(), k),
+            None => Vec::new(),
+        }
+    }
+
+    /// Like `top_k`, but the prefix may be one edit (insert, delete,
+    /// substitute or swap adjacent characters) away from the term's
+    pub fn fuzzy_top_k(&self, prefix: &str, k: usize) -> Vec<Completion> {
+        let chars: Vec<char> = prefix.chars().collect();
+        let mut starts = Vec::new();
+        self.fuzzy_nodes(&chars, 1, &mut String::new(), &mut starts);
+
+        let mut best: HashMap<String, u64> = HashMap::new();
+        for (path, node) in starts {
+            for completion in node.best_completions(path, k) {
+                best.insert(completion.term, completion.weight);
+            }
+        }
+
+        let mut completions: Vec<Completion> = best
+            .into_iter()
+            .map(|(term, weight)| Completion { term, weight })
+            .collect();
+        completions.sort_by(|a, b| b.weight.cmp(&a.weight).then(a.term.cmp(&b.term)));
+        completions.truncate(k);
+        completions
+    }
+
+    fn node(&self, prefix: &str) -> Option<&Trie> {
         let mut current = self;
-        for c in word.chars() {
-            current = current.children.entry(c).or_insert(Trie::new());
+        for c in prefix.chars() {
+            current = current.children.get(&c)?;
         }
-        current.is_end = true;
+        Some(current)
     }
 
-    pub fn search(&self, query: &str) -> Vec<String> {
-        vec![query.to_string()]
+    /// Nodes reachable by matching `prefix` with up to `edits` edits, with their paths
+    fn fuzzy_nodes<'a>(&'a self, prefix: &[char], edits: usize, path: &mut String, out: &mut Vec<(String, &'a Trie)>) {
+        let Some((&first, rest)) = prefix.split_first() else {
+            out.push((path.clone(), self));
+            return;
+        };
+
+        if let Some(child) = self.children.get(&first) {
+            path.push(first);
+            child.fuzzy_nodes(rest, edits, path, out);
+            path.pop();
+        }
+        if edits == 0 {
+            return;
+        }
+
+        // A character typed by mistake
+        self.fuzzy_nodes(rest, edits - 1, path, out);
+
+        for (&c, child) in &self.children {
+            path.push(c);
+            // A wrong character, or one left out
+            if c != first {
+                child.fuzzy_nodes(rest, edits - 1, path, out);
+            }
+            child.fuzzy_nodes(prefix, edits - 1, path, out);
+            path.pop();
+        }
+
+        // Two characters swapped
+        if let Some(&second) = rest.first() {
+            if let Some(node) = self.children.get(&second).and_then(|child| child.children.get(&first)) {
+                path.push(second);
+                path.push(first);
+                node.fuzzy_nodes(&rest[1..], edits - 1, path, out);
+                path.pop();
+                path.pop();
+            }
+        }
+    }
+
+    /// Up to `k` terms below this node, best first. Nodes are expanded in
+    /// order of their subtree's best weight, so this stops after the k-th term.
+    fn best_completions(&self, path: String, k: usize) -> Vec<Completion> {
+        let mut nodes = vec![(self, path)];
+        // (priority, is a finished term, index into nodes); terms win ties
+        let mut heap = BinaryHeap::from([(self.best, false, 0usize)]);
+        let mut completions = Vec::new();
+
+        while let Some((priority, is_term, index)) = heap.pop() {
+            if completions.len() >= k {
+                break;
+            }
+            let (node, path) = &nodes[index];
+
+            if is_term {
+                completions.push(Completion { term: path.clone(), weight: priority });
+                continue;
+            }
+
+            let node = *node;
+            let path = path.clone();
+            if node.is_end {
+                heap.push((node.weight, true, index));
+            }
+            for (c, child) in &node.children {
+                let mut child_path = path.clone();
+                child_path.push(*c);
+                nodes.push((child, child_path));
+                heap.push((child.best, false, nodes.len() - 1));
+            }
+        }
+
+        completions
+    }
+}
+
+impl Default for Trie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trie() -> Trie {
+        let mut trie = Trie::new();
+        trie.insert_weighted("kubernetes", 40);
+        trie.insert_weighted("kubectl", 25);
+        trie.insert_weighted("kube proxy", 5);
+        trie.insert_weighted("kafka", 30);
+        trie
+    }
+
+    #[test]
+    fn test_top_k_by_weight() {
+        let trie = trie();
+
+        let terms: Vec<String> = trie.top_k("kub", 2).into_iter().map(|c| c.term).collect();
+        assert_eq!(terms, vec!["kubernetes", "kubectl"]);
+        assert_eq!(trie.search("kube").len(), 3);
+        assert!(trie.top_k("x", 5).is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy_prefix() {
+        let trie = trie();
+
+        let terms = |prefix: &str| -> Vec<String> {
+            trie.fuzzy_top_k(prefix, 3).into_iter().map(|c| c.term).collect()
+        };
+        assert_eq!(terms("kbu"), vec!["kubernetes", "kubectl", "kube proxy"]);
+        assert_eq!(terms("kafak"), vec!["kafka"]);
+        assert_eq!(terms("kubk")[0], "kubernetes");
+        assert!(terms("zzz").is_empty());
     }
-}
\ No newline at end of file
+}
@@ -1,15 +1,27 @@
 use sha2::{Sha256, Digest};
-use chrono::{DateTime, Utc, TimeZone};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc, TimeZone};
 use uuid::Uuid;
 use anyhow::Result;
 
 /// Calculate SHA-256 hash of content
 pub fn calculate_hash(content: &str) -> String {
+    hash_bytes(content.as_bytes())
+}
+
+/// Calculate SHA-256 hash of raw bytes
+pub fn hash_bytes(content: &[u8]) -> String {
     let mut hasher = Sha256::new();
-    hasher.update(content.as_bytes());
+    hasher.update(content);
     format!("{:x}", hasher.finalize())
 }
 
+/// Delay before retry number `attempts`: `base_secs` doubled per earlier
+/// attempt, capped at `max_secs`
+pub fn exponential_backoff(base_secs: u64, max_secs: u64, attempts: u32) -> std::time::Duration {
+    let factor = 2u64.saturating_pow(attempts.saturating_sub(1));
+    std::time::Duration::from_secs(base_secs.saturating_mul(factor).min(max_secs))
+}
+
 /// Format date to RFC3339
 pub fn format_date<Tz: TimeZone>(date: DateTime<Tz>) -> String 
 where
@@ -18,6 +30,26 @@ where
     date.to_rfc3339()
 }
 
+/// Parse a loosely formatted date (RFC 3339, RFC 2822 or plain `YYYY-MM-DD`)
+pub fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_rfc2822(value))
+        .map(|d| d.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .or_else(|| {
+                    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                        .ok()
+                        .and_then(|d| d.and_hms_opt(0, 0, 0))
+                })
+                .map(|d| Utc.from_utc_datetime(&d))
+        })
+}
+
 /// Truncate text to specified length with ellipsis
 pub fn truncate_text(text: &str, max_length: usize) -> String {
     if text.len() <= max_length {
@@ -72,12 +104,26 @@ pub fn is_valid_file_type(filename: &str, allowed_types: &[String]) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_exponential_backoff() {
+        let delays: Vec<u64> = (1..=6).map(|attempt| exponential_backoff(5, 60, attempt).as_secs()).collect();
+        assert_eq!(delays, vec![5, 10, 20, 40, 60, 60]);
+        assert_eq!(exponential_backoff(5, 60, 200).as_secs(), 60);
+    }
+
     #[test]
     fn test_calculate_hash() {
         let hash = calculate_hash("test content");
         assert_eq!(hash.len(), 64); // SHA-256 hash length
     }
 
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("2024-03-01").unwrap().to_rfc3339(), "2024-03-01T00:00:00+00:00");
+        assert_eq!(parse_date("2024-03-01T12:30:00+02:00").unwrap().to_rfc3339(), "2024-03-01T10:30:00+00:00");
+        assert!(parse_date("next tuesday").is_none());
+    }
+
     #[test]
     fn test_truncate_text() {
         let text = "This is a long text that needs to be truncated";
@@ -2,7 +2,10 @@ pub mod helpers;
 
 pub use self::helpers::{
     calculate_hash,
+    hash_bytes,
+    exponential_backoff,
     format_date,
+    parse_date,
     truncate_text,
     sanitize_input,
     generate_id,
@@ -0,0 +1,335 @@
+use crate::config::WebhookConfig;
+use crate::utils::helpers::exponential_backoff;
+use crate::webhooks::{
+    is_public_address, sign, Webhook, WebhookDelivery, WebhookEvent, WebhookPayload, DELIVERY_HEADER, EVENT_HEADER,
+    SIGNATURE_HEADER, TIMESTAMP_HEADER,
+};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use futures::stream::{self, StreamExt};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Response bodies are kept in the delivery log up to this many bytes
+const MAX_LOGGED_RESPONSE: usize = 512;
+
+/// Stores webhook subscriptions and delivers events to them. Events are
+/// written to `webhook_deliveries` first, so nothing is lost when a
+/// receiver is down or the server restarts mid-delivery.
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    pool: PgPool,
+    client: reqwest::Client,
+    config: WebhookConfig,
+    notify: Arc<Notify>,
+}
+
+struct DueDelivery {
+    id: String,
+    event: String,
+    payload: serde_json::Value,
+    attempts: i32,
+    max_attempts: i32,
+    url: String,
+    secret: String,
+}
+
+impl WebhookDispatcher {
+    pub fn new(pool: PgPool, config: WebhookConfig) -> Result<Self> {
+        // Redirects are not followed, so a receiver cannot bounce deliveries
+        // to an internal host
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+
+        Ok(Self {
+            pool,
+            client,
+            config,
+            notify: Arc::new(Notify::new()),
+        })
+    }
+
+    /// Register an endpoint. A signing secret is generated when none is given.
+    /// Endpoints on loopback, private or link-local addresses are refused.
+    pub async fn subscribe(&self, url: &str, events: &[WebhookEvent], secret: Option<String>) -> Result<Webhook> {
+        let parsed = url::Url::parse(url).context("Invalid webhook URL")?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            anyhow::bail!("Webhook URLs must use http or https");
+        }
+        ensure_public_host(&parsed).await?;
+        if events.is_empty() {
+            anyhow::bail!("At least one event is required");
+        }
+
+        let secret = secret.unwrap_or_else(|| format!("whsec_{}", Uuid::new_v4().simple()));
+        let events: Vec<String> = events.iter().map(|e| e.as_str().to_string()).collect();
+
+        let webhook = sqlx::query_as!(
+            Webhook,
+            r#"
+            INSERT INTO webhooks (url, events, secret)
+            VALUES ($1, $2, $3)
+            RETURNING id::text AS "id!", url, events, secret, active, created_at AS "created_at!"
+            "#,
+            url,
+            &events,
+            secret
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(webhook)
+    }
+
+    pub async fn list(&self) -> Result<Vec<Webhook>> {
+        let webhooks = sqlx::query_as!(
+            Webhook,
+            r#"
+            SELECT id::text AS "id!", url, events, secret, active, created_at AS "created_at!"
+            FROM webhooks
+            ORDER BY created_at
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(webhooks)
+    }
+
+    /// Remove a subscription along with its delivery log
+    pub async fn unsubscribe(&self, id: &str) -> Result<bool> {
+        let deleted = sqlx::query!("DELETE FROM webhooks WHERE id = $1::uuid", id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        Ok(deleted > 0)
+    }
+
+    pub async fn deliveries(&self, webhook_id: &str, limit: i64, offset: i64) -> Result<Vec<WebhookDelivery>> {
+        let deliveries = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            SELECT id::text AS "id!", webhook_id::text AS "webhook_id!", event, payload, status,
+                   attempts, response_status, last_error, next_attempt_at, created_at, delivered_at
+            FROM webhook_deliveries
+            WHERE webhook_id = $1::uuid
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            webhook_id,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// Queue an event for every active subscriber. Failing to queue is
+    /// logged rather than returned so it never fails document processing.
+    pub async fn emit(&self, event: WebhookEvent, data: serde_json::Value) {
+        if let Err(e) = self.enqueue(event, data).await {
+            error!("Failed to queue {} webhooks: {}", event, e);
+        }
+    }
+
+    async fn enqueue(&self, event: WebhookEvent, data: serde_json::Value) -> Result<()> {
+        let payload = WebhookPayload {
+            id: Uuid::new_v4().to_string(),
+            event,
+            created_at: Utc::now(),
+            data,
+        };
+
+        let queued = sqlx::query!(
+            r#"
+            INSERT INTO webhook_deliveries (webhook_id, event, payload, max_attempts)
+            SELECT id, $1, $2, $3 FROM webhooks
+            WHERE active AND $1 = ANY(events)
+            "#,
+            event.as_str(),
+            serde_json::to_value(&payload)?,
+            self.config.max_attempts as i32
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        if queued > 0 {
+            self.notify.notify_one();
+        }
+        Ok(())
+    }
+
+    /// Deliver queued events until the task is dropped
+    pub fn start(&self) -> JoinHandle<()> {
+        let dispatcher = self.clone();
+        tokio::spawn(async move { dispatcher.run().await })
+    }
+
+    async fn run(&self) {
+        let poll = Duration::from_millis(self.config.poll_interval_ms);
+
+        loop {
+            let due = match self.claim().await {
+                Ok(due) => due,
+                Err(e) => {
+                    error!("Failed to claim webhook deliveries: {}", e);
+                    Vec::new()
+                }
+            };
+
+            if due.is_empty() {
+                let _ = tokio::time::timeout(poll, self.notify.notified()).await;
+                continue;
+            }
+
+            stream::iter(due)
+                .for_each_concurrent(self.config.concurrency.max(1), |delivery| async move {
+                    let id = delivery.id.clone();
+                    if let Err(e) = self.deliver(delivery).await {
+                        error!("Failed to record webhook delivery {}: {}", id, e);
+                    }
+                })
+                .await;
+        }
+    }
+
+    /// Due deliveries are leased by pushing `next_attempt_at` past the
+    /// request timeout; a dispatcher that dies mid-request just lets it lapse.
+    async fn claim(&self) -> Result<Vec<DueDelivery>> {
+        let lease = self.config.timeout_secs as f64 + 60.0;
+
+        let due = sqlx::query_as!(
+            DueDelivery,
+            r#"
+            UPDATE webhook_deliveries d
+            SET attempts = d.attempts + 1, next_attempt_at = NOW() + make_interval(secs => $1)
+            FROM webhooks w
+            WHERE w.id = d.webhook_id
+              AND d.id IN (
+                  SELECT id FROM webhook_deliveries
+                  WHERE status = 'pending' AND next_attempt_at <= NOW()
+                  ORDER BY next_attempt_at
+                  FOR UPDATE SKIP LOCKED
+                  LIMIT $2
+              )
+            RETURNING d.id::text AS "id!", d.event, d.payload, d.attempts, d.max_attempts, w.url, w.secret
+            "#,
+            lease,
+            self.config.concurrency.max(1) as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(due)
+    }
+
+    async fn deliver(&self, delivery: DueDelivery) -> Result<()> {
+        let body = serde_json::to_vec(&delivery.payload)?;
+        let timestamp = Utc::now().timestamp();
+
+        let response = self.client
+            .post(&delivery.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, &delivery.event)
+            .header(DELIVERY_HEADER, &delivery.id)
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, sign(&delivery.secret, timestamp, &body))
+            .body(body)
+            .send()
+            .await;
+
+        let (status, error) = match response {
+            Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None),
+            Ok(response) => {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                let excerpt: String = text.chars().take(MAX_LOGGED_RESPONSE).collect();
+                (Some(status.as_u16()), Some(format!("HTTP {}: {}", status, excerpt)))
+            }
+            Err(e) => (e.status().map(|s| s.as_u16()), Some(e.to_string())),
+        };
+
+        match error {
+            None => self.mark_delivered(&delivery.id, status).await,
+            Some(error) => {
+                warn!(
+                    "Webhook delivery {} to {} failed on attempt {}/{}: {}",
+                    delivery.id, delivery.url, delivery.attempts, delivery.max_attempts, error
+                );
+                self.mark_failed(&delivery, status, &error).await
+            }
+        }
+    }
+
+    async fn mark_delivered(&self, id: &str, status: Option<u16>) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'delivered', response_status = $2, last_error = NULL,
+                delivered_at = NOW(), next_attempt_at = NULL
+            WHERE id = $1::uuid
+            "#,
+            id,
+            status.map(i32::from)
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Schedule a retry, or give up once out of attempts
+    async fn mark_failed(&self, delivery: &DueDelivery, status: Option<u16>, error: &str) -> Result<()> {
+        let exhausted = delivery.attempts >= delivery.max_attempts;
+        let delay = exponential_backoff(
+            self.config.base_backoff_secs,
+            self.config.max_backoff_secs,
+            delivery.attempts.max(0) as u32,
+        );
+
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = CASE WHEN $4 THEN 'failed' ELSE 'pending' END,
+                response_status = $2, last_error = $3,
+                next_attempt_at = CASE WHEN $4 THEN NULL ELSE NOW() + make_interval(secs => $5) END
+            WHERE id = $1::uuid
+            "#,
+            delivery.id,
+            status.map(i32::from),
+            error,
+            exhausted,
+            delay.as_secs_f64()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Fail unless every address the URL's host resolves to is public
+async fn ensure_public_host(url: &url::Url) -> Result<()> {
+    let host = url.host_str().context("Webhook URL has no host")?;
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let addresses: Vec<_> = tokio::net::lookup_host((host.trim_start_matches('[').trim_end_matches(']'), port))
+        .await
+        .with_context(|| format!("Failed to resolve {}", host))?
+        .collect();
+    if addresses.is_empty() || addresses.iter().any(|address| !is_public_address(address.ip())) {
+        anyhow::bail!("Webhook URLs must point to a public address");
+    }
+    Ok(())
+}
@@ -0,0 +1,210 @@
+pub mod dispatcher;
+
+pub use self::dispatcher::WebhookDispatcher;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+
+/// Header carrying `sha256=<hex>` over `"{timestamp}.{body}"`
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    #[serde(rename = "document.processed")]
+    DocumentProcessed,  // Indexed and searchable
+    #[serde(rename = "document.failed")]
+    DocumentFailed,     // Gave up on an upload or source file
+    #[serde(rename = "document.updated")]
+    DocumentUpdated,
+    #[serde(rename = "document.deleted")]
+    DocumentDeleted,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 4] = [
+        WebhookEvent::DocumentProcessed,
+        WebhookEvent::DocumentFailed,
+        WebhookEvent::DocumentUpdated,
+        WebhookEvent::DocumentDeleted,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::DocumentProcessed => "document.processed",
+            WebhookEvent::DocumentFailed => "document.failed",
+            WebhookEvent::DocumentUpdated => "document.updated",
+            WebhookEvent::DocumentDeleted => "document.deleted",
+        }
+    }
+}
+
+impl fmt::Display for WebhookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|event| event.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown webhook event '{}'", s))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<String>,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One attempt history entry in the delivery log
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub webhook_id: String,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub status: String,  // pending, delivered or failed
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// The body posted to subscribers. `id` is shared by every delivery of the
+/// same event, so receivers can drop repeats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    pub id: String,
+    pub event: WebhookEvent,
+    pub created_at: DateTime<Utc>,
+    pub data: serde_json::Value,
+}
+
+/// `sha256=<hex>` HMAC of `"{timestamp}.{body}"`. The timestamp is signed
+/// too so receivers can reject replays.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={:x}", mac.finalize().into_bytes())
+}
+
+/// Constant-time check of a signature produced by [`sign`]
+pub fn verify(secret: &str, timestamp: i64, body: &[u8], signature: &str) -> bool {
+    let Some(hex) = signature.strip_prefix("sha256=") else {
+        return false;
+    };
+    let Some(expected) = decode_hex(hex) else {
+        return false;
+    };
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// Whether webhooks may be sent to `ip`. Loopback, private, link-local and
+/// other internal ranges are refused so subscriptions cannot reach hosts
+/// behind the server.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || (first & 0xfe00) == 0xfc00   // Unique local
+                    || (first & 0xffc0) == 0xfe80)  // Link-local
+            }
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || a == 0
+        || (a == 100 && (b & 0xc0) == 64))  // Carrier-grade NAT
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let body = br#"{"event":"document.processed"}"#;
+        let signature = sign("whsec_test", 1700000000, body);
+
+        assert_eq!(
+            signature,
+            "sha256=6b2259ac6702728677c828c808d5124fe6cfb92187f88fbd2a6b0560a894a825"
+        );
+        assert!(verify("whsec_test", 1700000000, body, &signature));
+        assert!(!verify("whsec_test", 1700000001, body, &signature));
+        assert!(!verify("other", 1700000000, body, &signature));
+    }
+
+    #[test]
+    fn test_internal_addresses_are_refused() {
+        for internal in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0", "::1", "fd00::1", "fe80::1", "::ffff:10.0.0.1"] {
+            assert!(!is_public_address(internal.parse().unwrap()), "{}", internal);
+        }
+        for public in ["93.184.216.34", "2606:2800:220:1::1"] {
+            assert!(is_public_address(public.parse().unwrap()), "{}", public);
+        }
+    }
+
+    #[test]
+    fn test_event_names() {
+        assert_eq!("document.deleted".parse::<WebhookEvent>().unwrap(), WebhookEvent::DocumentDeleted);
+        assert!("document.created".parse::<WebhookEvent>().is_err());
+        assert_eq!(
+            serde_json::to_string(&WebhookEvent::DocumentProcessed).unwrap(),
+            "\"document.processed\""
+        );
+    }
+}
@@ -16,7 +16,7 @@ async fn test_document_processing() {
     // Test PDF processing
     let pdf_content = include_bytes!("../test_data/test.pdf");
     let pdf_doc = DocumentUpload::Pdf {
-        base64_content: base64::encode(pdf_content),
+        content: FileContent(pdf_content.to_vec()),
         filename: "test.pdf".to_string(),
         metadata: None,
     };
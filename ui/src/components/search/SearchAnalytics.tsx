@@ -1,5 +1,5 @@
 import React from 'react';
-import { BarChart2, Clock, Hash, Zap } from 'lucide-react';
+import { BarChart2, Clock, Hash } from 'lucide-react';
 import {
   Card,
   CardHeader,
@@ -11,8 +11,6 @@ interface SearchAnalytics {
   execution_time_ms: number;
   total_results: number;
   max_score: number;
-  search_type: string;
-  vector_query: boolean;
   field_weights?: Record<string, number>;
 }
 
@@ -22,7 +20,7 @@ interface Props {
 
 const SearchAnalytics: React.FC<Props> = ({ analytics }) => {
   return (
-    <div className="grid grid-cols-1 md:grid-cols-2 lg:grid-cols-4 gap-4">
+    <div className="grid grid-cols-1 md:grid-cols-2 lg:grid-cols-3 gap-4">
       <Card>
         <CardHeader className="flex flex-row items-center justify-between space-y-0 pb-2">
           <CardTitle className="text-sm font-medium">
@@ -70,27 +68,8 @@ const SearchAnalytics: React.FC<Props> = ({ analytics }) => {
         </CardContent>
       </Card>
 
-      <Card>
-        <CardHeader className="flex flex-row items-center justify-between space-y-0 pb-2">
-          <CardTitle className="text-sm font-medium">
-            Search Type
-          </CardTitle>
-          <Zap className="h-4 w-4 text-muted-foreground" />
-        </CardHeader>
-        <CardContent>
-          <div className="text-2xl font-bold capitalize">
-            {analytics.vector_query ? 'Hybrid' : 'Text'}
-          </div>
-          <p className="text-xs text-muted-foreground">
-            {analytics.vector_query 
-              ? 'Vector + Text Search' 
-              : 'Text-only Search'}
-          </p>
-        </CardContent>
-      </Card>
-
       {analytics.field_weights && (
-        <Card className="md:col-span-2 lg:col-span-4">
+        <Card className="md:col-span-2 lg:col-span-3">
           <CardHeader>
             <CardTitle className="text-sm font-medium">Field Weights</CardTitle>
           </CardHeader>
@@ -1,5 +1,5 @@
 import React, { useState } from 'react';
-import { FileText, Tag, Star, ChevronDown, ChevronUp } from 'lucide-react';
+import { FileText, Star, ChevronDown, ChevronUp } from 'lucide-react';
 import {
   Card,
   CardHeader,
@@ -12,18 +12,15 @@ interface SearchResult {
   title: string;
   content: string;
   author?: string;
-  tags: string[];
   scores: {
     text_score: number;
     vector_score: number;
     final_score: number;
   };
-  highlights: string[];
   metadata: {
     source_type: string;
     word_count: number;
     created_at: string;
-    last_modified: string;
   };
 }
 
@@ -83,32 +80,6 @@ const SearchResults: React.FC<Props> = ({ results }) => {
           </CardHeader>
           <CardContent>
             <div className="space-y-4">
-              {/* Highlights */}
-              <div>
-                {result.highlights.map((highlight, index) => (
-                  <p
-                    key={index}
-                    className="text-sm text-gray-600 mb-1"
-                    dangerouslySetInnerHTML={{ __html: highlight }}
-                  />
-                ))}
-              </div>
-
-              {/* Tags */}
-              {result.tags.length > 0 && (
-                <div className="flex flex-wrap gap-2">
-                  {result.tags.map((tag, index) => (
-                    <span
-                      key={index}
-                      className="inline-flex items-center px-2 py-1 rounded-full text-xs bg-gray-100"
-                    >
-                      <Tag className="h-3 w-3 mr-1" />
-                      {tag}
-                    </span>
-                  ))}
-                </div>
-              )}
-
               {/* Expand/Collapse Button */}
               <button
                 onClick={() => toggleExpand(result.id)}
@@ -146,8 +117,6 @@ const SearchResults: React.FC<Props> = ({ results }) => {
                     <div>{result.metadata.word_count}</div>
                     <div className="text-gray-500">Created:</div>
                     <div>{new Date(result.metadata.created_at).toLocaleString()}</div>
-                    <div className="text-gray-500">Modified:</div>
-                    <div>{new Date(result.metadata.last_modified).toLocaleString()}</div>
                   </div>
                 </div>
               )}
@@ -7,7 +7,6 @@ export interface SearchResult {
     vector_score: number;
     final_score: number;
   };
-  highlights: string[];
   metadata: {
     source_type: string;
     author?: string;
@@ -20,7 +19,6 @@ export interface SearchResponse {
   query: {
     original: string;
     expanded: string;
-    vector_query: boolean;
   };
   results: SearchResult[];
   analytics: SearchAnalytics;
@@ -30,8 +28,6 @@ export interface SearchAnalytics {
   execution_time_ms: number;
   total_results: number;
   max_score: number;
-  search_type: string;
-  vector_query: boolean;
 }
 
 export interface ProcessingStatus {